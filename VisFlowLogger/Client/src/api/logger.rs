@@ -0,0 +1,62 @@
+use crate::internal::flusher::{self, Command};
+use crate::internal::operation::{Operation, OPERATION};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+pub struct VisFlowLoggerConfig {
+    /// URL of the server's `save_logs` endpoint.
+    pub url: String,
+    /// Number of buffered entries that triggers a flush.
+    pub batch_size: usize,
+    /// Maximum time an entry stays buffered before it is sent.
+    pub flush_interval: Duration,
+}
+
+impl Default for VisFlowLoggerConfig {
+    fn default() -> Self {
+        VisFlowLoggerConfig {
+            url: "http://127.0.0.1:8080/".to_string(),
+            batch_size: 500,
+            flush_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Handle to the background flusher. Cheap to clone.
+#[derive(Clone)]
+pub struct VisFlowLogger {
+    sender: mpsc::UnboundedSender<Command>,
+}
+
+impl VisFlowLogger {
+    /// Spawns the background flusher, so it must be called from within a tokio runtime.
+    pub fn new(config: VisFlowLoggerConfig) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(flusher::run(config, receiver));
+        VisFlowLogger { sender }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn from_sender(sender: mpsc::UnboundedSender<Command>) -> Self {
+        VisFlowLogger { sender }
+    }
+
+    /// Runs `fut` as a new operation. Every entry emitted inside it (on the same task) belongs
+    /// to that operation. Pending entries are flushed once `fut` completes.
+    pub async fn run<F: Future>(&self, operation_name: &str, fut: F) -> F::Output {
+        let operation = Arc::new(Operation::new(operation_name, self.sender.clone()));
+        let output = OPERATION.scope(operation, fut).await;
+        self.flush().await;
+        output
+    }
+
+    /// Sends every buffered entry to the server and waits for the request to finish.
+    pub async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        if self.sender.send(Command::Flush(done)).is_ok() {
+            let _ = wait.await;
+        }
+    }
+}
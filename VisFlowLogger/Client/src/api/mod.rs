@@ -0,0 +1,4 @@
+///publicly exposed API for others to interact
+pub mod logger;
+pub mod models;
+pub mod operation;
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Wire format of a single entry. Same shape as the server's persistence `VisFlowLogEntry`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VisFlowLogEntry {
+    pub operation_id: String,
+    pub block_name: String,
+    pub log_type: VisFlowLogEntryLogType,
+    pub log_value: Option<String>,
+    pub sequence: u32,
+    /// Task the entry was emitted from, set inside [`crate::in_current_operation`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    /// Span of the task that started `span_id`. None when it was the operation's own task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_span_id: Option<String>,
+    /// When the entry was emitted. The server measures block durations from it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VisFlowLogEntryLogType {
+    Log,
+    Start,
+    End,
+    Store,
+    ExternalCall,
+    ExternalCallStore,
+    Error,
+    Throw,
+    Branch,
+    LoopStart,
+    LoopIter,
+    LoopEnd,
+}
+
+/// Same shape as the server's `VisFlowLogPayLoad`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisFlowLogPayLoad {
+    pub operation_id: String,
+    pub operation_name: String,
+    pub logs: Vec<VisFlowLogEntry>,
+}
+
+/// Request body of the server's `save_logs` endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveLogsPayload {
+    pub operation: Vec<VisFlowLogPayLoad>,
+}
+
+impl Display for VisFlowLogEntryLogType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let type_str = match self {
+            VisFlowLogEntryLogType::Log => "LOG",
+            VisFlowLogEntryLogType::Start => "START",
+            VisFlowLogEntryLogType::End => "END",
+            VisFlowLogEntryLogType::Store => "STORE",
+            VisFlowLogEntryLogType::ExternalCall => "EXTERNAL_CALL",
+            VisFlowLogEntryLogType::ExternalCallStore => "EXTERNAL_CALL_STORE",
+            VisFlowLogEntryLogType::Error => "ERROR",
+            VisFlowLogEntryLogType::Throw => "THROW",
+            VisFlowLogEntryLogType::Branch => "BRANCH",
+            VisFlowLogEntryLogType::LoopStart => "LOOP_START",
+            VisFlowLogEntryLogType::LoopIter => "LOOP_ITER",
+            VisFlowLogEntryLogType::LoopEnd => "LOOP_END",
+        };
+        write!(f, "{}", type_str)
+    }
+}
+
+impl Display for VisFlowLogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "VisFlowLogEntry {{ operation_id: {}, block_name: {}, log_type: {}, log_value: {}, sequence: {} }}",
+            self.operation_id,
+            self.block_name,
+            self.log_type,
+            self.log_value.as_deref().unwrap_or("None"),
+            self.sequence
+        )
+    }
+}
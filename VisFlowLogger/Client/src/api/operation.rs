@@ -0,0 +1,164 @@
+use crate::api::models::VisFlowLogEntryLogType;
+use crate::internal::operation::{Operation, Span, OPERATION, SPAN};
+use std::future::Future;
+use std::sync::Arc;
+
+/*
+All functions here act on the operation of the current task (see `VisFlowLogger::run`).
+Outside an operation they do nothing.
+ */
+
+/// Emits END for the block when dropped, so early returns and `?` still close the block.
+#[must_use = "dropping the guard immediately ends the block"]
+pub struct BlockGuard {
+    block: Option<(Arc<Operation>, String)>,
+}
+
+impl BlockGuard {
+    /// Ends the block now instead of at the end of the scope.
+    pub fn end(self) {}
+
+    /// Ends the block with an error instead of END, marking it and the call to it as failed.
+    pub fn throw(mut self, error: impl Into<String>) {
+        if let Some((operation, block_name)) = self.block.take() {
+            operation.throw(&block_name, error.into());
+        }
+    }
+}
+
+impl Drop for BlockGuard {
+    fn drop(&mut self) {
+        if let Some((operation, block_name)) = self.block.take() {
+            operation.end(&block_name);
+        }
+    }
+}
+
+/// Emits LOOP_END for the loop when dropped, like [`BlockGuard`] does for blocks.
+#[must_use = "dropping the guard immediately ends the loop"]
+pub struct LoopGuard {
+    block: Option<(Arc<Operation>, String)>,
+}
+
+impl LoopGuard {
+    /// Marks the start of the next iteration. `label` may be empty.
+    pub fn iteration(&self, label: impl Into<String>) {
+        if let Some((operation, block_name)) = &self.block {
+            operation.emit(
+                block_name,
+                VisFlowLogEntryLogType::LoopIter,
+                Some(label.into()),
+            );
+        }
+    }
+
+    /// Ends the loop now instead of at the end of the scope.
+    pub fn end(self) {}
+}
+
+impl Drop for LoopGuard {
+    fn drop(&mut self) {
+        if let Some((operation, block_name)) = self.block.take() {
+            operation.emit(&block_name, VisFlowLogEntryLogType::LoopEnd, None);
+        }
+    }
+}
+
+/// Starts a block and returns a guard that ends it.
+pub fn start(block_name: &str) -> BlockGuard {
+    let block = with_operation(|operation| {
+        operation.start(block_name);
+        (operation.clone(), block_name.to_string())
+    });
+    BlockGuard { block }
+}
+
+/// Ends a block manually. Only needed when the guard returned by [`start`] was forgotten.
+pub fn end(block_name: &str) {
+    with_operation(|operation| operation.end(block_name));
+}
+
+pub fn log(block_name: &str, value: impl Into<String>) {
+    emit_value(block_name, VisFlowLogEntryLogType::Log, value.into());
+}
+
+/// Logs the decision the block took, e.g. `"retries < 3: yes"`.
+pub fn branch(block_name: &str, condition: impl Into<String>) {
+    emit_value(block_name, VisFlowLogEntryLogType::Branch, condition.into());
+}
+
+/// Starts a loop inside the block and returns a guard that ends it. Mark each iteration
+/// with [`LoopGuard::iteration`]. Blocks started in between are called by the loop.
+pub fn start_loop(block_name: &str, label: &str) -> LoopGuard {
+    let block = with_operation(|operation| {
+        operation.emit(
+            block_name,
+            VisFlowLogEntryLogType::LoopStart,
+            Some(label.to_string()),
+        );
+        (operation.clone(), block_name.to_string())
+    });
+    LoopGuard { block }
+}
+
+/// Logs an error the block recovers from. The block is marked as failed but keeps running.
+pub fn error(block_name: &str, value: impl Into<String>) {
+    emit_value(block_name, VisFlowLogEntryLogType::Error, value.into());
+}
+
+/// Stores the return value of the block that just ended.
+pub fn store(block_name: &str, value: impl Into<String>) {
+    emit_value(block_name, VisFlowLogEntryLogType::Store, value.into());
+}
+
+/// Stores a value for the block that called the one which just ended. Does nothing when the
+/// ended block was the outermost one, since there is no caller to store it.
+pub fn store_in_caller(value: impl Into<String>) {
+    with_operation(|operation| {
+        if let Some(caller) = operation.current_block() {
+            operation.emit(&caller, VisFlowLogEntryLogType::Store, Some(value.into()));
+        }
+    });
+}
+
+pub fn external_call(block_name: &str, value: impl Into<String>) {
+    emit_value(
+        block_name,
+        VisFlowLogEntryLogType::ExternalCall,
+        value.into(),
+    );
+}
+
+pub fn external_call_store(block_name: &str, value: impl Into<String>) {
+    emit_value(
+        block_name,
+        VisFlowLogEntryLogType::ExternalCallStore,
+        value.into(),
+    );
+}
+
+pub fn current_operation_id() -> Option<String> {
+    with_operation(|operation| operation.id.clone())
+}
+
+/// Carries the current operation into `fut`. Use it when handing work to `tokio::spawn`,
+/// which does not inherit task-local values. `fut` gets its own span, so its blocks don't mix
+/// with blocks of tasks running at the same time.
+pub fn in_current_operation<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+    let operation = OPERATION.try_with(|operation| operation.clone()).ok();
+    let span = Span::child();
+    async move {
+        match operation {
+            Some(operation) => OPERATION.scope(operation, SPAN.scope(span, fut)).await,
+            None => fut.await,
+        }
+    }
+}
+
+fn emit_value(block_name: &str, log_type: VisFlowLogEntryLogType, value: String) {
+    with_operation(|operation| operation.emit(block_name, log_type, Some(value)));
+}
+
+fn with_operation<R>(f: impl FnOnce(&Arc<Operation>) -> R) -> Option<R> {
+    OPERATION.try_with(f).ok()
+}
@@ -0,0 +1,70 @@
+use crate::api::logger::VisFlowLoggerConfig;
+use crate::api::models::{SaveLogsPayload, VisFlowLogEntry, VisFlowLogPayLoad};
+use tokio::sync::{mpsc, oneshot};
+
+pub enum Command {
+    Entry {
+        operation_name: String,
+        entry: VisFlowLogEntry,
+    },
+    Flush(oneshot::Sender<()>),
+}
+
+/// Buffers entries and sends them in batches until every sender is dropped.
+pub async fn run(config: VisFlowLoggerConfig, mut receiver: mpsc::UnboundedReceiver<Command>) {
+    let client = reqwest::Client::new();
+    let mut buffer: Vec<(String, VisFlowLogEntry)> = Vec::new();
+    let mut ticker = tokio::time::interval(config.flush_interval);
+    loop {
+        tokio::select! {
+            command = receiver.recv() => match command {
+                Some(Command::Entry { operation_name, entry }) => {
+                    buffer.push((operation_name, entry));
+                    if buffer.len() >= config.batch_size {
+                        send(&client, &config.url, &mut buffer).await;
+                    }
+                }
+                Some(Command::Flush(done)) => {
+                    send(&client, &config.url, &mut buffer).await;
+                    let _ = done.send(());
+                }
+                None => {
+                    send(&client, &config.url, &mut buffer).await;
+                    break;
+                }
+            },
+            _ = ticker.tick() => send(&client, &config.url, &mut buffer).await,
+        }
+    }
+}
+
+async fn send(client: &reqwest::Client, url: &str, buffer: &mut Vec<(String, VisFlowLogEntry)>) {
+    if buffer.is_empty() {
+        return;
+    }
+    let payload = into_payload(std::mem::take(buffer));
+    match client.post(url).json(&payload).send().await {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => eprintln!("VisFlowLogger server rejected logs: {}", response.status()),
+        Err(e) => eprintln!("Error sending logs to VisFlowLogger server: {}", e),
+    }
+}
+
+/// Groups entries by operation, keeping the order in which operations were first seen.
+pub fn into_payload(entries: Vec<(String, VisFlowLogEntry)>) -> SaveLogsPayload {
+    let mut operation: Vec<VisFlowLogPayLoad> = Vec::new();
+    for (operation_name, entry) in entries {
+        match operation
+            .iter_mut()
+            .find(|op| op.operation_id == entry.operation_id)
+        {
+            Some(op) => op.logs.push(entry),
+            None => operation.push(VisFlowLogPayLoad {
+                operation_id: entry.operation_id.clone(),
+                operation_name,
+                logs: vec![entry],
+            }),
+        }
+    }
+    SaveLogsPayload { operation }
+}
@@ -0,0 +1,4 @@
+pub mod flusher;
+pub mod operation;
+#[cfg(test)]
+mod unit_test;
@@ -0,0 +1,123 @@
+use crate::api::models::{VisFlowLogEntry, VisFlowLogEntryLogType};
+use crate::internal::flusher::Command;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
+
+tokio::task_local! {
+    pub static OPERATION: Arc<Operation>;
+    /// Set for tasks started with `in_current_operation`. The operation's own task has no span.
+    pub static SPAN: Span;
+}
+
+/// A task that runs concurrently with the rest of its operation. It has its own open blocks.
+#[derive(Clone)]
+pub struct Span {
+    pub id: String,
+    pub parent_id: Option<String>,
+}
+
+impl Span {
+    /// A new span started from the current task.
+    pub fn child() -> Span {
+        Span {
+            id: Uuid::new_v4().to_string(),
+            parent_id: current_span().map(|span| span.id),
+        }
+    }
+}
+
+pub fn current_span() -> Option<Span> {
+    SPAN.try_with(Span::clone).ok()
+}
+
+fn current_span_id() -> Option<String> {
+    current_span().map(|span| span.id)
+}
+
+/// State shared by every entry of one operation.
+pub struct Operation {
+    pub id: String,
+    pub name: String,
+    counter: AtomicU32,
+    /// Names of the blocks that are currently open per span, innermost last.
+    open_blocks: Mutex<HashMap<Option<String>, Vec<String>>>,
+    sender: UnboundedSender<Command>,
+}
+
+impl Operation {
+    pub fn new(name: &str, sender: UnboundedSender<Command>) -> Self {
+        Operation {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            counter: AtomicU32::new(0),
+            open_blocks: Mutex::new(HashMap::new()),
+            sender,
+        }
+    }
+
+    pub fn start(&self, block_name: &str) {
+        self.open_blocks
+            .lock()
+            .unwrap()
+            .entry(current_span_id())
+            .or_default()
+            .push(block_name.to_string());
+        self.emit(block_name, VisFlowLogEntryLogType::Start, None);
+    }
+
+    pub fn end(&self, block_name: &str) {
+        self.close(block_name, VisFlowLogEntryLogType::End, None);
+    }
+
+    /// Ends the block abnormally, the error propagates to its caller.
+    pub fn throw(&self, block_name: &str, error: String) {
+        self.close(block_name, VisFlowLogEntryLogType::Throw, Some(error));
+    }
+
+    fn close(&self, block_name: &str, log_type: VisFlowLogEntryLogType, value: Option<String>) {
+        let mut open_blocks = self.open_blocks.lock().unwrap();
+        let span_id = current_span_id();
+        if let Some(blocks) = open_blocks.get_mut(&span_id) {
+            if let Some(pos) = blocks.iter().rposition(|name| name == block_name) {
+                blocks.truncate(pos);
+            }
+            if blocks.is_empty() {
+                open_blocks.remove(&span_id);
+            }
+        }
+        drop(open_blocks);
+        self.emit(block_name, log_type, value);
+    }
+
+    /// Innermost block of the current span that is still open.
+    pub fn current_block(&self) -> Option<String> {
+        self.open_blocks
+            .lock()
+            .unwrap()
+            .get(&current_span_id())
+            .and_then(|blocks| blocks.last().cloned())
+    }
+
+    pub fn emit(&self, block_name: &str, log_type: VisFlowLogEntryLogType, value: Option<String>) {
+        let span = current_span();
+        let entry = VisFlowLogEntry {
+            operation_id: self.id.clone(),
+            block_name: block_name.to_string(),
+            log_type,
+            log_value: value,
+            sequence: self.counter.fetch_add(1, Ordering::SeqCst),
+            parent_span_id: span.as_ref().and_then(|span| span.parent_id.clone()),
+            span_id: span.map(|span| span.id),
+            timestamp: Some(Utc::now()),
+        };
+        //Flusher is gone only when the runtime shuts down. Nothing left to do with the entry.
+        let _ = self.sender.send(Command::Entry {
+            operation_name: self.name.clone(),
+            entry,
+        });
+    }
+}
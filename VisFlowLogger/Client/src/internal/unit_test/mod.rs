@@ -0,0 +1,293 @@
+use crate::api::logger::VisFlowLogger;
+use crate::api::models::{VisFlowLogEntry, VisFlowLogEntryLogType};
+use crate::internal::flusher::{into_payload, Command};
+use tokio::sync::mpsc;
+use vis_flow_logger_macros::vis_flow;
+
+fn drain(receiver: &mut mpsc::UnboundedReceiver<Command>) -> Vec<(String, VisFlowLogEntry)> {
+    let mut entries = Vec::new();
+    while let Ok(command) = receiver.try_recv() {
+        match command {
+            Command::Entry {
+                operation_name,
+                entry,
+            } => entries.push((operation_name, entry)),
+            Command::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+    entries
+}
+
+/// Collects entries until the first flush. Flush waits for an answer, so this runs as its own task.
+fn collect(
+    mut receiver: mpsc::UnboundedReceiver<Command>,
+) -> tokio::task::JoinHandle<Vec<(String, VisFlowLogEntry)>> {
+    tokio::spawn(async move {
+        let mut entries = Vec::new();
+        while let Some(command) = receiver.recv().await {
+            match command {
+                Command::Entry {
+                    operation_name,
+                    entry,
+                } => entries.push((operation_name, entry)),
+                Command::Flush(done) => {
+                    let _ = done.send(());
+                    break;
+                }
+            }
+        }
+        entries
+    })
+}
+
+fn summary(
+    entries: &[(String, VisFlowLogEntry)],
+) -> Vec<(&str, VisFlowLogEntryLogType, Option<&str>)> {
+    entries
+        .iter()
+        .map(|(_, e)| (e.block_name.as_str(), e.log_type, e.log_value.as_deref()))
+        .collect()
+}
+
+#[tokio::test]
+async fn guard_emits_end_on_early_return() {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let logger = VisFlowLogger::from_sender(sender);
+
+    fn sum(a: i32, b: i32) -> i32 {
+        let _block = crate::start("sum");
+        if a == 0 {
+            return b;
+        }
+        crate::log("sum", format!("{} + {}", a, b));
+        a + b
+    }
+
+    let collector = collect(receiver);
+    logger
+        .run("adding", async {
+            let _main = crate::start("main");
+            let result = sum(0, 2);
+            crate::store("main", format!("sum = {}", result));
+        })
+        .await;
+    let entries = collector.await.unwrap();
+
+    let types: Vec<(&str, VisFlowLogEntryLogType)> = entries
+        .iter()
+        .map(|(_, e)| (e.block_name.as_str(), e.log_type))
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            ("main", VisFlowLogEntryLogType::Start),
+            ("sum", VisFlowLogEntryLogType::Start),
+            ("sum", VisFlowLogEntryLogType::End),
+            ("main", VisFlowLogEntryLogType::Store),
+            ("main", VisFlowLogEntryLogType::End),
+        ]
+    );
+    let sequences: Vec<u32> = entries.iter().map(|(_, e)| e.sequence).collect();
+    assert_eq!(sequences, vec![0, 1, 2, 3, 4]);
+    assert!(entries.iter().all(|(_, e)| e.timestamp.is_some()));
+    assert!(entries.iter().all(|(name, _)| name == "adding"));
+}
+
+#[tokio::test]
+async fn entries_outside_operation_are_ignored() {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let _logger = VisFlowLogger::from_sender(sender);
+    let _block = crate::start("main");
+    crate::log("main", "not part of any operation");
+    assert!(crate::current_operation_id().is_none());
+    assert!(drain(&mut receiver).is_empty());
+}
+
+#[test]
+fn payload_groups_entries_by_operation() {
+    let entry = |operation_id: &str, sequence| VisFlowLogEntry {
+        operation_id: operation_id.to_string(),
+        block_name: "main".to_string(),
+        log_type: VisFlowLogEntryLogType::Log,
+        log_value: Some("value".to_string()),
+        sequence,
+        span_id: None,
+        parent_span_id: None,
+        timestamp: None,
+    };
+    let payload = into_payload(vec![
+        ("a".to_string(), entry("1", 0)),
+        ("b".to_string(), entry("2", 0)),
+        ("a".to_string(), entry("1", 1)),
+    ]);
+    assert_eq!(payload.operation.len(), 2);
+    assert_eq!(payload.operation[0].operation_name, "a");
+    assert_eq!(payload.operation[0].logs.len(), 2);
+    assert_eq!(payload.operation[1].logs.len(), 1);
+}
+
+#[vis_flow(store)]
+fn parse(input: &str) -> Result<i32, String> {
+    if input.is_empty() {
+        return Err("empty".to_string());
+    }
+    input.parse::<i32>().map_err(|e| e.to_string())
+}
+
+#[vis_flow(name = "double", store)]
+async fn double_async(value: i32) -> i32 {
+    value * 2
+}
+
+#[vis_flow]
+async fn run_all() {
+    let _ = parse("");
+    let _ = parse("21");
+    double_async(21).await;
+}
+
+#[tokio::test]
+async fn vis_flow_macro_emits_balanced_blocks() {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let logger = VisFlowLogger::from_sender(sender);
+    let collector = collect(receiver);
+    logger.run("macro", run_all()).await;
+    let entries = collector.await.unwrap();
+
+    use VisFlowLogEntryLogType::*;
+    assert_eq!(
+        summary(&entries),
+        vec![
+            ("run_all", Start, None),
+            ("parse", Start, None),
+            ("parse", End, None),
+            ("run_all", Store, Some("parse = Err(\"empty\")")),
+            ("parse", Start, None),
+            ("parse", End, None),
+            ("run_all", Store, Some("parse = Ok(21)")),
+            ("double", Start, None),
+            ("double", End, None),
+            ("run_all", Store, Some("double = 42")),
+            ("run_all", End, None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn spawned_tasks_get_their_own_span() {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let logger = VisFlowLogger::from_sender(sender);
+    let collector = collect(receiver);
+    logger
+        .run("concurrent", async {
+            let _main = crate::start("main");
+            let task = |name: &'static str| {
+                tokio::spawn(crate::in_current_operation(async move {
+                    let _block = crate::start(name);
+                    crate::in_current_operation(async move {
+                        let _inner = crate::start("inner");
+                    })
+                    .await;
+                    crate::store_in_caller("done");
+                }))
+            };
+            let (a, b) = (task("a"), task("b"));
+            a.await.unwrap();
+            b.await.unwrap();
+        })
+        .await;
+    let entries = collector.await.unwrap();
+
+    let span_of = |block_name: &str| {
+        let (_, entry) = entries
+            .iter()
+            .find(|(_, e)| e.block_name == block_name)
+            .unwrap();
+        (entry.span_id.clone(), entry.parent_span_id.clone())
+    };
+    assert_eq!(span_of("main"), (None, None));
+    let (a, b) = (span_of("a"), span_of("b"));
+    assert!(a.0.is_some() && a.0 != b.0);
+    assert_eq!((a.1, b.1), (None, None));
+    //Each task stores into its own open block, not the one the other task opened last
+    let mut stored_in: Vec<&str> = entries
+        .iter()
+        .filter(|(_, e)| e.log_type == VisFlowLogEntryLogType::Store)
+        .map(|(_, e)| e.block_name.as_str())
+        .collect();
+    stored_in.sort();
+    assert_eq!(stored_in, vec!["a", "b"]);
+    let inner_parents: Vec<Option<String>> = entries
+        .iter()
+        .filter(|(_, e)| e.block_name == "inner" && e.log_type == VisFlowLogEntryLogType::Start)
+        .map(|(_, e)| e.parent_span_id.clone())
+        .collect();
+    assert!(inner_parents.contains(&a.0) && inner_parents.contains(&b.0));
+}
+
+#[tokio::test]
+async fn throw_ends_the_block_with_an_error() {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let logger = VisFlowLogger::from_sender(sender);
+    let collector = collect(receiver);
+    logger
+        .run("failing", async {
+            let _main = crate::start("main");
+            let load = crate::start("load");
+            load.throw("file not found");
+            crate::error("main", "falling back");
+        })
+        .await;
+    let entries = collector.await.unwrap();
+
+    use VisFlowLogEntryLogType::*;
+    assert_eq!(
+        summary(&entries),
+        vec![
+            ("main", Start, None),
+            ("load", Start, None),
+            ("load", Throw, Some("file not found")),
+            ("main", Error, Some("falling back")),
+            ("main", End, None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn loop_guard_marks_iterations_and_ends_the_loop() {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let logger = VisFlowLogger::from_sender(sender);
+    let collector = collect(receiver);
+    logger
+        .run("looping", async {
+            let _main = crate::start("main");
+            crate::branch("main", "has pages");
+            let pages = crate::start_loop("main", "pages");
+            for page in 1..=2 {
+                pages.iteration(page.to_string());
+                let _fetch = crate::start("fetch");
+            }
+        })
+        .await;
+    let entries = collector.await.unwrap();
+
+    use VisFlowLogEntryLogType::*;
+    assert_eq!(
+        summary(&entries),
+        vec![
+            ("main", Start, None),
+            ("main", Branch, Some("has pages")),
+            ("main", LoopStart, Some("pages")),
+            ("main", LoopIter, Some("1")),
+            ("fetch", Start, None),
+            ("fetch", End, None),
+            ("main", LoopIter, Some("2")),
+            ("fetch", Start, None),
+            ("fetch", End, None),
+            ("main", LoopEnd, None),
+            ("main", End, None),
+        ]
+    );
+}
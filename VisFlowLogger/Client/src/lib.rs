@@ -0,0 +1,21 @@
+//! Rust client for the VisFlowLogger server.
+//!
+//! Wrap a unit of work with [`VisFlowLogger::run`] and use [`start`], [`log`], [`store`],
+//! [`external_call`] and friends anywhere inside it. Entries are buffered and sent to the
+//! server's `save_logs` endpoint in batches. [`vis_flow`] does the START/END bookkeeping for a
+//! whole function.
+// Lets `#[vis_flow]` expand to `::vis_flow_logger::...` inside this crate's own tests.
+extern crate self as vis_flow_logger;
+
+pub mod api;
+mod internal;
+
+pub use api::logger::{VisFlowLogger, VisFlowLoggerConfig};
+pub use api::models::{
+    SaveLogsPayload, VisFlowLogEntry, VisFlowLogEntryLogType, VisFlowLogPayLoad,
+};
+pub use api::operation::{
+    branch, current_operation_id, end, error, external_call, external_call_store,
+    in_current_operation, log, start, start_loop, store, store_in_caller, BlockGuard, LoopGuard,
+};
+pub use vis_flow_logger_macros::vis_flow;
@@ -0,0 +1,79 @@
+//! Attribute macros for the `vis_flow_logger` crate. Use them through `vis_flow_logger::vis_flow`.
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::Parser;
+use syn::{parse_macro_input, ItemFn, LitStr, ReturnType, Type};
+
+/**
+Wraps a function so it emits START when called and END when it returns, including early
+`return`s and `?`. The function name is used as `block_name`. <br>
+Options :-
+1. `name = "..."` overrides the block name.
+2. `store` emits STORE with the `Debug` of the return value for the calling block.
+ */
+#[proc_macro_attribute]
+pub fn vis_flow(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut function = parse_macro_input!(item as ItemFn);
+    let mut block_name: Option<LitStr> = None;
+    let mut store = false;
+    let options = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            block_name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("store") {
+            store = true;
+            Ok(())
+        } else {
+            Err(meta.error("expected `name = \"...\"` or `store`"))
+        }
+    });
+    if let Err(e) = options.parse(attr) {
+        return e.to_compile_error().into();
+    }
+
+    let block_name = block_name
+        .map(|name| name.value())
+        .unwrap_or_else(|| function.sig.ident.to_string());
+    let returns_value = match &function.sig.output {
+        ReturnType::Default => false,
+        ReturnType::Type(_, ty) => !matches!(ty.as_ref(), Type::Tuple(t) if t.elems.is_empty()),
+    };
+    if store && !returns_value {
+        return syn::Error::new_spanned(
+            &function.sig,
+            "`store` needs a function that returns a value",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    // The body runs in its own closure/async block so `return` hands the value back to us.
+    let body = &function.block;
+    let call_body = if function.sig.asyncness.is_some() {
+        quote! { async move #body.await }
+    } else {
+        match &function.sig.output {
+            ReturnType::Type(_, ty) if !matches!(ty.as_ref(), Type::ImplTrait(_)) => {
+                quote! { (move || -> #ty #body)() }
+            }
+            _ => quote! { (move || #body)() },
+        }
+    };
+    let store_result = if store {
+        quote! {
+            ::vis_flow_logger::store_in_caller(format!("{} = {:?}", #block_name, __vis_flow_result));
+        }
+    } else {
+        quote! {}
+    };
+
+    function.block = syn::parse_quote! {{
+        let __vis_flow_result = {
+            let __vis_flow_guard = ::vis_flow_logger::start(#block_name);
+            #call_body
+        };
+        #store_result
+        __vis_flow_result
+    }};
+    quote!(#function).into()
+}
@@ -0,0 +1,219 @@
+"use strict";
+
+(function () {
+    const container = document.getElementById("diagram");
+    const svg = container.querySelector("svg");
+    const panel = document.getElementById("flow");
+    if (!svg) {
+        return;
+    }
+
+    // Pan and zoom move the view box over the whole diagram
+    const [, , width, height] = svg.getAttribute("viewBox").split(/\s+/).map(Number);
+    let view = { x: 0, y: 0, width: width, height: height };
+
+    function show() {
+        svg.setAttribute("viewBox", `${view.x} ${view.y} ${view.width} ${view.height}`);
+    }
+
+    // Diagram units per screen pixel, the diagram keeps its ratio inside the window
+    function scale() {
+        return Math.max(view.width / svg.clientWidth, view.height / svg.clientHeight);
+    }
+
+    function toDiagram(clientX, clientY) {
+        const point = svg.createSVGPoint();
+        point.x = clientX;
+        point.y = clientY;
+        return point.matrixTransform(svg.getScreenCTM().inverse());
+    }
+
+    function zoom(factor, center) {
+        const next = view.width * factor;
+        if (next < width / 50 || next > width * 20) {
+            return;
+        }
+        view = {
+            x: center.x - (center.x - view.x) * factor,
+            y: center.y - (center.y - view.y) * factor,
+            width: next,
+            height: view.height * factor,
+        };
+        show();
+    }
+
+    function zoomCenter(factor) {
+        zoom(factor, { x: view.x + view.width / 2, y: view.y + view.height / 2 });
+    }
+
+    function fit() {
+        view = { x: 0, y: 0, width: width, height: height };
+        show();
+    }
+
+    container.addEventListener("wheel", (event) => {
+        event.preventDefault();
+        zoom(Math.exp(event.deltaY * 0.0015), toDiagram(event.clientX, event.clientY));
+    }, { passive: false });
+
+    // A drag pans, and doesn't count as a click on what it started on
+    let drag = null;
+    container.addEventListener("pointerdown", (event) => {
+        drag = { x: event.clientX, y: event.clientY, moved: false };
+    });
+    container.addEventListener("pointermove", (event) => {
+        if (!drag) {
+            return;
+        }
+        const dx = event.clientX - drag.x;
+        const dy = event.clientY - drag.y;
+        if (!drag.moved && Math.hypot(dx, dy) < 4) {
+            return;
+        }
+        if (!drag.moved) {
+            drag.moved = true;
+            container.setPointerCapture(event.pointerId);
+            container.classList.add("panning");
+            panel.hidden = true;
+        }
+        view.x -= dx * scale();
+        view.y -= dy * scale();
+        drag.x = event.clientX;
+        drag.y = event.clientY;
+        show();
+    });
+    container.addEventListener("pointerup", (event) => {
+        if (container.hasPointerCapture(event.pointerId)) {
+            container.releasePointerCapture(event.pointerId);
+        }
+        container.classList.remove("panning");
+        // The click event comes after, it reads whether this was a drag
+        setTimeout(() => { drag = null; });
+    });
+
+    document.getElementById("zoom-in").addEventListener("click", () => zoomCenter(0.8));
+    document.getElementById("zoom-out").addEventListener("click", () => zoomCenter(1.25));
+    document.getElementById("fit").addEventListener("click", fit);
+    document.addEventListener("keydown", (event) => {
+        if (event.key === "+" || event.key === "=") {
+            zoomCenter(0.8);
+        } else if (event.key === "-") {
+            zoomCenter(1.25);
+        } else if (event.key === "0") {
+            fit();
+        }
+    });
+
+    // Collapsing a block hides the blocks it called, and theirs, with the links to them
+    const blocks = new Map();
+    for (const block of svg.querySelectorAll(".block")) {
+        blocks.set(block.getAttribute("data-block"), block);
+    }
+    const collapsed = new Set();
+
+    function callees(blockId) {
+        let count = 0;
+        for (const block of blocks.values()) {
+            if (block.getAttribute("data-caller") === blockId) {
+                count += 1 + callees(block.getAttribute("data-block"));
+            }
+        }
+        return count;
+    }
+
+    function isHidden(blockId) {
+        let caller = blocks.get(blockId)?.getAttribute("data-caller");
+        while (caller) {
+            if (collapsed.has(caller)) {
+                return true;
+            }
+            caller = blocks.get(caller)?.getAttribute("data-caller");
+        }
+        return false;
+    }
+
+    function badge(block, text) {
+        const title = block.querySelector(".title");
+        let label = title.querySelector(".badge");
+        if (!text) {
+            label?.remove();
+            return;
+        }
+        if (!label) {
+            const rect = title.querySelector("rect");
+            label = document.createElementNS("http://www.w3.org/2000/svg", "text");
+            label.setAttribute("class", "badge");
+            label.setAttribute("text-anchor", "end");
+            label.setAttribute("font-weight", "bold");
+            label.setAttribute("x", Number(rect.getAttribute("x")) + Number(rect.getAttribute("width")) - 8);
+            label.setAttribute("y", Number(rect.getAttribute("y")) + 18);
+            title.appendChild(label);
+        }
+        label.textContent = text;
+    }
+
+    function update() {
+        for (const [blockId, block] of blocks) {
+            block.classList.toggle("hidden", isHidden(blockId));
+            block.classList.toggle("collapsed", collapsed.has(blockId));
+            badge(block, collapsed.has(blockId) ? `+${callees(blockId)}` : "");
+        }
+        for (const call of svg.querySelectorAll(".call")) {
+            const hidden = collapsed.has(call.getAttribute("data-from"))
+                || isHidden(call.getAttribute("data-from"))
+                || isHidden(call.getAttribute("data-to"));
+            call.classList.toggle("hidden", hidden);
+        }
+    }
+
+    svg.addEventListener("click", (event) => {
+        if (drag?.moved) {
+            return;
+        }
+        const block = event.target.closest(".title")?.parentElement;
+        if (!block || !block.classList.contains("block")) {
+            return;
+        }
+        const blockId = block.getAttribute("data-block");
+        if (collapsed.has(blockId)) {
+            collapsed.delete(blockId);
+        } else if (callees(blockId) > 0) {
+            collapsed.add(blockId);
+        }
+        update();
+    });
+
+    document.getElementById("expand").addEventListener("click", () => {
+        collapsed.clear();
+        update();
+    });
+
+    // Hovering a flow shows its whole value, which the node only shows the start of
+    const fields = {
+        type: document.getElementById("flow-type"),
+        sequence: document.getElementById("flow-sequence"),
+        value: document.getElementById("flow-value"),
+    };
+    for (const flow of svg.querySelectorAll(".flow")) {
+        // The panel replaces the browser's own tooltip
+        flow.querySelector("title")?.remove();
+        flow.addEventListener("pointerenter", () => {
+            if (drag?.moved) {
+                return;
+            }
+            fields.type.textContent = flow.getAttribute("data-type");
+            fields.sequence.textContent = flow.getAttribute("data-sequence") ?? "-";
+            fields.value.textContent = flow.getAttribute("data-value") ?? "-";
+            panel.hidden = false;
+        });
+        flow.addEventListener("pointermove", (event) => {
+            const x = Math.min(event.clientX + 16, window.innerWidth - panel.offsetWidth - 8);
+            const y = Math.min(event.clientY + 16, window.innerHeight - panel.offsetHeight - 8);
+            panel.style.left = `${Math.max(x, 8)}px`;
+            panel.style.top = `${Math.max(y, 8)}px`;
+        });
+        flow.addEventListener("pointerleave", () => {
+            panel.hidden = true;
+        });
+    }
+})();
@@ -0,0 +1 @@
+ALTER TABLE logs ALTER COLUMN log_value TYPE TEXT;
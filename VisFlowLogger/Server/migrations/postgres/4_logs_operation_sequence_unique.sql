@@ -0,0 +1,6 @@
+-- Retried requests used to save every log again. Keep one row per (operation_id, sequence).
+DELETE FROM logs a USING logs b
+WHERE a.operation_id = b.operation_id AND a.sequence = b.sequence AND a.id > b.id;
+
+DROP INDEX IF EXISTS logs_operation_id_sequence_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS logs_operation_id_sequence_key ON logs (operation_id, sequence);
@@ -0,0 +1,3 @@
+-- Logs of concurrent tasks in one operation name the span they ran in and the span that started it
+ALTER TABLE logs ADD COLUMN IF NOT EXISTS span_id VARCHAR(100);
+ALTER TABLE logs ADD COLUMN IF NOT EXISTS parent_span_id VARCHAR(100);
@@ -0,0 +1,3 @@
+-- Time the client wrote the log, if it sent one, and time the server received it. Both in UTC.
+ALTER TABLE logs ADD COLUMN IF NOT EXISTS logged_at TIMESTAMP;
+ALTER TABLE logs ADD COLUMN IF NOT EXISTS received_at TIMESTAMP;
@@ -0,0 +1,2 @@
+-- Sequences are unsigned 32-bit numbers, which INT can't hold above 2147483647
+ALTER TABLE logs ALTER COLUMN sequence TYPE BIGINT;
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS logs (
+id TEXT PRIMARY KEY,
+operation_id TEXT NOT NULL,
+block_name TEXT NOT NULL,
+log_type TEXT NOT NULL,
+log_value TEXT,
+sequence INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS operations (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    created TIMESTAMP NOT NULL,
+    updated TIMESTAMP NOT NULL
+);
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS logs_operation_id_sequence_idx ON logs (operation_id, sequence);
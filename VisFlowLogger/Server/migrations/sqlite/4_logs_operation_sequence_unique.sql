@@ -0,0 +1,7 @@
+-- Retried requests used to save every log again. Keep one row per (operation_id, sequence).
+DELETE FROM logs WHERE rowid NOT IN (
+    SELECT MIN(rowid) FROM logs GROUP BY operation_id, sequence
+);
+
+DROP INDEX IF EXISTS logs_operation_id_sequence_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS logs_operation_id_sequence_key ON logs (operation_id, sequence);
@@ -0,0 +1,3 @@
+-- Logs of concurrent tasks in one operation name the span they ran in and the span that started it
+ALTER TABLE logs ADD COLUMN span_id TEXT;
+ALTER TABLE logs ADD COLUMN parent_span_id TEXT;
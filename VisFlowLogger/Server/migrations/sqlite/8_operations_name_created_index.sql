@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS operations_name_created_idx ON operations (name, created);
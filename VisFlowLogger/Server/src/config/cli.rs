@@ -0,0 +1,122 @@
+use crate::config::settings::{DatabaseBackend, Settings};
+use crate::services::diagram_generator::api::models::diagram_options::{Direction, Theme};
+use crate::services::diagram_generator::api::services::DiagramType;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(about = "Visual Flow Logger server")]
+pub struct Cli {
+    /// Path to a TOML config file
+    #[arg(long, env = "VFL_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+    #[command(flatten)]
+    pub overrides: Overrides,
+    /// Runs the server when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Manage the database schema
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MigrateAction {
+    /// List every migration and whether it was applied
+    Status,
+    /// Apply pending migrations
+    Up {
+        /// Only print the migrations that would be applied
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Settings that can be overridden by an environment variable or a CLI flag.
+#[derive(Args)]
+pub struct Overrides {
+    #[arg(long, env = "VFL_BIND_ADDRESS", global = true)]
+    pub bind_address: Option<String>,
+    #[arg(long, env = "VFL_WORKERS", global = true)]
+    pub workers: Option<usize>,
+    /// Maximum size of a request body in bytes
+    #[arg(long, env = "VFL_PAYLOAD_LIMIT", global = true)]
+    pub payload_limit: Option<usize>,
+    /// Maximum size of a single NDJSON line in bytes
+    #[arg(long, env = "VFL_NDJSON_MAX_LINE", global = true)]
+    pub ndjson_max_line: Option<usize>,
+    /// Most operations merged by an aggregate request
+    #[arg(long, env = "VFL_AGGREGATE_MAX_OPERATIONS", global = true)]
+    pub aggregate_max_operations: Option<usize>,
+    #[arg(long, env = "VFL_DATABASE_BACKEND", value_enum, global = true)]
+    pub database_backend: Option<DatabaseBackend>,
+    #[arg(long, env = "VFL_DATABASE_URL", global = true)]
+    pub database_url: Option<String>,
+    #[arg(long, env = "VFL_DATABASE_MAX_CONNECTIONS", global = true)]
+    pub database_max_connections: Option<u32>,
+    #[arg(long, env = "VFL_DATABASE_MIN_CONNECTIONS", global = true)]
+    pub database_min_connections: Option<u32>,
+    #[arg(
+        long,
+        env = "VFL_DATABASE_AUTO_MIGRATE",
+        value_name = "BOOL",
+        global = true
+    )]
+    pub database_auto_migrate: Option<bool>,
+    #[arg(long, env = "VFL_DEFAULT_DIAGRAM_TYPE", value_enum, global = true)]
+    pub default_diagram_type: Option<DiagramType>,
+    #[arg(long, env = "VFL_DIAGRAM_THEME", value_enum, global = true)]
+    pub diagram_theme: Option<Theme>,
+    #[arg(long, env = "VFL_DIAGRAM_DIRECTION", value_enum, global = true)]
+    pub diagram_direction: Option<Direction>,
+}
+
+impl Overrides {
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(bind_address) = &self.bind_address {
+            settings.server.bind_address = bind_address.clone();
+        }
+        if let Some(workers) = self.workers {
+            settings.server.workers = Some(workers);
+        }
+        if let Some(payload_limit) = self.payload_limit {
+            settings.server.payload_limit = payload_limit;
+        }
+        if let Some(ndjson_max_line) = self.ndjson_max_line {
+            settings.server.ndjson_max_line = ndjson_max_line;
+        }
+        if let Some(aggregate_max_operations) = self.aggregate_max_operations {
+            settings.server.aggregate_max_operations = aggregate_max_operations;
+        }
+        if let Some(backend) = self.database_backend {
+            settings.database.backend = backend;
+        }
+        if let Some(url) = &self.database_url {
+            settings.database.url = url.clone();
+        }
+        if let Some(max_connections) = self.database_max_connections {
+            settings.database.max_connections = max_connections;
+        }
+        if let Some(min_connections) = self.database_min_connections {
+            settings.database.min_connections = min_connections;
+        }
+        if let Some(auto_migrate) = self.database_auto_migrate {
+            settings.database.auto_migrate = auto_migrate;
+        }
+        if let Some(default_type) = self.default_diagram_type {
+            settings.diagram.default_type = default_type;
+        }
+        if let Some(theme) = self.diagram_theme {
+            settings.diagram.theme = theme;
+        }
+        if let Some(direction) = self.diagram_direction {
+            settings.diagram.direction = direction;
+        }
+    }
+}
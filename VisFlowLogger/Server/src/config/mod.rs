@@ -0,0 +1,38 @@
+use crate::config::cli::Cli;
+use crate::config::settings::Settings;
+use std::path::Path;
+
+pub mod cli;
+pub mod settings;
+#[cfg(test)]
+mod unit_test;
+
+/// Used when `--config` is not given and the file exists in the working directory.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/**
+Builds the server settings. Layers, each one overriding the previous :- <br>
+1. Built in defaults
+2. TOML config file
+3. Environment variables
+4. CLI flags
+ */
+pub fn load(cli: &Cli) -> Result<Settings, String> {
+    let mut settings = match &cli.config {
+        Some(path) => read_file(path)?,
+        None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+            read_file(Path::new(DEFAULT_CONFIG_FILE))?
+        }
+        None => Settings::default(),
+    };
+    // clap already resolved environment variables against CLI flags
+    cli.overrides.apply(&mut settings);
+    settings.validate()?;
+    Ok(settings)
+}
+
+fn read_file(path: &Path) -> Result<Settings, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+    toml::from_str(&content).map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+}
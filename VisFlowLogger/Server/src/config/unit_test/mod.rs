@@ -0,0 +1,91 @@
+use crate::config::cli::Cli;
+use crate::config::settings::Settings;
+use crate::services::diagram_generator::api::services::DiagramType;
+use clap::Parser;
+
+#[test]
+fn cli_flags_override_config_file() {
+    let mut settings: Settings = toml::from_str(
+        r#"
+        [server]
+        bind_address = "0.0.0.0:9000"
+        workers = 2
+
+        [database]
+        url = "postgres://file"
+        max_connections = 4
+        "#,
+    )
+    .unwrap();
+    // Untouched sections keep their defaults
+    assert_eq!(settings.server.payload_limit, 2 * 1024 * 1024);
+    assert_eq!(settings.server.ndjson_max_line, 2 * 1024 * 1024);
+    assert!(settings.diagram.default_type == DiagramType::Mermaid);
+
+    let cli = Cli::try_parse_from([
+        "Server",
+        "--database-url",
+        "postgres://cli",
+        "--workers",
+        "8",
+        "--ndjson-max-line",
+        "1024",
+        "--aggregate-max-operations",
+        "20",
+    ])
+    .unwrap();
+    cli.overrides.apply(&mut settings);
+    assert_eq!(settings.server.bind_address, "0.0.0.0:9000");
+    assert_eq!(settings.server.workers, Some(8));
+    assert_eq!(settings.server.ndjson_max_line, 1024);
+    assert_eq!(settings.server.aggregate_max_operations, 20);
+    assert_eq!(settings.server.payload_limit, 2 * 1024 * 1024);
+    assert_eq!(settings.database.url, "postgres://cli");
+    assert_eq!(settings.database.max_connections, 4);
+    assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    assert!(toml::from_str::<Settings>("[server]\nport = 8080").is_err());
+
+    let mut settings = Settings::default();
+    settings.database.min_connections = 20;
+    assert!(settings.validate().is_err());
+
+    let mut settings = Settings::default();
+    settings.server.aggregate_max_operations = 0;
+    assert!(settings.validate().is_err());
+}
+
+#[test]
+fn diagram_defaults_come_from_the_config_and_cli() {
+    use crate::services::diagram_generator::api::models::block::DGBlockFlowType;
+    use crate::services::diagram_generator::api::models::diagram_options::{
+        Direction, Shape, Theme,
+    };
+    let mut settings: Settings = toml::from_str(
+        r##"
+        [diagram]
+        theme = "high-contrast"
+        block_colors = { load = "#A5D6A7" }
+        shapes = { Call = "hexagon" }
+        "##,
+    )
+    .unwrap();
+    let cli = Cli::try_parse_from(["Server", "--diagram-direction", "LR"]).unwrap();
+    cli.overrides.apply(&mut settings);
+    assert!(settings.validate().is_ok());
+
+    let options = settings.diagram.options();
+    assert_eq!(options.theme, Theme::HighContrast);
+    assert_eq!(options.direction, Direction::Lr);
+    assert_eq!(options.block_colors["load"], "#A5D6A7");
+    assert_eq!(options.shapes[&DGBlockFlowType::Call], Shape::Hexagon);
+
+    settings
+        .diagram
+        .block_colors
+        .insert("load".to_string(), "green".to_string());
+    assert!(settings.validate().is_err());
+}
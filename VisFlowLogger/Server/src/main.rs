@@ -1,50 +1,132 @@
+use crate::config::cli::{Cli, Command, MigrateAction};
 use crate::services::diagram_generator::api::services::DiagramType;
 use crate::services::diagram_generator::AvailableServices;
+use crate::services::graph_aggregator::AvailableServices as GAAS;
+use crate::services::graph_differ::AvailableServices as GDAS;
 use crate::services::graph_generator::AvailableServices as GGAS;
-use crate::services::persistence::api::services::vis_flow_op::VisFlowOp;
 use crate::services::persistence::AvailableServices as PAS;
 use actix_web::{web, App, HttpServer};
+use clap::Parser;
 use server::models::app_state::{AppState, AvailableServices as AS};
 use std::sync::Arc;
 
+mod config;
 mod server;
 mod services;
 mod utils;
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
+    let cli = Cli::parse();
+    let settings = config::load(&cli).unwrap_or_else(|e| startup_error(&e));
+
     //Create the services
-    let vis_flow_log_repo = services::persistence::api::services::vis_flow_log::new().await;
-    let vis_flow_op_repo = services::persistence::api::services::vis_flow_op::new().await;
+    let persistence = services::persistence::new(&settings.database)
+        .await
+        .unwrap_or_else(|e| startup_error(&e.to_string()));
+    if let Some(Command::Migrate { action }) = cli.command {
+        return migrate(&persistence, action).await;
+    }
+    if settings.database.auto_migrate {
+        for migration in persistence
+            .schema_migration
+            .up(false)
+            .await
+            .unwrap_or_else(|e| startup_error(&e.to_string()))
+        {
+            println!("Applied migration {}", migration);
+        }
+    }
     let graph_generator = services::graph_generator::api::services::graph_generator::new();
-    let diagram_generator = services::diagram_generator::api::services::new(DiagramType::Mermaid);
+    let graph_aggregator = services::graph_aggregator::api::services::graph_aggregator::new();
+    let graph_differ = services::graph_differ::api::services::graph_differ::new();
 
     let app_state = AppState {
         services: AS {
-            persistence: PAS {
-                vis_flow_log: Arc::new(vis_flow_log_repo),
-                vis_flow_op: Arc::new(vis_flow_op_repo),
-            },
+            persistence,
             graph_generator: GGAS {
                 graph_generator: Arc::new(graph_generator),
             },
             diagram_generator: AvailableServices {
-                mermaid: Arc::new(diagram_generator),
+                mermaid: services::diagram_generator::api::services::new(DiagramType::Mermaid),
+                mermaid_sequence: services::diagram_generator::api::services::new(
+                    DiagramType::MermaidSequence,
+                ),
+                plantuml: services::diagram_generator::api::services::new(DiagramType::Plantuml),
+                dot: services::diagram_generator::api::services::new(DiagramType::Dot),
+                folded: services::diagram_generator::api::services::new(DiagramType::Folded),
+                flamegraph: services::diagram_generator::api::services::new(
+                    DiagramType::Flamegraph,
+                ),
+                svg: services::diagram_generator::api::services::new(DiagramType::Svg),
+                html: services::diagram_generator::api::services::new(DiagramType::Html),
+            },
+            graph_aggregator: GAAS {
+                graph_aggregator: Arc::new(graph_aggregator),
+            },
+            graph_differ: GDAS {
+                graph_differ: Arc::new(graph_differ),
             },
         },
+        settings: settings.clone(),
     };
 
-    HttpServer::new(move || {
+    let payload_limit = settings.server.payload_limit;
+    let mut server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(app_state.clone()))
+            .app_data(web::JsonConfig::default().limit(payload_limit))
+            .app_data(web::PayloadConfig::default().limit(payload_limit))
             .service(server::route::save_logs)
+            .service(server::route::save_logs_ndjson)
             .service(server::route::get_logs_by_operation_id)
             .service(server::route::get_operations)
             .service(server::route::get_graphs_by_operation_id)
+            .service(server::route::validate_operation)
             .service(server::route::generate_diagram_for_operation)
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
+            .service(server::route::generate_mermaid_sequence_diagram_for_operation)
+            .service(server::route::generate_plantuml_diagram_for_operation)
+            .service(server::route::generate_dot_diagram_for_operation)
+            .service(server::route::generate_folded_stacks_for_operation)
+            .service(server::route::generate_flame_graph_for_operation)
+            .service(server::route::generate_default_diagram_for_operation)
+            .service(server::route::view_operation)
+            .service(server::route::aggregate_operations)
+            .service(server::route::generate_aggregate_diagram)
+            .service(server::route::diff_operations)
+            .service(server::route::generate_diff_diagram)
+    });
+    if let Some(workers) = settings.server.workers {
+        server = server.workers(workers);
+    }
+    let bind_address = &settings.server.bind_address;
+    println!("Listening on {}", bind_address);
+    server
+        .bind(bind_address)
+        .unwrap_or_else(|e| startup_error(&format!("Failed to bind {}: {}", bind_address, e)))
+        .run()
+        .await
+}
+
+/// Handles `Server migrate ...` and exits without starting the HTTP server.
+async fn migrate(persistence: &PAS, action: MigrateAction) -> std::io::Result<()> {
+    let result = match action {
+        MigrateAction::Status => persistence.schema_migration.status().await,
+        MigrateAction::Up { dry_run } => persistence.schema_migration.up(dry_run).await,
+    };
+    let migrations = result.unwrap_or_else(|e| startup_error(&e.to_string()));
+    if migrations.is_empty() {
+        println!("Nothing to migrate");
+    }
+    for migration in migrations {
+        println!("{}", migration);
+    }
+    Ok(())
+}
+
+/// Startup failures are reported as a single line instead of a panic backtrace.
+fn startup_error(message: &str) -> ! {
+    eprintln!("Startup error: {}", message);
+    std::process::exit(1)
 }
 
 /*
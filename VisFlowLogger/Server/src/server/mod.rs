@@ -1,2 +1,6 @@
+pub mod models;
+pub mod ndjson;
 pub mod route;
-pub mod models;
\ No newline at end of file
+
+#[cfg(test)]
+mod unit_test;
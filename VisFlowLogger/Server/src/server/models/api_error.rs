@@ -0,0 +1,109 @@
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::graph_generator::api::models::graph_error::GraphError;
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
+use std::fmt::Display;
+
+/// Problem details (RFC 9457) returned for every failed request
+#[derive(Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    pub title: String,
+    pub status: u16,
+    /// Machine-readable name of the error, e.g. "UnbalancedBlock"
+    pub code: &'static str,
+    pub detail: String,
+}
+
+/**
+Errors of every subsystem a route can fail with. <br>
+Routes return `Result<HttpResponse, ApiError>`, so `?` turns a service error into a problem response.
+ */
+#[derive(Debug)]
+pub enum ApiError {
+    Persistence(PersistenceError),
+    Graph(GraphError),
+    Diagram(DiagramError),
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Persistence(e) => e.code(),
+            ApiError::Graph(e) => e.code(),
+            ApiError::Diagram(e) => e.code(),
+        }
+    }
+
+    pub fn problem(&self) -> Problem {
+        let status = self.status_code();
+        Problem {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            code: self.code(),
+            detail: self.to_string(),
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::Persistence(e) => write!(f, "{}", e),
+            ApiError::Graph(e) => write!(f, "{}", e),
+            ApiError::Diagram(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::Persistence(
+                PersistenceError::OperationNotFound(_) | PersistenceError::NoOperationsNamed { .. },
+            ) => StatusCode::NOT_FOUND,
+            ApiError::Persistence(PersistenceError::DbUnavailable(_)) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            ApiError::Persistence(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            // The stored logs are well-formed JSON but don't describe a valid flow
+            ApiError::Graph(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Diagram(
+                DiagramError::UnsupportedFormat(_) | DiagramError::InvalidOption(_),
+            ) => StatusCode::BAD_REQUEST,
+            // Graphs are built by the server, so a graph the diagram generator rejects is a bug
+            ApiError::Diagram(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if self.status_code().is_server_error() {
+            eprintln!("{}", self);
+        }
+        HttpResponse::build(self.status_code())
+            .content_type("application/problem+json")
+            .json(self.problem())
+    }
+}
+
+impl From<PersistenceError> for ApiError {
+    fn from(e: PersistenceError) -> Self {
+        ApiError::Persistence(e)
+    }
+}
+
+impl From<GraphError> for ApiError {
+    fn from(e: GraphError) -> Self {
+        ApiError::Graph(e)
+    }
+}
+
+impl From<DiagramError> for ApiError {
+    fn from(e: DiagramError) -> Self {
+        ApiError::Diagram(e)
+    }
+}
@@ -1,10 +1,13 @@
 #[derive(Clone)]
 pub struct AppState {
     pub services: AvailableServices,
+    pub settings: crate::config::settings::Settings,
 }
 #[derive(Clone)]
 pub struct AvailableServices {
     pub persistence: crate::services::persistence::AvailableServices,
     pub graph_generator: crate::services::graph_generator::AvailableServices,
-    pub diagram_generator : crate::services::diagram_generator::AvailableServices
+    pub diagram_generator: crate::services::diagram_generator::AvailableServices,
+    pub graph_aggregator: crate::services::graph_aggregator::AvailableServices,
+    pub graph_differ: crate::services::graph_differ::AvailableServices,
 }
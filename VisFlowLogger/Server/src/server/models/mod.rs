@@ -1,2 +1,3 @@
+pub mod api_error;
 pub mod app_state;
 pub mod payload;
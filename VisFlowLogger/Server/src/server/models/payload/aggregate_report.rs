@@ -0,0 +1,19 @@
+use crate::services::graph_aggregator::api::models::aggregate_graph::AggregateGraph;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct AggregateReport {
+    pub operation_name: String,
+    #[serde(flatten)]
+    pub graph: AggregateGraph,
+    ///Operations of the name whose graph couldn't be built, left out of the aggregate
+    pub skipped: Vec<SkippedOperation>,
+}
+
+#[derive(Serialize)]
+pub struct SkippedOperation {
+    pub operation_id: String,
+    ///Machine-readable name of the error, as in problem responses
+    pub code: &'static str,
+    pub detail: String,
+}
@@ -0,0 +1,13 @@
+use crate::services::graph_differ::api::models::graph_diff::GraphDiff;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct DiffReport {
+    ///Operation compared against, like a run that behaved well
+    pub before: String,
+    pub after: String,
+    ///Both operations made the same calls with the same values
+    pub identical: bool,
+    #[serde(flatten)]
+    pub diff: GraphDiff,
+}
@@ -0,0 +1,77 @@
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::{
+    DiagramOptions, Direction, Theme,
+};
+use crate::services::diagram_generator::api::services::DiagramFormat;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+///Query parameters of the graph and diagram routes
+#[derive(Deserialize)]
+pub struct GraphQuery {
+    ///Repairs unbalanced traces instead of rejecting them, see `generate_lenient_graph`
+    #[serde(default)]
+    pub lenient: bool,
+    ///Keeps only the first iteration of every loop, the loop node still shows the iteration count
+    #[serde(default)]
+    pub collapse_loops: bool,
+    ///Diagram routes only: `svg` or `png` draws the diagram on the server instead of sending its source
+    #[serde(default)]
+    pub format: DiagramFormat,
+    ///Diagram routes only: picks another palette for the block colors
+    pub seed: Option<u64>,
+    ///Diagram routes only: replaces the server's default theme
+    pub theme: Option<Theme>,
+    ///Diagram routes only: `TB` or `LR`, replaces the server's default direction
+    pub direction: Option<Direction>,
+    ///Diagram routes only: block fills as `name:#RRGGBB,other:#RGB`, added to the server's
+    pub colors: Option<String>,
+    ///Diagram routes only: node shapes as `Call:hexagon,Log:rounded`, added to the server's
+    pub shapes: Option<String>,
+    ///Diagram routes only: longest flowchart label before it is shortened, 0 keeps labels whole
+    pub max_label: Option<usize>,
+    ///Diagram routes only: levels of calls drawn below the root, deeper ones are counted in the block that made them
+    pub max_depth: Option<usize>,
+    ///Diagram routes only: folds the calls of a block to itself into one `×N` node
+    #[serde(default)]
+    pub collapse_recursion: bool,
+    ///Diagram routes only: leaves `Log` flows out
+    #[serde(default)]
+    pub hide_logs: bool,
+    ///Diagram routes only: block ID or name whose call tree is drawn instead of the whole operation
+    pub focus: Option<String>,
+    ///Aggregate routes only: merges the operations created at or after this time, e.g. `2025-01-31T08:00:00`
+    pub from: Option<NaiveDateTime>,
+    ///Aggregate routes only: merges the operations created before this time
+    pub to: Option<NaiveDateTime>,
+    ///Aggregate routes only: merges only this many of the newest operations, at most the server's
+    /// `aggregate_max_operations`
+    pub limit: Option<usize>,
+}
+
+impl GraphQuery {
+    ///The server's diagram options with the ones this query sets on top
+    pub fn diagram_options(
+        &self,
+        defaults: DiagramOptions,
+    ) -> Result<DiagramOptions, DiagramError> {
+        let mut options = defaults;
+        options.seed = self.seed.or(options.seed);
+        options.theme = self.theme.unwrap_or(options.theme);
+        options.direction = self.direction.unwrap_or(options.direction);
+        options.max_label = self.max_label.unwrap_or(options.max_label);
+        options.max_depth = self.max_depth.or(options.max_depth);
+        options.collapse_recursion |= self.collapse_recursion;
+        options.hide_logs |= self.hide_logs;
+        options.focus = self.focus.clone().or(options.focus);
+        if let Some(colors) = &self.colors {
+            options
+                .block_colors
+                .extend(DiagramOptions::parse_block_colors(colors)?);
+        }
+        if let Some(shapes) = &self.shapes {
+            options.shapes.extend(DiagramOptions::parse_shapes(shapes)?);
+        }
+        Ok(options)
+    }
+}
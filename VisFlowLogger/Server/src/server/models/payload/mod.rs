@@ -1 +1,6 @@
+pub mod aggregate_report;
+pub mod diff_report;
+pub mod graph_query;
+pub mod ndjson_payload;
 pub mod save_logs_payload;
+pub mod validation_report;
@@ -0,0 +1,33 @@
+use crate::services::persistence::api::model::vis_flow_log_model::VisFlowLogEntry;
+use serde::{Deserialize, Serialize};
+
+/// One line of an `application/x-ndjson` ingestion body.
+/// `operation_name` defaults to the `operation_id` when omitted.
+#[derive(Deserialize)]
+pub struct NdjsonLogLine {
+    pub operation_name: Option<String>,
+    #[serde(flatten)]
+    pub log: VisFlowLogEntry,
+}
+
+#[derive(Serialize)]
+pub struct NdjsonLineError {
+    /// 1-based line number in the request body
+    pub line: usize,
+    pub error: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct NdjsonIngestReport {
+    pub lines: usize,
+    pub saved: usize,
+    /// Lines identical to logs that were already saved
+    pub duplicates: usize,
+    pub rejected: usize,
+    pub errors: Vec<NdjsonLineError>,
+    /// Set when more lines were rejected than are listed in `errors`
+    pub errors_truncated: bool,
+    /// Storage failure that aborted the ingestion, lines up to `lines` were processed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
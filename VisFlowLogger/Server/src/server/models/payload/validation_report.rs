@@ -0,0 +1,11 @@
+use crate::services::graph_generator::api::models::graph_violation::GraphViolation;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ValidationReport {
+    pub operation_id: String,
+    pub valid: bool,
+    ///Number of logs stored for the operation
+    pub entries: usize,
+    pub violations: Vec<GraphViolation>,
+}
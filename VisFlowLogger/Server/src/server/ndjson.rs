@@ -0,0 +1,235 @@
+use crate::server::models::payload::ndjson_payload::{
+    NdjsonIngestReport, NdjsonLineError, NdjsonLogLine,
+};
+use crate::services::graph_generator::api::models::vis_flow_log_entry::VisFlowLogEntryLogType;
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::model::vis_flow_ingest_model::{IngestOutcome, LogConflict};
+use crate::services::persistence::api::model::vis_flow_log_model::VisFlowLogEntry;
+use crate::services::persistence::AvailableServices;
+use std::collections::{HashMap, HashSet};
+
+/// Number of accepted lines buffered before they are written to the database
+pub const LINES_PER_CHUNK: usize = 1000;
+/// Rejected lines past this are only counted, not listed in the report
+pub const MAX_REPORTED_ERRORS: usize = 1000;
+/// Column width of `operation_id`, `block_name` and the span ids in the `logs` table
+const MAX_NAME_LENGTH: usize = 100;
+
+/**
+Splits a byte stream into lines without holding more than one line in memory. <br>
+A trailing `\r` is dropped so CRLF bodies are accepted too.
+ */
+pub struct LineSplitter {
+    buffer: Vec<u8>,
+    max_line_length: usize,
+}
+
+impl LineSplitter {
+    pub fn new(max_line_length: usize) -> Self {
+        LineSplitter {
+            buffer: Vec::new(),
+            max_line_length,
+        }
+    }
+
+    /// Returns the lines completed by `chunk`, or an error once a line grows past the limit
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        let mut lines = Vec::new();
+        let mut rest = chunk;
+        while let Some(position) = rest.iter().position(|b| *b == b'\n') {
+            self.buffer.extend_from_slice(&rest[..position]);
+            self.check_length()?;
+            lines.push(Self::take_line(&mut self.buffer));
+            rest = &rest[position + 1..];
+        }
+        self.buffer.extend_from_slice(rest);
+        self.check_length()?;
+        Ok(lines)
+    }
+
+    /// Returns the last line if the body does not end with a newline
+    pub fn finish(mut self) -> Option<Vec<u8>> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        Some(Self::take_line(&mut self.buffer))
+    }
+
+    fn check_length(&self) -> Result<(), String> {
+        if self.buffer.len() > self.max_line_length {
+            return Err(format!(
+                "Line is longer than {} bytes",
+                self.max_line_length
+            ));
+        }
+        Ok(())
+    }
+
+    fn take_line(buffer: &mut Vec<u8>) -> Vec<u8> {
+        let mut line = std::mem::take(buffer);
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        line
+    }
+}
+
+/// Parses and validates a single NDJSON line
+pub fn parse_line(line: &[u8]) -> Result<NdjsonLogLine, String> {
+    let parsed: NdjsonLogLine = serde_json::from_slice(line).map_err(|e| e.to_string())?;
+    let log = &parsed.log;
+    if VisFlowLogEntryLogType::from_log_type(&log.log_type).is_none() {
+        return Err(format!("Invalid log type: {}", log.log_type));
+    }
+    if log.operation_id.is_empty() || log.operation_id.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "operation_id must be between 1 and {} characters",
+            MAX_NAME_LENGTH
+        ));
+    }
+    if log.block_name.is_empty() || log.block_name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "block_name must be between 1 and {} characters",
+            MAX_NAME_LENGTH
+        ));
+    }
+    for (field, span) in [
+        ("span_id", &log.span_id),
+        ("parent_span_id", &log.parent_span_id),
+    ] {
+        if span
+            .as_ref()
+            .is_some_and(|span| span.chars().count() > MAX_NAME_LENGTH)
+        {
+            return Err(format!(
+                "{} must be at most {} characters",
+                field, MAX_NAME_LENGTH
+            ));
+        }
+    }
+    if let Some(name) = &parsed.operation_name {
+        if name.chars().count() > MAX_NAME_LENGTH {
+            return Err(format!(
+                "operation_name must be at most {} characters",
+                MAX_NAME_LENGTH
+            ));
+        }
+    }
+    Ok(parsed)
+}
+
+/**
+Accumulates validated lines and writes them in chunks of [`LINES_PER_CHUNK`]. <br>
+Operations are upserted together with the first chunk that references them.
+Each chunk is atomic and can be resent: logs already saved with the same content count as duplicates.
+ */
+pub struct NdjsonIngestor<'a> {
+    persistence: &'a AvailableServices,
+    /// Accepted logs with their line number
+    pending_logs: Vec<(usize, VisFlowLogEntry)>,
+    pending_operations: HashMap<String, String>,
+    known_operations: HashSet<String>,
+    report: NdjsonIngestReport,
+}
+
+impl<'a> NdjsonIngestor<'a> {
+    pub fn new(persistence: &'a AvailableServices) -> Self {
+        NdjsonIngestor {
+            persistence,
+            pending_logs: Vec::new(),
+            pending_operations: HashMap::new(),
+            known_operations: HashSet::new(),
+            report: NdjsonIngestReport::default(),
+        }
+    }
+
+    /// Handles one line, flushing when a chunk is full. Fails only when storage fails.
+    pub async fn line(&mut self, line: &[u8]) -> Result<(), PersistenceError> {
+        self.report.lines += 1;
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return Ok(());
+        }
+        match parse_line(line) {
+            Ok(parsed) => {
+                let operation_id = parsed.log.operation_id.clone();
+                if !self.known_operations.contains(&operation_id) {
+                    let operation_name = parsed.operation_name.unwrap_or(operation_id.clone());
+                    self.pending_operations
+                        .insert(operation_id.clone(), operation_name);
+                    self.known_operations.insert(operation_id);
+                }
+                self.pending_logs.push((self.report.lines, parsed.log));
+                if self.pending_logs.len() >= LINES_PER_CHUNK {
+                    self.flush().await?;
+                }
+            }
+            Err(error) => self.reject(self.report.lines, error),
+        }
+        Ok(())
+    }
+
+    /// Records a line that could not be parsed, validated or saved
+    fn reject(&mut self, line: usize, error: String) {
+        self.report.rejected += 1;
+        if self.report.errors.len() < MAX_REPORTED_ERRORS {
+            self.report.errors.push(NdjsonLineError { line, error });
+        } else {
+            self.report.errors_truncated = true;
+        }
+    }
+
+    /**
+    Writes the buffered chunk in one transaction. <br>
+    Lines conflicting with logs saved earlier are rejected and the rest of the chunk is written again.
+     */
+    pub async fn flush(&mut self) -> Result<(), PersistenceError> {
+        let operations: Vec<(String, String)> = self.pending_operations.drain().collect();
+        loop {
+            let logs: Vec<&VisFlowLogEntry> =
+                self.pending_logs.iter().map(|(_, log)| log).collect();
+            if logs.is_empty() && operations.is_empty() {
+                return Ok(());
+            }
+            let outcome = self
+                .persistence
+                .vis_flow_ingest
+                .ingest(&operations, &logs)
+                .await?;
+            match outcome {
+                IngestOutcome::Saved {
+                    inserted,
+                    duplicates,
+                } => {
+                    self.report.saved += inserted;
+                    self.report.duplicates += duplicates;
+                    self.pending_logs.clear();
+                    return Ok(());
+                }
+                IngestOutcome::Conflict { conflicts } => {
+                    let conflicts: HashMap<(&str, u32), &LogConflict> = conflicts
+                        .iter()
+                        .map(|c| ((c.operation_id.as_str(), c.sequence), c))
+                        .collect();
+                    let (rejected, kept) = std::mem::take(&mut self.pending_logs)
+                        .into_iter()
+                        .partition(|(_, log)| {
+                            conflicts.contains_key(&(log.operation_id.as_str(), log.sequence))
+                        });
+                    self.pending_logs = kept;
+                    let rejected: Vec<(usize, VisFlowLogEntry)> = rejected;
+                    for (line, log) in rejected {
+                        let conflict = conflicts[&(log.operation_id.as_str(), log.sequence)];
+                        self.reject(line, conflict.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn into_report(self, error: Option<String>) -> NdjsonIngestReport {
+        NdjsonIngestReport {
+            error,
+            ..self.report
+        }
+    }
+}
@@ -1,17 +1,40 @@
+use crate::server::models::api_error::ApiError;
 use crate::server::models::app_state::AppState;
+use crate::server::models::payload::aggregate_report::{AggregateReport, SkippedOperation};
+use crate::server::models::payload::diff_report::DiffReport;
+use crate::server::models::payload::graph_query::GraphQuery;
 use crate::server::models::payload::save_logs_payload::SaveLogsPayload;
+use crate::server::models::payload::validation_report::ValidationReport;
+use crate::server::ndjson::{LineSplitter, NdjsonIngestor};
 use crate::services::diagram_generator::api::models::block::DGBlock;
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::services::{DiagramFormat, DiagramType};
+use crate::services::graph_aggregator::api::models::aggregate_graph::AggregateGraph;
+use crate::services::graph_generator::api::models::graph_error::GraphError;
+use crate::services::graph_generator::api::models::graph_violation::GraphViolation;
+use crate::services::graph_generator::api::models::vis_flow::GGBlock;
 use crate::services::graph_generator::api::models::vis_flow_log_entry::{
     VisFlowLogEntry, VisFlowLogEntryLogType,
 };
-use actix_web::{get, post, web, HttpResponse};
-use std::collections::HashMap;
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::model::vis_flow_ingest_model::IngestOutcome;
+use crate::services::persistence::api::model::vis_flow_log_model::VisFlowLogEntity;
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, ResponseError};
+use clap::ValueEnum;
+use futures_util::StreamExt;
+use std::collections::{BTreeMap, HashMap};
 
+/**
+Saves the whole payload in one transaction. Logs are identified by `(operation_id, sequence)`,
+so a retried request is answered with the logs counted as `duplicates` instead of saving them twice. <br>
+Logs that reuse a key with different content are returned with 409 and nothing is saved.
+ */
 #[post("/")]
 pub async fn save_logs(
     body: web::Json<SaveLogsPayload>,
     app_state: web::Data<AppState>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     let payload = body.into_inner();
     let all_operations: Vec<(String, String)> = payload
         .operation
@@ -23,190 +46,540 @@ pub async fn save_logs(
             )
         })
         .collect();
-    let all_logs = payload
+    let all_logs: Vec<_> = payload
         .operation
         .iter()
         .flat_map(|x| x.logs.iter())
         .collect();
 
-    let save_logs_future = app_state
-        .services
-        .persistence
-        .vis_flow_log
-        .save_log(&all_logs);
-    let upsert_ops_future = app_state
+    let outcome = app_state
         .services
         .persistence
-        .vis_flow_op
-        .upsert(all_operations);
+        .vis_flow_ingest
+        .ingest(&all_operations, &all_logs)
+        .await?;
+    Ok(match outcome {
+        IngestOutcome::Saved { .. } => HttpResponse::Ok().json(outcome),
+        IngestOutcome::Conflict { .. } => HttpResponse::Conflict().json(outcome),
+    })
+}
+
+/**
+Streams `application/x-ndjson` bodies, one log per line, so batches of any size can be ingested. <br>
+Every line is validated on its own; invalid lines are reported by line number and skipped.
+ */
+#[post("/ndjson")]
+pub async fn save_logs_ndjson(
+    request: HttpRequest,
+    mut body: web::Payload,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    let is_ndjson = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/x-ndjson"));
+    if !is_ndjson {
+        return HttpResponse::UnsupportedMediaType().json("Expected application/x-ndjson");
+    }
 
-    let (save_logs_result, upsert_ops_result) = tokio::join!(save_logs_future, upsert_ops_future);
-    if !save_logs_result || !upsert_ops_result {
-        return HttpResponse::InternalServerError().json("Failed to save logs or operations");
+    let mut splitter = LineSplitter::new(app_state.settings.server.ndjson_max_line);
+    let mut ingestor = NdjsonIngestor::new(&app_state.services.persistence);
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ingestor.into_report(Some(e.to_string())))
+            }
+        };
+        let lines = match splitter.push(&chunk) {
+            Ok(lines) => lines,
+            Err(e) => return HttpResponse::PayloadTooLarge().json(ingestor.into_report(Some(e))),
+        };
+        for line in lines {
+            if let Err(e) = ingestor.line(&line).await {
+                return storage_failure(ingestor, e);
+            }
+        }
+    }
+    if let Some(line) = splitter.finish() {
+        if let Err(e) = ingestor.line(&line).await {
+            return storage_failure(ingestor, e);
+        }
+    }
+    if let Err(e) = ingestor.flush().await {
+        return storage_failure(ingestor, e);
     }
-    HttpResponse::Ok().json("Saved logs to database")
+    HttpResponse::Ok().json(ingestor.into_report(None))
+}
+
+///Answers with the status of the storage error and the report of the lines processed so far
+fn storage_failure(ingestor: NdjsonIngestor, error: PersistenceError) -> HttpResponse {
+    let error = ApiError::from(error);
+    eprintln!("{}", error);
+    HttpResponse::build(error.status_code()).json(ingestor.into_report(Some(error.to_string())))
 }
 
 #[get("/")]
-pub async fn get_operations(app_state: web::Data<AppState>) -> HttpResponse {
+pub async fn get_operations(app_state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
     println!("Getting all operations");
-    match app_state
+    let ops = app_state
         .services
         .persistence
         .vis_flow_op
         .get_operations()
-        .await
-    {
-        Ok(ops) => HttpResponse::Ok().json(ops),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+        .await?;
+    Ok(HttpResponse::Ok().json(ops))
 }
 
 #[get("/{operation_id}")]
 pub async fn get_logs_by_operation_id(
     operation_id: web::Path<String>,
     app_state: web::Data<AppState>,
-) -> HttpResponse {
-    match app_state
+) -> Result<HttpResponse, ApiError> {
+    let logs = app_state
         .services
         .persistence
         .vis_flow_log
         .get_logs_by_operation_id(operation_id.into_inner())
-        .await
-    {
-        Ok(logs) => HttpResponse::Ok().json(logs),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+        .await?;
+    Ok(HttpResponse::Ok().json(logs))
 }
 
 #[get("/graph/{operation_id}")]
 pub async fn get_graphs_by_operation_id(
     operation_id: web::Path<String>,
+    query: web::Query<GraphQuery>,
     app_state: web::Data<AppState>,
-) -> HttpResponse {
-    let logs = match app_state
-        .services
-        .persistence
-        .vis_flow_log
-        .get_logs_by_operation_id(operation_id.into_inner())
-        .await
-    {
-        Ok(logs) => logs,
-        Err(e) => {
-            return HttpResponse::InternalServerError().body(format!("Failed to get logs: {}", e))
-        }
-    };
-
-    if let Some(invalid_log) = logs.iter().find(|log| {
-        !matches!(
-            log.log_type.as_str(),
-            "LOG" | "START" | "END" | "STORE" | "EXTERNAL_CALL" | "EXTERNAL_CALL_STORE"
-        )
-    }) {
-        return HttpResponse::BadRequest()
-            .body(format!("Invalid log_type: {}", invalid_log.log_type));
-    }
+) -> Result<HttpResponse, ApiError> {
+    let graph = build_graph(operation_id.into_inner(), &query, &app_state).await?;
+    // Sorted by key, so the same operation always gives the same JSON
+    Ok(HttpResponse::Ok().json(graph.into_iter().collect::<BTreeMap<_, _>>()))
+}
 
-    let entries = logs
+/**
+Checks the logs of an operation against the graph rules and lists every violation with
+the entry index and sequence, the expected and actual block and the open blocks at that point. <br>
+Logs with an unknown `log_type` are reported on their own, since the trace can't be checked without them.
+ */
+#[get("/validate/{operation_id}")]
+pub async fn validate_operation(
+    operation_id: web::Path<String>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let operation_id = operation_id.into_inner();
+    let logs = load_logs(operation_id.clone(), &app_state).await?;
+    let invalid_log_types: Vec<GraphViolation> = logs
         .iter()
-        .map(|log| VisFlowLogEntry {
-            log_type: match log.log_type.as_str() {
-                "LOG" => VisFlowLogEntryLogType::Log,
-                "START" => VisFlowLogEntryLogType::Start,
-                "END" => VisFlowLogEntryLogType::End,
-                "STORE" => VisFlowLogEntryLogType::Store,
-                "EXTERNAL_CALL" => VisFlowLogEntryLogType::ExternalCall,
-                "EXTERNAL_CALL_STORE" => VisFlowLogEntryLogType::ExternalCallStore,
-                _ => unreachable!(),
-            },
-            log_value: log.log_value.clone(),
-            block_name: log.block_name.clone(),
+        .enumerate()
+        .filter(|(_, log)| VisFlowLogEntryLogType::from_log_type(&log.log_type).is_none())
+        .map(|(index, log)| {
+            GraphViolation::new(
+                GraphError::InvalidLogType(log.log_type.clone()),
+                Some((index, log.sequence)),
+                vec![],
+            )
         })
         .collect();
+    let entries = logs.len();
+    let violations = if invalid_log_types.is_empty() {
+        let entries = to_entries(logs)?;
+        app_state
+            .services
+            .graph_generator
+            .graph_generator
+            .validate(&entries)
+    } else {
+        invalid_log_types
+    };
 
-    match app_state
-        .services
-        .graph_generator
-        .graph_generator
-        .generate_graph(entries)
-    {
-        Ok(graph) => HttpResponse::Ok().json(graph),
-        Err(e) => {
-            HttpResponse::InternalServerError().body(format!("Failed to generate graph: {}", e))
-        }
-    }
+    Ok(HttpResponse::Ok().json(ValidationReport {
+        operation_id,
+        valid: violations.is_empty(),
+        entries,
+        violations,
+    }))
 }
 
 #[get("/diagram/mermaid/{operation_id}")]
 pub async fn generate_diagram_for_operation(
     operation_id: web::Path<String>,
+    query: web::Query<GraphQuery>,
     app_state: web::Data<AppState>,
-) -> HttpResponse {
-    let logs = match app_state
+) -> Result<HttpResponse, ApiError> {
+    generate_diagram(
+        operation_id.into_inner(),
+        DiagramType::Mermaid,
+        &query,
+        &app_state,
+    )
+    .await
+}
+
+///Mermaid `sequenceDiagram` of the calls and returns, in flow order
+#[get("/diagram/mermaid-sequence/{operation_id}")]
+pub async fn generate_mermaid_sequence_diagram_for_operation(
+    operation_id: web::Path<String>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    generate_diagram(
+        operation_id.into_inner(),
+        DiagramType::MermaidSequence,
+        &query,
+        &app_state,
+    )
+    .await
+}
+
+///PlantUML sequence diagram of the calls and returns, in flow order
+#[get("/diagram/plantuml/{operation_id}")]
+pub async fn generate_plantuml_diagram_for_operation(
+    operation_id: web::Path<String>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    generate_diagram(
+        operation_id.into_inner(),
+        DiagramType::Plantuml,
+        &query,
+        &app_state,
+    )
+    .await
+}
+
+///Graphviz DOT, for rendering large flows offline with `dot`
+#[get("/diagram/dot/{operation_id}")]
+pub async fn generate_dot_diagram_for_operation(
+    operation_id: web::Path<String>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    generate_diagram(
+        operation_id.into_inner(),
+        DiagramType::Dot,
+        &query,
+        &app_state,
+    )
+    .await
+}
+
+///Call stacks in the folded format, weighted by duration when the logs have times, else by entry count
+#[get("/diagram/folded/{operation_id}")]
+pub async fn generate_folded_stacks_for_operation(
+    operation_id: web::Path<String>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    generate_diagram(
+        operation_id.into_inner(),
+        DiagramType::Folded,
+        &query,
+        &app_state,
+    )
+    .await
+}
+
+///SVG flame graph of the same stacks as `/diagram/folded`
+#[get("/diagram/flamegraph/{operation_id}")]
+pub async fn generate_flame_graph_for_operation(
+    operation_id: web::Path<String>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    generate_diagram(
+        operation_id.into_inner(),
+        DiagramType::Flamegraph,
+        &query,
+        &app_state,
+    )
+    .await
+}
+
+///Generates the diagram type configured as `diagram.default_type`
+#[get("/diagram/{operation_id}")]
+pub async fn generate_default_diagram_for_operation(
+    operation_id: web::Path<String>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let diagram_type = app_state.settings.diagram.default_type;
+    generate_diagram(operation_id.into_inner(), diagram_type, &query, &app_state).await
+}
+
+///Page to explore the flowchart in the browser, with everything it needs inside
+#[get("/view/{operation_id}")]
+pub async fn view_operation(
+    operation_id: web::Path<String>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    generate_diagram(
+        operation_id.into_inner(),
+        DiagramType::Html,
+        &query,
+        &app_state,
+    )
+    .await
+}
+
+/**
+Merges the graphs of every operation with the name, created between `from` and `to` when the query sets them,
+into one call graph with call counts, distinct paths and min/avg/max durations. <br>
+Only the newest `limit` operations are merged, at most the server's `aggregate_max_operations`.
+Operations whose graph can't be built are listed as `skipped`.
+ */
+#[get("/aggregate/{operation_name}")]
+pub async fn aggregate_operations(
+    operation_name: web::Path<String>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let operation_name = operation_name.into_inner();
+    let (graph, skipped) = build_aggregate(&operation_name, &query, &app_state).await?;
+    Ok(HttpResponse::Ok().json(AggregateReport {
+        operation_name,
+        graph,
+        skipped,
+    }))
+}
+
+///Any diagram type of the merged call tree, every block labeled with its number of runs
+#[get("/aggregate/{operation_name}/diagram/{diagram_type}")]
+pub async fn generate_aggregate_diagram(
+    path: web::Path<(String, DiagramType)>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let (operation_name, diagram_type) = path.into_inner();
+    let (aggregate, _) = build_aggregate(&operation_name, &query, &app_state).await?;
+    let graph = app_state
         .services
-        .persistence
-        .vis_flow_log
-        .get_logs_by_operation_id(operation_id.into_inner())
+        .graph_aggregator
+        .graph_aggregator
+        .to_graph(&aggregate);
+    render_diagram(graph, diagram_type, &query, &app_state).await
+}
+
+/**
+Compares the graphs of two operations, like a good and a bad run, and lists the calls one of them made
+and the other didn't, the logs and stored values that differ and the calls that ended otherwise. <br>
+The query applies to both graphs.
+ */
+#[get("/diff/{before}/{after}")]
+pub async fn diff_operations(
+    path: web::Path<(String, String)>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let (before, after) = path.into_inner();
+    let before_graph = build_graph(before.clone(), &query, &app_state).await?;
+    let after_graph = build_graph(after.clone(), &query, &app_state).await?;
+    let diff = app_state
+        .services
+        .graph_differ
+        .graph_differ
+        .diff(&before_graph, &after_graph);
+    Ok(HttpResponse::Ok().json(DiffReport {
+        before,
+        after,
+        identical: diff.changes.is_empty(),
+        diff,
+    }))
+}
+
+/**
+Diagram of both operations, the newer one with what only the older one did put back. <br>
+Mermaid and DOT draw added calls and flows in green, removed ones in red and changed calls in orange.
+SVG and the viewer only fill the added and removed blocks, the other types don't mark them.
+ */
+#[get("/diff/{before}/{after}/diagram/{diagram_type}")]
+pub async fn generate_diff_diagram(
+    path: web::Path<(String, String, DiagramType)>,
+    query: web::Query<GraphQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let (before, after, diagram_type) = path.into_inner();
+    let before_graph = build_graph(before, &query, &app_state).await?;
+    let after_graph = build_graph(after, &query, &app_state).await?;
+    let graph = app_state
+        .services
+        .graph_differ
+        .graph_differ
+        .to_graph(&before_graph, &after_graph);
+    render_diagram(graph, diagram_type, &query, &app_state).await
+}
+
+async fn generate_diagram(
+    operation_id: String,
+    diagram_type: DiagramType,
+    query: &GraphQuery,
+    app_state: &AppState,
+) -> Result<HttpResponse, ApiError> {
+    let graph = build_graph(operation_id, query, app_state).await?;
+    render_diagram(graph, diagram_type, query, app_state).await
+}
+
+///Draws a graph with the diagram type and the options of the query, as text or as an image. <br>
+///Images are rasterized on the blocking thread pool, off the workers
+async fn render_diagram(
+    graph: HashMap<String, GGBlock>,
+    diagram_type: DiagramType,
+    query: &GraphQuery,
+    app_state: &AppState,
+) -> Result<HttpResponse, ApiError> {
+    let graph = graph
+        .into_iter()
+        .map(|(key, block)| (key, DGBlock::from(block)))
+        .collect::<HashMap<String, DGBlock>>();
+
+    let options = query.diagram_options(app_state.settings.diagram.options())?;
+    let diagram_type = match query.format {
+        DiagramFormat::Text => diagram_type,
+        DiagramFormat::Svg | DiagramFormat::Png => diagram_type.svg_type().ok_or_else(|| {
+            let name = diagram_type
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default();
+            DiagramError::UnsupportedFormat(format!("{} diagrams can't be drawn as an image", name))
+        })?,
+    };
+    let diagram = app_state
+        .services
+        .diagram_generator
+        .get(diagram_type)
+        .generate_diagram(graph, &options)?;
+    if query.format == DiagramFormat::Png {
+        let png = web::block(move || {
+            crate::services::diagram_generator::api::services::rasterize(&diagram)
+        })
         .await
-    {
-        Ok(logs) => logs,
-        Err(e) => {
-            return HttpResponse::InternalServerError().body(format!("Failed to get logs: {}", e))
-        }
+        .map_err(|e| DiagramError::RenderFailed(e.to_string()))??;
+        return Ok(HttpResponse::Ok().content_type("image/png").body(png));
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(diagram_type.content_type())
+        .body(diagram))
+}
+
+///Builds the graph of an operation, strict unless the query asks for the lenient mode. Loops are collapsed on request
+async fn build_graph(
+    operation_id: String,
+    query: &GraphQuery,
+    app_state: &AppState,
+) -> Result<HashMap<String, GGBlock>, ApiError> {
+    let entries = load_entries(operation_id, app_state).await?;
+    let graph_generator = &app_state.services.graph_generator.graph_generator;
+    let mut graph = if query.lenient {
+        graph_generator.generate_lenient_graph(entries)?
+    } else {
+        graph_generator.generate_graph(entries)?
     };
+    if query.collapse_loops {
+        graph_generator.collapse_loops(&mut graph);
+    }
+    Ok(graph)
+}
 
-    if let Some(invalid_log) = logs.iter().find(|log| {
-        !matches!(
-            log.log_type.as_str(),
-            "LOG" | "START" | "END" | "STORE" | "EXTERNAL_CALL" | "EXTERNAL_CALL_STORE"
+/**
+Builds the graph of the newest operations with the name in the query's time window and merges them. <br>
+Fails when no operation matches, or with the first error when none of their graphs could be built.
+ */
+async fn build_aggregate(
+    operation_name: &str,
+    query: &GraphQuery,
+    app_state: &AppState,
+) -> Result<(AggregateGraph, Vec<SkippedOperation>), ApiError> {
+    let max_operations = app_state.settings.server.aggregate_max_operations;
+    let operations = app_state
+        .services
+        .persistence
+        .vis_flow_op
+        .get_operations_named(
+            operation_name,
+            query.from,
+            query.to,
+            query
+                .limit
+                .unwrap_or(max_operations)
+                .clamp(1, max_operations),
         )
-    }) {
-        return HttpResponse::BadRequest()
-            .body(format!("Invalid log_type: {}", invalid_log.log_type));
+        .await?;
+    if operations.is_empty() {
+        return Err(PersistenceError::NoOperationsNamed {
+            name: operation_name.to_string(),
+            from: query.from,
+            to: query.to,
+        }
+        .into());
     }
-
-    let entries = logs
-        .iter()
-        .map(|log| VisFlowLogEntry {
-            log_type: match log.log_type.as_str() {
-                "LOG" => VisFlowLogEntryLogType::Log,
-                "START" => VisFlowLogEntryLogType::Start,
-                "END" => VisFlowLogEntryLogType::End,
-                "STORE" => VisFlowLogEntryLogType::Store,
-                "EXTERNAL_CALL" => VisFlowLogEntryLogType::ExternalCall,
-                "EXTERNAL_CALL_STORE" => VisFlowLogEntryLogType::ExternalCallStore,
-                _ => unreachable!(),
-            },
-            log_value: log.log_value.clone(),
-            block_name: log.block_name.clone(),
+    let mut graphs = Vec::new();
+    let mut errors = Vec::new();
+    for operation in operations {
+        match build_graph(operation.operation_id.clone(), query, app_state).await {
+            Ok(graph) => graphs.push((operation.operation_id, graph)),
+            Err(e) => errors.push((operation.operation_id, e)),
+        }
+    }
+    if graphs.is_empty() {
+        return Err(errors.remove(0).1);
+    }
+    let skipped = errors
+        .into_iter()
+        .map(|(operation_id, e)| SkippedOperation {
+            operation_id,
+            code: e.code(),
+            detail: e.to_string(),
         })
         .collect();
-
-    let graph = match app_state
+    let aggregate = app_state
         .services
-        .graph_generator
-        .graph_generator
-        .generate_graph(entries)
-    {
-        Ok(gg_graph) => gg_graph
-            .into_iter()
-            .map(|(key, block)| (key, DGBlock::from(block)))
-            .collect::<HashMap<String, DGBlock>>(),
-        Err(e) => {
-            return HttpResponse::InternalServerError()
-                .body(format!("Failed to generate graph: {}", e))
-        }
-    };
+        .graph_aggregator
+        .graph_aggregator
+        .aggregate(graphs);
+    Ok((aggregate, skipped))
+}
+
+///Reads the logs of an operation as graph entries
+async fn load_entries(
+    operation_id: String,
+    app_state: &AppState,
+) -> Result<Vec<VisFlowLogEntry>, ApiError> {
+    let logs = load_logs(operation_id, app_state).await?;
+    Ok(to_entries(logs)?)
+}
 
-    match app_state
+///An operation without logs is not found
+async fn load_logs(
+    operation_id: String,
+    app_state: &AppState,
+) -> Result<Vec<VisFlowLogEntity>, ApiError> {
+    let logs = app_state
         .services
-        .diagram_generator
-        .mermaid
-        .generate_diagram(graph)
-    {
-        Ok(diagram) => HttpResponse::Ok().body(diagram),
-        Err(e) => {
-            HttpResponse::InternalServerError().body(format!("Failed to generate diagram: {}", e))
-        }
+        .persistence
+        .vis_flow_log
+        .get_logs_by_operation_id(operation_id.clone())
+        .await?;
+    if logs.is_empty() {
+        return Err(PersistenceError::OperationNotFound(operation_id).into());
     }
+    Ok(logs)
+}
+
+fn to_entries(logs: Vec<VisFlowLogEntity>) -> Result<Vec<VisFlowLogEntry>, GraphError> {
+    logs.into_iter()
+        .map(|log| {
+            let log_type = VisFlowLogEntryLogType::from_log_type(&log.log_type)
+                .ok_or(GraphError::InvalidLogType(log.log_type))?;
+            Ok(VisFlowLogEntry {
+                log_type,
+                log_value: log.log_value,
+                block_name: log.block_name,
+                sequence: log.sequence,
+                span_id: log.span_id,
+                parent_span_id: log.parent_span_id,
+                timestamp: log.timestamp,
+            })
+        })
+        .collect()
 }
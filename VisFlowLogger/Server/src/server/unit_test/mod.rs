@@ -0,0 +1,449 @@
+use crate::config::settings::{DatabaseBackend, DatabaseSettings};
+use crate::server::ndjson::{LineSplitter, NdjsonIngestor, LINES_PER_CHUNK};
+
+#[test]
+fn splitter_handles_lines_across_chunks() {
+    let mut splitter = LineSplitter::new(64);
+    assert!(splitter.push(b"{\"a\":").unwrap().is_empty());
+    let lines = splitter.push(b"1}\r\n{\"b\":2}\n{\"c\"").unwrap();
+    assert_eq!(lines, vec![b"{\"a\":1}".to_vec(), b"{\"b\":2}".to_vec()]);
+    assert!(splitter.push(b":3}").unwrap().is_empty());
+    assert_eq!(splitter.finish(), Some(b"{\"c\":3}".to_vec()));
+
+    let mut splitter = LineSplitter::new(4);
+    assert!(splitter.push(b"abc\nabcde").is_err());
+}
+
+#[tokio::test]
+async fn ingestor_saves_in_chunks_and_reports_bad_lines() {
+    let persistence = crate::services::persistence::new(&DatabaseSettings {
+        backend: DatabaseBackend::Memory,
+        ..DatabaseSettings::default()
+    })
+    .await
+    .unwrap();
+    let mut ingestor = NdjsonIngestor::new(&persistence);
+
+    let total = LINES_PER_CHUNK + 10;
+    for sequence in 0..total {
+        let line = format!(
+            r#"{{"operation_id":"op","operation_name":"batch","block_name":"main","log_type":"LOG","log_value":"v","sequence":{}}}"#,
+            sequence
+        );
+        ingestor.line(line.as_bytes()).await.unwrap();
+    }
+    ingestor.line(b"").await.unwrap();
+    ingestor.line(b"not json").await.unwrap();
+    ingestor
+        .line(br#"{"operation_id":"op","block_name":"main","log_type":"NOPE","log_value":null,"sequence":1}"#)
+        .await
+        .unwrap();
+    ingestor.flush().await.unwrap();
+
+    let report = ingestor.into_report(None);
+    assert_eq!(report.lines, total + 3);
+    assert_eq!(report.saved, total);
+    assert_eq!(report.rejected, 2);
+    let lines: Vec<usize> = report.errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, vec![total + 2, total + 3]);
+
+    let saved = persistence
+        .vis_flow_log
+        .get_logs_by_operation_id(String::from("op"))
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), total);
+    let operations = persistence.vis_flow_op.get_operations().await.unwrap();
+    assert_eq!(operations.len(), 1);
+    assert_eq!(operations[0].operation_name, "batch");
+}
+
+#[test]
+fn names_are_limited_in_characters_not_bytes() {
+    use crate::server::ndjson::parse_line;
+    let line = |block_name: &str| {
+        format!(
+            r#"{{"operation_id":"op","block_name":"{}","log_type":"LOG","log_value":null,"sequence":1}}"#,
+            block_name
+        )
+    };
+    let name = "名".repeat(100);
+    assert_eq!(name.len(), 300);
+    assert_eq!(
+        parse_line(line(&name).as_bytes()).unwrap().log.block_name,
+        name
+    );
+    assert_eq!(
+        parse_line(line(&"名".repeat(101)).as_bytes())
+            .err()
+            .unwrap(),
+        "block_name must be between 1 and 100 characters"
+    );
+}
+
+#[test]
+fn errors_map_to_problem_responses() {
+    use crate::server::models::api_error::ApiError;
+    use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+    use crate::services::graph_generator::api::models::graph_error::GraphError;
+    use crate::services::persistence::api::model::persistence_error::PersistenceError;
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+
+    let cases: Vec<(ApiError, StatusCode, &str)> = vec![
+        (
+            PersistenceError::OperationNotFound("op".to_string()).into(),
+            StatusCode::NOT_FOUND,
+            "OperationNotFound",
+        ),
+        (
+            PersistenceError::NoOperationsNamed {
+                name: "checkout".to_string(),
+                from: None,
+                to: None,
+            }
+            .into(),
+            StatusCode::NOT_FOUND,
+            "NoOperationsNamed",
+        ),
+        (
+            PersistenceError::from_sqlx("Error fetching logs", sqlx::Error::PoolTimedOut).into(),
+            StatusCode::SERVICE_UNAVAILABLE,
+            "DbUnavailable",
+        ),
+        (
+            PersistenceError::from_sqlx("Error fetching logs", sqlx::Error::RowNotFound).into(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "QueryFailed",
+        ),
+        (
+            GraphError::StoreWithoutCall {
+                block_name: "main".to_string(),
+            }
+            .into(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "StoreWithoutCall",
+        ),
+        (
+            DiagramError::MissingStartBlock.into(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "MissingStartBlock",
+        ),
+        (
+            DiagramError::UnsupportedFormat("folded".to_string()).into(),
+            StatusCode::BAD_REQUEST,
+            "UnsupportedFormat",
+        ),
+        (
+            DiagramError::InvalidOption("red is not a color".to_string()).into(),
+            StatusCode::BAD_REQUEST,
+            "InvalidOption",
+        ),
+    ];
+    for (error, status, code) in cases {
+        assert_eq!(error.status_code(), status);
+        let problem = error.problem();
+        assert_eq!(problem.status, status.as_u16());
+        assert_eq!(problem.code, code);
+        let response = error.error_response();
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/problem+json"
+        );
+    }
+}
+
+///App state over the in-memory backend with every service, like the one built in `main`
+async fn app_state() -> crate::server::models::app_state::AppState {
+    use crate::server::models::app_state::{AppState, AvailableServices};
+    use crate::services::diagram_generator::api::services::{new, DiagramType};
+    use std::sync::Arc;
+
+    let settings = crate::config::settings::Settings {
+        database: DatabaseSettings {
+            backend: DatabaseBackend::Memory,
+            ..DatabaseSettings::default()
+        },
+        ..Default::default()
+    };
+    AppState {
+        services: AvailableServices {
+            persistence: crate::services::persistence::new(&settings.database)
+                .await
+                .unwrap(),
+            graph_generator: crate::services::graph_generator::AvailableServices {
+                graph_generator: Arc::new(
+                    crate::services::graph_generator::api::services::graph_generator::new(),
+                ),
+            },
+            diagram_generator: crate::services::diagram_generator::AvailableServices {
+                mermaid: new(DiagramType::Mermaid),
+                mermaid_sequence: new(DiagramType::MermaidSequence),
+                plantuml: new(DiagramType::Plantuml),
+                dot: new(DiagramType::Dot),
+                folded: new(DiagramType::Folded),
+                flamegraph: new(DiagramType::Flamegraph),
+                svg: new(DiagramType::Svg),
+                html: new(DiagramType::Html),
+            },
+            graph_aggregator: crate::services::graph_aggregator::AvailableServices {
+                graph_aggregator: Arc::new(
+                    crate::services::graph_aggregator::api::services::graph_aggregator::new(),
+                ),
+            },
+            graph_differ: crate::services::graph_differ::AvailableServices {
+                graph_differ: Arc::new(
+                    crate::services::graph_differ::api::services::graph_differ::new(),
+                ),
+            },
+        },
+        settings,
+    }
+}
+
+///Test service with every route of `main`, its operations posted to `/` first
+macro_rules! service_with {
+    ($($operation:expr),* $(,)?) => {{
+        use crate::server::route;
+        let app = actix_web::test::init_service(
+            actix_web::App::new()
+                .app_data(actix_web::web::Data::new(app_state().await))
+                .service(route::save_logs)
+                .service(route::save_logs_ndjson)
+                .service(route::get_logs_by_operation_id)
+                .service(route::get_operations)
+                .service(route::get_graphs_by_operation_id)
+                .service(route::validate_operation)
+                .service(route::generate_diagram_for_operation)
+                .service(route::generate_mermaid_sequence_diagram_for_operation)
+                .service(route::generate_plantuml_diagram_for_operation)
+                .service(route::generate_dot_diagram_for_operation)
+                .service(route::generate_folded_stacks_for_operation)
+                .service(route::generate_flame_graph_for_operation)
+                .service(route::generate_default_diagram_for_operation)
+                .service(route::view_operation)
+                .service(route::aggregate_operations)
+                .service(route::generate_aggregate_diagram)
+                .service(route::diff_operations)
+                .service(route::generate_diff_diagram),
+        )
+        .await;
+        $(
+            let request = actix_web::test::TestRequest::post()
+                .uri("/")
+                .set_json($operation)
+                .to_request();
+            let response = actix_web::test::call_service(&app, request).await;
+            assert!(response.status().is_success());
+        )*
+        app
+    }};
+}
+
+///Payload of one operation, every log as `(log_type, block_name, timestamp)`
+fn operation(
+    operation_id: &str,
+    operation_name: &str,
+    logs: &[(&str, &str, Option<&str>)],
+) -> serde_json::Value {
+    let logs: Vec<serde_json::Value> = logs
+        .iter()
+        .enumerate()
+        .map(|(sequence, (log_type, block_name, timestamp))| {
+            serde_json::json!({
+                "operation_id": operation_id,
+                "block_name": block_name,
+                "log_type": log_type,
+                "log_value": "",
+                "sequence": sequence,
+                "timestamp": timestamp,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "operation": [{
+            "operation_id": operation_id,
+            "operation_name": operation_name,
+            "logs": logs,
+        }]
+    })
+}
+
+const MAIN_CALLS_LOAD: &[(&str, &str, Option<&str>)] = &[
+    ("START", "main", None),
+    ("START", "load", None),
+    ("LOG", "load", None),
+    ("END", "load", None),
+    ("END", "main", None),
+];
+
+#[actix_web::test]
+async fn diagram_routes_draw_svg_and_png() {
+    use actix_web::test::{call_service, read_body, TestRequest};
+
+    let app = service_with!(operation("op", "checkout", MAIN_CALLS_LOAD));
+
+    let request = TestRequest::get()
+        .uri("/diagram/mermaid/op?format=svg")
+        .to_request();
+    let response = call_service(&app, request).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "image/svg+xml"
+    );
+    let body = read_body(response).await;
+    assert!(body.starts_with(b"<svg"));
+    assert!(String::from_utf8_lossy(&body).contains("load"));
+
+    let request = TestRequest::get()
+        .uri("/diagram/mermaid/op?format=png")
+        .to_request();
+    let response = call_service(&app, request).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "image/png");
+    assert!(read_body(response).await.starts_with(b"\x89PNG\r\n\x1a\n"));
+}
+
+#[actix_web::test]
+async fn diagram_routes_reject_images_of_text_only_types() {
+    use actix_web::test::{call_and_read_body_json, TestRequest};
+
+    let app = service_with!(operation("op", "checkout", MAIN_CALLS_LOAD));
+    let request = TestRequest::get()
+        .uri("/diagram/folded/op?format=png")
+        .to_request();
+    let problem: serde_json::Value = call_and_read_body_json(&app, request).await;
+    assert_eq!(problem["status"], 400);
+    assert_eq!(problem["code"], "UnsupportedFormat");
+}
+
+#[actix_web::test]
+async fn view_route_sends_the_viewer_page() {
+    use actix_web::test::{call_service, read_body, TestRequest};
+
+    let app = service_with!(operation("op", "checkout", MAIN_CALLS_LOAD));
+    let response = call_service(&app, TestRequest::get().uri("/view/op").to_request()).await;
+    assert_eq!(response.status(), 200);
+    assert!(response
+        .headers()
+        .get("content-type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    let page = String::from_utf8(read_body(response).await.to_vec()).unwrap();
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(page.contains("<title>VisFlow · main</title>"));
+
+    let response = call_service(&app, TestRequest::get().uri("/view/missing").to_request()).await;
+    assert_eq!(response.status(), 404);
+}
+
+#[actix_web::test]
+async fn aggregate_routes_merge_the_newest_operations_in_the_window() {
+    use actix_web::test::{call_and_read_body_json, call_service, read_body, TestRequest};
+
+    let app = service_with!(
+        operation(
+            "broken",
+            "checkout",
+            &[("START", "main", None), ("END", "load", None)]
+        ),
+        operation("first", "checkout", MAIN_CALLS_LOAD),
+        operation("second", "checkout", MAIN_CALLS_LOAD),
+        operation("other", "refund", MAIN_CALLS_LOAD),
+    );
+    let aggregate = |uri: &str| TestRequest::get().uri(uri).to_request();
+
+    let report: serde_json::Value =
+        call_and_read_body_json(&app, aggregate("/aggregate/checkout")).await;
+    let mut operations = report["operations"].as_array().unwrap().clone();
+    operations.sort_by_key(|id| id.to_string());
+    assert_eq!(operations, vec!["first", "second"]);
+    assert_eq!(report["skipped"].as_array().unwrap().len(), 1);
+    assert_eq!(report["skipped"][0]["operation_id"], "broken");
+
+    let report: serde_json::Value = call_and_read_body_json(
+        &app,
+        aggregate("/aggregate/checkout?limit=1&from=2000-01-01T00:00:00&to=2999-01-01T00:00:00"),
+    )
+    .await;
+    assert_eq!(
+        report["operations"],
+        serde_json::json!(["second"]),
+        "only the newest operation is merged"
+    );
+    assert_eq!(report["skipped"], serde_json::json!([]));
+
+    for uri in [
+        "/aggregate/checkout?from=2999-01-01T00:00:00",
+        "/aggregate/checkout?to=2000-01-01T00:00:00",
+        "/aggregate/missing",
+    ] {
+        let problem: serde_json::Value = call_and_read_body_json(&app, aggregate(uri)).await;
+        assert_eq!(problem["status"], 404, "{}", uri);
+        assert_eq!(problem["code"], "NoOperationsNamed", "{}", uri);
+    }
+
+    let response = call_service(&app, aggregate("/aggregate/checkout/diagram/mermaid")).await;
+    assert_eq!(response.status(), 200);
+    let diagram = String::from_utf8(read_body(response).await.to_vec()).unwrap();
+    assert!(diagram.contains("load"));
+}
+
+#[actix_web::test]
+async fn diff_routes_compare_two_operations() {
+    use actix_web::test::{call_and_read_body_json, call_service, read_body, TestRequest};
+
+    let app = service_with!(
+        operation("good", "checkout", MAIN_CALLS_LOAD),
+        operation(
+            "bad",
+            "checkout",
+            &[
+                ("START", "main", None),
+                ("START", "retry", None),
+                ("END", "retry", None),
+                ("END", "main", None),
+            ]
+        ),
+    );
+    let get = |uri: &str| TestRequest::get().uri(uri).to_request();
+
+    let report: serde_json::Value = call_and_read_body_json(&app, get("/diff/good/bad")).await;
+    assert_eq!(report["before"], "good");
+    assert_eq!(report["after"], "bad");
+    assert_eq!(report["identical"], false);
+    let changes: Vec<(&str, &str)> = report["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| {
+            (
+                change["change"].as_str().unwrap(),
+                change["path"][1].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        changes,
+        vec![("RemovedCall", "load"), ("AddedCall", "retry")]
+    );
+
+    let report: serde_json::Value = call_and_read_body_json(&app, get("/diff/good/good")).await;
+    assert_eq!(report["identical"], true);
+
+    let response = call_service(&app, get("/diff/good/bad/diagram/mermaid")).await;
+    assert_eq!(response.status(), 200);
+    let diagram = String::from_utf8(read_body(response).await.to_vec()).unwrap();
+    assert!(diagram.contains("load") && diagram.contains("retry"));
+
+    let response = call_service(&app, get("/diff/good/bad/diagram/svg")).await;
+    assert_eq!(response.status(), 200);
+    assert!(read_body(response).await.starts_with(b"<svg"));
+
+    let problem: serde_json::Value = call_and_read_body_json(&app, get("/diff/good/missing")).await;
+    assert_eq!(problem["status"], 404);
+    assert_eq!(problem["code"], "OperationNotFound");
+}
@@ -7,15 +7,51 @@ pub struct DGBlock {
     pub caller: Option<String>,
     pub name: String,
     pub flow: Vec<DGBlockFlow>,
+    /// The block never ended and was closed by the lenient graph mode.
+    #[serde(default)]
+    pub aborted: bool,
+    /// The block threw or logged an error.
+    #[serde(default)]
+    pub failed: bool,
+    #[serde(default)]
+    pub kind: DGBlockKind,
+    /// Span the block ran in, if its entries named one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    /// Set when the client sent times for the START and END of the block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<DGBlockDuration>,
+    /// Runs of the block it stands for, drawn as `×N`: calls to itself folded by the diagram options,
+    /// or the calls of every operation merged into an aggregated graph.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runs: Option<usize>,
+    /// Blocks below it the diagram options left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<usize>,
+    /// Set in the graph of a diff, drawn in the added, removed or changed colors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<DGBlockChange>,
+}
+
+impl DGBlock {
+    /// Name of the block in stacks and titles. A loop is named by its label.
+    pub fn frame(&self) -> String {
+        match self.kind {
+            DGBlockKind::Block => self.name.clone(),
+            DGBlockKind::Loop => format!("loop: {}", self.name),
+        }
+    }
 }
 
 impl Display for DGBlock {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "VisFlowBlock {{ caller: {:?}, name: {}, flow: [{}] }}",
+            "VisFlowBlock {{ caller: {:?}, name: {}, aborted: {}, failed: {}, flow: [{}] }}",
             self.caller,
             self.name,
+            self.aborted,
+            self.failed,
             self.flow
                 .iter()
                 .map(|b| format!("{}", b))
@@ -25,12 +61,50 @@ impl Display for DGBlock {
     }
 }
 
+/// How long a block ran, in microseconds.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct DGBlockDuration {
+    /// From START to END, including the blocks it called.
+    pub inclusive_us: u64,
+    /// Time spent in the block itself.
+    pub exclusive_us: u64,
+}
+
+impl DGBlockDuration {
+    /// Label like `12.3 ms (self 2.1 ms)`.
+    pub fn label(&self) -> String {
+        if self.inclusive_us == self.exclusive_us {
+            return format_us(self.inclusive_us);
+        }
+        format!(
+            "{} (self {})",
+            format_us(self.inclusive_us),
+            format_us(self.exclusive_us)
+        )
+    }
+}
+
+/// Microseconds in the largest unit that keeps them above 1, like `850 µs` or `1.25 s`.
+pub fn format_us(us: u64) -> String {
+    match us {
+        0..1_000 => format!("{} µs", us),
+        1_000..1_000_000 => format!("{:.1} ms", us as f64 / 1_000.0),
+        _ => format!("{:.2} s", us as f64 / 1_000_000.0),
+    }
+}
+
 #[derive(Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct DGBlockFlow {
     pub flow_pointer_id: Option<String>,
     pub flow_id: String,
     pub flow_type: DGBlockFlowType,
     pub value: Option<String>,
+    /// Sequence number of the entry that added the flow. Calls have the one that started the block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u32>,
+    /// Set in the graph of a diff, drawn in the added, removed or changed colors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<DGBlockChange>,
 }
 
 impl Display for DGBlockFlow {
@@ -43,13 +117,27 @@ impl Display for DGBlockFlow {
     }
 }
 
-#[derive(Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum DGBlockFlowType {
     Call,
     CallStore,
     Log,
     ExternalCall,
     ExternalCallStore,
+    /// Stored value without a preceding call, kept by the lenient graph mode.
+    Store,
+    /// Call of a block that ran in another span, concurrently with the rest of the caller.
+    AsyncCall,
+    /// Error logged by the block.
+    Error,
+    /// Call of a block that threw. The value is the error.
+    CallError,
+    /// Decision taken by the block. The value is the condition.
+    Branch,
+    /// Call of a loop block. The value is the number of iterations.
+    Loop,
+    /// Start of the next iteration in a loop block.
+    LoopIteration,
 }
 
 impl Display for DGBlockFlowType {
@@ -60,7 +148,33 @@ impl Display for DGBlockFlowType {
             DGBlockFlowType::Log => "Log",
             DGBlockFlowType::ExternalCall => "ExternalCall",
             DGBlockFlowType::ExternalCallStore => "ExternalCallStore",
+            DGBlockFlowType::Store => "Store",
+            DGBlockFlowType::AsyncCall => "AsyncCall",
+            DGBlockFlowType::Error => "Error",
+            DGBlockFlowType::CallError => "CallError",
+            DGBlockFlowType::Branch => "Branch",
+            DGBlockFlowType::Loop => "Loop",
+            DGBlockFlowType::LoopIteration => "LoopIteration",
         };
         write!(f, "{}", type_str)
     }
 }
+
+/// A function call, or a loop inside one. The name of a loop is its label.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum DGBlockKind {
+    #[default]
+    Block,
+    Loop,
+}
+
+/// How a block or flow of a diff differs between the older and the newer operation.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum DGBlockChange {
+    /// Only in the newer operation.
+    Added,
+    /// Only in the older operation.
+    Removed,
+    /// A call of both operations with another value or outcome, shown as in the newer one.
+    Changed,
+}
@@ -0,0 +1,47 @@
+use std::fmt::Display;
+
+///Why a graph couldn't be turned into a diagram
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiagramError {
+    ///Every graph needs a block with the key "START"
+    MissingStartBlock,
+    ///A call points to a block ID that isn't in the graph
+    UnknownBlock(String),
+    ///The diagram type can't be sent in the requested format
+    UnsupportedFormat(String),
+    ///The SVG couldn't be turned into an image
+    RenderFailed(String),
+    ///A theme, color or shape given with the request or in the settings isn't valid
+    InvalidOption(String),
+}
+
+impl DiagramError {
+    ///Machine-readable name of the error, stable across releases
+    pub fn code(&self) -> &'static str {
+        match self {
+            DiagramError::MissingStartBlock => "MissingStartBlock",
+            DiagramError::UnknownBlock(_) => "UnknownBlock",
+            DiagramError::UnsupportedFormat(_) => "UnsupportedFormat",
+            DiagramError::RenderFailed(_) => "RenderFailed",
+            DiagramError::InvalidOption(_) => "InvalidOption",
+        }
+    }
+}
+
+impl Display for DiagramError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiagramError::MissingStartBlock => write!(f, "Graph has no START block"),
+            DiagramError::UnknownBlock(block_id) => {
+                write!(f, "A call points to unknown block {}", block_id)
+            }
+            DiagramError::UnsupportedFormat(reason) => write!(f, "{}", reason),
+            DiagramError::RenderFailed(reason) => {
+                write!(f, "Couldn't render the diagram: {}", reason)
+            }
+            DiagramError::InvalidOption(reason) => write!(f, "Invalid diagram option: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for DiagramError {}
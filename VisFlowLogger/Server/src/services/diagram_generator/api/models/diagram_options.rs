@@ -0,0 +1,131 @@
+use crate::services::diagram_generator::api::models::block::DGBlockFlowType;
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use serde::de::value::{Error, StrDeserializer};
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+///Per-request settings every diagram type reads
+#[derive(Debug, Default, Clone)]
+pub struct DiagramOptions {
+    ///Mixed into the hash that picks block colors, so another seed gives another palette
+    pub seed: Option<u64>,
+    pub theme: Theme,
+    ///Direction of the flows in flowcharts. Sequence diagrams and flame graphs have their own.
+    pub direction: Direction,
+    ///Fill of blocks by name, instead of the theme's
+    pub block_colors: HashMap<String, String>,
+    ///Node shape of a flow type in flowcharts, instead of the default one
+    pub shapes: HashMap<DGBlockFlowType, Shape>,
+    ///Flowchart labels longer than this many characters are shortened, with the full text in a tooltip. 0 keeps them whole.
+    pub max_label: usize,
+    ///Levels of calls drawn below START. A block at the last level that calls others is drawn without its flows.
+    pub max_depth: Option<usize>,
+    ///Folds the calls of a block to itself into its first one, drawn as `×N`
+    pub collapse_recursion: bool,
+    ///Leaves `Log` flows out
+    pub hide_logs: bool,
+    ///Block ID or name whose call tree is drawn instead of the whole graph
+    pub focus: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+    ///Black background with white lines and text
+    HighContrast,
+    ///Okabe-Ito colors, which stay apart with every kind of color blindness
+    ColorBlind,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+pub enum Direction {
+    ///Top to bottom
+    #[default]
+    #[serde(rename = "TB", alias = "tb")]
+    #[value(name = "TB", alias = "tb")]
+    Tb,
+    ///Left to right
+    #[serde(rename = "LR", alias = "lr")]
+    #[value(name = "LR", alias = "lr")]
+    Lr,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Tb => "TB",
+            Direction::Lr => "LR",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Shape {
+    Rectangle,
+    Rounded,
+    Stadium,
+    Parallelogram,
+    Trapezoid,
+    InvertedTrapezoid,
+    Diamond,
+    Hexagon,
+    Circle,
+    ///Rectangle with a pointed right side
+    Flag,
+    ///Rectangle with a double border
+    Subroutine,
+}
+
+impl DiagramOptions {
+    ///Parses block colors given as `name:#RRGGBB,other:#RGB`
+    pub fn parse_block_colors(text: &str) -> Result<HashMap<String, String>, DiagramError> {
+        pairs(text)?
+            .map(|(name, color)| {
+                if !is_color(color) {
+                    return Err(invalid(format!("{} is not a #RGB or #RRGGBB color", color)));
+                }
+                Ok((name.to_string(), color.to_string()))
+            })
+            .collect()
+    }
+
+    ///Parses shapes given as `Call:hexagon,Log:stadium`
+    pub fn parse_shapes(text: &str) -> Result<HashMap<DGBlockFlowType, Shape>, DiagramError> {
+        pairs(text)?
+            .map(|(flow_type, shape)| Ok((from_name(flow_type)?, from_name(shape)?)))
+            .collect()
+    }
+}
+
+///Colors end up in every diagram syntax, so only plain hex colors are accepted
+pub fn is_color(text: &str) -> bool {
+    text.strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn pairs(text: &str) -> Result<impl Iterator<Item = (&str, &str)>, DiagramError> {
+    let pairs: Vec<(&str, &str)> = text
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            pair.rsplit_once(':')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| invalid(format!("{} is not a key:value pair", pair)))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(pairs.into_iter())
+}
+
+fn from_name<'a, T: Deserialize<'a>>(name: &'a str) -> Result<T, DiagramError> {
+    let deserializer: StrDeserializer<'a, Error> = name.into_deserializer();
+    T::deserialize(deserializer).map_err(|e| invalid(e.to_string()))
+}
+
+fn invalid(reason: String) -> DiagramError {
+    DiagramError::InvalidOption(reason)
+}
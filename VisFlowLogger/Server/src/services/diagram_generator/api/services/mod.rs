@@ -1,17 +1,103 @@
 use crate::services::diagram_generator::api::models::block::DGBlock;
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::DiagramOptions;
+use crate::services::diagram_generator::internal::application::dot_diagram_generator::DotDiagramGenerator;
+use crate::services::diagram_generator::internal::application::flame_graph_diagram_generator::FlameGraphDiagramGenerator;
+use crate::services::diagram_generator::internal::application::folded_stack_diagram_generator::FoldedStackDiagramGenerator;
+use crate::services::diagram_generator::internal::application::html_diagram_generator::HtmlDiagramGenerator;
 use crate::services::diagram_generator::internal::application::mermaid_diagram_generator::MermaidDiagramGenerator;
+use crate::services::diagram_generator::internal::application::mermaid_sequence_diagram_generator::MermaidSequenceDiagramGenerator;
+use crate::services::diagram_generator::internal::application::plantuml_diagram_generator::PlantUmlDiagramGenerator;
+use crate::services::diagram_generator::internal::application::png_rasterizer;
+use crate::services::diagram_generator::internal::application::svg_diagram_generator::SvgDiagramGenerator;
 use async_trait::async_trait;
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[async_trait]
 pub trait DiagramGenerator: Send + Sync {
-    fn generate_diagram(&self, graph: HashMap<String, DGBlock>) -> Result<String, String>;
+    fn generate_diagram(
+        &self,
+        graph: HashMap<String, DGBlock>,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError>;
 }
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
 pub enum DiagramType {
     Mermaid,
+    ///Mermaid `sequenceDiagram`
+    #[serde(rename = "mermaid-sequence")]
+    MermaidSequence,
+    ///PlantUML sequence diagram
+    Plantuml,
+    ///Graphviz DOT
+    Dot,
+    ///Folded stacks, one line per call path with its weight
+    Folded,
+    ///SVG flame graph
+    Flamegraph,
+    ///Flowchart laid out and drawn as SVG by the server
+    Svg,
+    ///Page with the SVG flowchart, to pan, zoom and collapse in the browser
+    Html,
 }
-pub fn new(diagram_type: DiagramType) -> impl DiagramGenerator {
+
+///How a diagram is sent: its own syntax, or drawn by the server
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagramFormat {
+    #[default]
+    Text,
+    Svg,
+    Png,
+}
+
+impl DiagramType {
+    ///Content type of the generated diagram
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            DiagramType::Mermaid
+            | DiagramType::MermaidSequence
+            | DiagramType::Plantuml
+            | DiagramType::Folded => "text/plain; charset=utf-8",
+            DiagramType::Dot => "text/vnd.graphviz; charset=utf-8",
+            DiagramType::Flamegraph | DiagramType::Svg => "image/svg+xml",
+            DiagramType::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    /**
+    Type that draws this diagram as SVG. <br>
+    Flowcharts are drawn by the server's own layout, flame graphs already are SVG.
+    Sequence diagrams and folded stacks can't be drawn.
+     */
+    pub fn svg_type(&self) -> Option<DiagramType> {
+        match self {
+            DiagramType::Mermaid | DiagramType::Dot | DiagramType::Svg | DiagramType::Html => {
+                Some(DiagramType::Svg)
+            }
+            DiagramType::Flamegraph => Some(DiagramType::Flamegraph),
+            DiagramType::MermaidSequence | DiagramType::Plantuml | DiagramType::Folded => None,
+        }
+    }
+}
+
+pub fn new(diagram_type: DiagramType) -> Arc<dyn DiagramGenerator> {
     match diagram_type {
-        DiagramType::Mermaid => MermaidDiagramGenerator {},
+        DiagramType::Mermaid => Arc::new(MermaidDiagramGenerator {}),
+        DiagramType::MermaidSequence => Arc::new(MermaidSequenceDiagramGenerator {}),
+        DiagramType::Plantuml => Arc::new(PlantUmlDiagramGenerator {}),
+        DiagramType::Dot => Arc::new(DotDiagramGenerator {}),
+        DiagramType::Folded => Arc::new(FoldedStackDiagramGenerator {}),
+        DiagramType::Flamegraph => Arc::new(FlameGraphDiagramGenerator {}),
+        DiagramType::Svg => Arc::new(SvgDiagramGenerator {}),
+        DiagramType::Html => Arc::new(HtmlDiagramGenerator {}),
     }
 }
+
+///Renders an SVG diagram to PNG in process, with the system fonts
+pub fn rasterize(svg: &str) -> Result<Vec<u8>, DiagramError> {
+    png_rasterizer::rasterize(svg)
+}
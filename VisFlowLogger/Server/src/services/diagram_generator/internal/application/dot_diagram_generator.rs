@@ -0,0 +1,266 @@
+use crate::services::diagram_generator::api::models::block::{
+    DGBlock, DGBlockFlow, DGBlockFlowType,
+};
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::DiagramOptions;
+use crate::services::diagram_generator::api::models::diagram_options::Shape;
+use crate::services::diagram_generator::api::services::DiagramGenerator;
+use crate::services::diagram_generator::internal::common::escape::{dot, truncate};
+use crate::services::diagram_generator::internal::common::filter::filter;
+use crate::services::diagram_generator::internal::common::label::{called_block, flow_label};
+use crate::services::diagram_generator::internal::common::style::{
+    block_colors, block_title, change_colors, flow_shape, is_failure, palette,
+};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// Graphviz DOT with the same clusters, shapes and links as the Mermaid diagram
+pub struct DotDiagramGenerator;
+
+impl DotDiagramGenerator {
+    ///Node of a flow, as `shape`, `style` and `label` attributes
+    fn node(
+        graph: &HashMap<String, DGBlock>,
+        flow: &DGBlockFlow,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError> {
+        let palette = palette(options.theme);
+        let shape = match flow_shape(flow.flow_type, options) {
+            Shape::Rectangle => "shape=box",
+            Shape::Rounded | Shape::Stadium => "shape=box, style=\"rounded,filled\"",
+            Shape::Parallelogram => "shape=parallelogram",
+            Shape::Trapezoid => "shape=trapezium",
+            Shape::InvertedTrapezoid => "shape=invtrapezium",
+            Shape::Diamond => "shape=diamond",
+            Shape::Hexagon => "shape=hexagon",
+            Shape::Circle => "shape=circle",
+            Shape::Flag => "shape=cds",
+            Shape::Subroutine => "shape=box, peripheries=2",
+        };
+        let style = if shape.contains("style=") {
+            ""
+        } else {
+            ", style=filled"
+        };
+        let colors = if let Some(change) = flow.change {
+            // What a diff changed is told apart first
+            let (border, fill) = change_colors(change, palette);
+            format!(", fillcolor=\"{}\", color=\"{}\", penwidth=2", fill, border)
+        } else if is_failure(flow.flow_type) {
+            format!(
+                ", fillcolor=\"{}\", color=\"{}\", fontcolor=\"{}\"",
+                palette.failed_fill, palette.failed, palette.failed_text
+            )
+        } else {
+            format!(", fillcolor=\"{}\"", palette.node)
+        };
+        Ok(format!(
+            "{}{}{}, label={}",
+            shape,
+            style,
+            colors,
+            label(&flow_label(graph, flow)?, options)
+        ))
+    }
+
+    ///Node the links into a block point at. Empty blocks have a placeholder named after the block.
+    fn entry_node<'a>(block_id: &'a str, block: &'a DGBlock) -> &'a str {
+        block.flow.first().map_or(block_id, |f| f.flow_id.as_str())
+    }
+
+    fn exit_node<'a>(block_id: &'a str, block: &'a DGBlock) -> &'a str {
+        block.flow.last().map_or(block_id, |f| f.flow_id.as_str())
+    }
+}
+
+#[async_trait]
+impl DiagramGenerator for DotDiagramGenerator {
+    fn generate_diagram(
+        &self,
+        graph: HashMap<String, DGBlock>,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError> {
+        let graph = filter(graph, options)?;
+        let starting_block = graph.get("START").ok_or(DiagramError::MissingStartBlock)?;
+        let palette = palette(options.theme);
+        let colors = block_colors(&graph, options);
+        let mut block_ids: Vec<&String> = graph.keys().collect();
+        block_ids.sort();
+
+        let mut syntax = String::from("digraph VisFlow {\n");
+        syntax += &format!(
+            "\trankdir={};\n\tcompound=true;\n",
+            options.direction.as_str()
+        );
+        syntax += &format!(
+            "\tbgcolor=\"{}\";\n\tfontcolor=\"{}\";\n",
+            palette.background, palette.text
+        );
+        syntax += &format!(
+            "\tnode [fontname=\"Helvetica\", fontsize=11, color=\"{l}\", fontcolor=\"{t}\"];\n",
+            l = palette.line,
+            t = palette.text
+        );
+        syntax += &format!(
+            "\tedge [fontname=\"Helvetica\", fontsize=10, color=\"{l}\", fontcolor=\"{t}\"];\n",
+            l = palette.line,
+            t = palette.text
+        );
+
+        for block_id in &block_ids {
+            let block = &graph[*block_id];
+            let mut style = vec!["filled"];
+            if block.aborted {
+                // Blocks that never ended get a dashed border
+                style.push("dashed");
+            }
+            syntax += &format!("\tsubgraph {} {{\n", dot(&format!("cluster_{}", block_id)));
+            syntax += &format!(
+                "\t\tlabel={};\n\t\tstyle={};\n\t\tfillcolor=\"{}\";\n",
+                dot(&block_title(block)),
+                dot(&style.join(",")),
+                colors[*block_id]
+            );
+            if let Some(change) = block.change {
+                syntax += &format!(
+                    "\t\tcolor=\"{}\";\n\t\tpenwidth=3;\n",
+                    change_colors(change, palette).0
+                );
+            } else if block.failed {
+                syntax += &format!("\t\tcolor=\"{}\";\n\t\tpenwidth=3;\n", palette.failed);
+            }
+            if block.flow.is_empty() {
+                syntax += &format!("\t\t{} [shape=point, style=invis];\n", dot(block_id));
+            }
+            for flow in &block.flow {
+                syntax += &format!(
+                    "\t\t{} [{}];\n",
+                    dot(&flow.flow_id),
+                    Self::node(&graph, flow, options)?
+                );
+            }
+            // Basic internal flow arrow
+            for pair in block.flow.windows(2) {
+                let failed = matches!(
+                    pair[1].flow_type,
+                    DGBlockFlowType::Error | DGBlockFlowType::CallError
+                );
+                syntax += &format!(
+                    "\t\t{} -> {} [penwidth=2{}];\n",
+                    dot(&pair[0].flow_id),
+                    dot(&pair[1].flow_id),
+                    if failed {
+                        format!(", color=\"{}\"", palette.failed)
+                    } else {
+                        String::new()
+                    }
+                );
+            }
+            syntax += "\t}\n";
+        }
+
+        for block_id in &block_ids {
+            for flow in &graph[*block_id].flow {
+                let from = dot(&flow.flow_id);
+                match flow.flow_type {
+                    DGBlockFlowType::Call
+                    | DGBlockFlowType::CallStore
+                    | DGBlockFlowType::CallError
+                    | DGBlockFlowType::Loop => {
+                        let called_id = flow.flow_pointer_id.as_deref().unwrap_or_default();
+                        let called = called_block(&graph, flow)?;
+                        let failed = flow.flow_type == DGBlockFlowType::CallError;
+                        let highlight = match flow.change {
+                            Some(change) => {
+                                format!(
+                                    ", color=\"{}\", penwidth=3",
+                                    change_colors(change, palette).0
+                                )
+                            }
+                            None if failed => format!(", color=\"{}\", penwidth=3", palette.failed),
+                            None => String::new(),
+                        };
+                        syntax += &format!(
+                            "\t{} -> {} [style=dotted, arrowhead=odot{}];\n",
+                            from,
+                            dot(Self::entry_node(called_id, called)),
+                            highlight
+                        );
+                        let returned_from = dot(Self::exit_node(called_id, called));
+                        if failed {
+                            // The error travels back to the caller
+                            syntax += &format!(
+                                "\t{} -> {} [label={}{}];\n",
+                                returned_from,
+                                from,
+                                label(flow.value.as_deref().unwrap_or("throws"), options),
+                                highlight
+                            );
+                        } else if flow.flow_type == DGBlockFlowType::CallStore {
+                            syntax += &format!("\t{} -> {};\n", returned_from, from);
+                        }
+                    }
+                    // Runs concurrently with the caller, so it is only linked to where it starts
+                    DGBlockFlowType::AsyncCall => {
+                        let called_id = flow.flow_pointer_id.as_deref().unwrap_or_default();
+                        let called = called_block(&graph, flow)?;
+                        syntax += &format!(
+                            "\t{} -> {} [style=dashed, label=\"async\"];\n",
+                            from,
+                            dot(Self::entry_node(called_id, called))
+                        );
+                    }
+                    DGBlockFlowType::ExternalCallStore => {
+                        syntax +=
+                            &format!("\t{} -> {} [style=dotted, arrowhead=tee];\n", from, from);
+                    }
+                    DGBlockFlowType::ExternalCall => {
+                        let external = dot(&format!("external_{}", flow.flow_id));
+                        syntax += &format!(
+                            "\t{} [shape=box, style=\"rounded,filled\", fillcolor=\"{}\", label=\"External Call\"];\n",
+                            external, palette.node
+                        );
+                        syntax += &format!(
+                            "\t{} -> {} [style=dotted, arrowhead=tee];\n",
+                            from, external
+                        );
+                    }
+                    DGBlockFlowType::Log
+                    | DGBlockFlowType::Store
+                    | DGBlockFlowType::Error
+                    | DGBlockFlowType::Branch
+                    | DGBlockFlowType::LoopIteration => {}
+                }
+            }
+        }
+
+        syntax += &format!(
+            "\tBEGIN [shape=circle, style=filled, fillcolor=\"{c}\", fontcolor=\"{t}\", label=\"START\"];\n\tEND [shape=circle, style=filled, fillcolor=\"{c}\", fontcolor=\"{t}\", label=\"END\"];\n",
+            c = palette.start,
+            t = palette.start_text
+        );
+        syntax += &format!(
+            "\tBEGIN -> {} [penwidth=2];\n",
+            dot(Self::entry_node("START", starting_block))
+        );
+        syntax += &format!(
+            "\t{} -> END [penwidth=2{}];\n",
+            dot(Self::exit_node("START", starting_block)),
+            if starting_block.failed {
+                format!(", color=\"{}\"", palette.failed)
+            } else {
+                String::new()
+            }
+        );
+        syntax += "}\n";
+        Ok(syntax)
+    }
+}
+
+///Quoted label. Long ones are shortened and get the full text as their tooltip.
+fn label(text: &str, options: &DiagramOptions) -> String {
+    match truncate(text, options.max_label) {
+        Some(shown) => format!("{}, tooltip={}", dot(&shown), dot(text)),
+        None => dot(text),
+    }
+}
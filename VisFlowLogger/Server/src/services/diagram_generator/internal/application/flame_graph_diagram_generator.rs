@@ -0,0 +1,186 @@
+use crate::services::diagram_generator::api::models::block::{format_us, DGBlock};
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::{DiagramOptions, Theme};
+use crate::services::diagram_generator::api::services::DiagramGenerator;
+use crate::services::diagram_generator::internal::common::escape::{sanitize, truncate, xml};
+use crate::services::diagram_generator::internal::common::filter::filter;
+use crate::services::diagram_generator::internal::common::folded_stack::{FoldedStacks, Weight};
+use crate::services::diagram_generator::internal::common::style::{hash, name_color, palette};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+const WIDTH: f64 = 1200.0;
+const PADDING: f64 = 10.0;
+const TITLE_HEIGHT: f64 = 30.0;
+const FRAME_HEIGHT: f64 = 16.0;
+const FONT_SIZE: f64 = 12.0;
+/// Rough width of a character, used to fit labels into frames
+const CHAR_WIDTH: f64 = 7.0;
+
+/// Self-contained SVG flame graph. The starting block is at the bottom, callees stack on top.
+pub struct FlameGraphDiagramGenerator;
+
+///A block on one call path, with the weight of everything above it
+#[derive(Default)]
+struct Frame {
+    name: String,
+    total: u64,
+    children: Vec<Frame>,
+}
+
+impl Frame {
+    fn insert(&mut self, stack: &[String], weight: u64) {
+        self.total += weight;
+        let Some((name, rest)) = stack.split_first() else {
+            return;
+        };
+        //Stacks are sorted, so a frame with the same name can only be the latest child
+        match self.children.last_mut() {
+            Some(child) if &child.name == name => child.insert(rest, weight),
+            _ => {
+                let mut child = Frame {
+                    name: name.clone(),
+                    ..Frame::default()
+                };
+                child.insert(rest, weight);
+                self.children.push(child);
+            }
+        }
+    }
+
+    fn depth(&self) -> usize {
+        self.children
+            .iter()
+            .map(|c| c.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+struct Layout<'a> {
+    scale: f64,
+    bottom: f64,
+    total: u64,
+    weight: Weight,
+    options: &'a DiagramOptions,
+}
+
+impl FlameGraphDiagramGenerator {
+    fn render(frame: &Frame, x: f64, depth: usize, layout: &Layout, svg: &mut String) {
+        let width = frame.total as f64 * layout.scale;
+        let y = layout.bottom - (depth + 1) as f64 * FRAME_HEIGHT;
+        let share = frame.total as f64 * 100.0 / layout.total as f64;
+        let value = match layout.weight {
+            Weight::Entries if frame.total == 1 => "1 entry".to_string(),
+            Weight::Entries => format!("{} entries", frame.total),
+            Weight::Microseconds => format_us(frame.total),
+        };
+        let name = xml(&frame.name);
+        svg.push_str(&format!(
+            "<g><title>{} ({}, {:.2}%)</title><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\" rx=\"2\"/>",
+            name,
+            value,
+            share,
+            x,
+            y,
+            width,
+            FRAME_HEIGHT - 1.0,
+            color(&frame.name, layout.options)
+        ));
+        let fits = ((width - 6.0) / CHAR_WIDTH) as usize;
+        if fits >= 3 {
+            let name = sanitize(&frame.name);
+            let label = truncate(&name, fits).unwrap_or(name);
+            svg.push_str(&format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+                x + 3.0,
+                y + FONT_SIZE,
+                xml(&label)
+            ));
+        }
+        svg.push_str("</g>\n");
+        let mut child_x = x;
+        for child in &frame.children {
+            Self::render(child, child_x, depth + 1, layout, svg);
+            child_x += child.total as f64 * layout.scale;
+        }
+    }
+}
+
+#[async_trait]
+impl DiagramGenerator for FlameGraphDiagramGenerator {
+    fn generate_diagram(
+        &self,
+        graph: HashMap<String, DGBlock>,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError> {
+        let graph = filter(graph, options)?;
+        let folded = FoldedStacks::new(&graph)?;
+        let mut root = Frame::default();
+        for (stack, weight) in &folded.stacks {
+            root.insert(stack, *weight);
+        }
+        let height = TITLE_HEIGHT + root.depth() as f64 * FRAME_HEIGHT + 2.0 * PADDING;
+        let title = match folded.weight {
+            Weight::Entries => "Flame graph, weighted by entries",
+            Weight::Microseconds => "Flame graph, weighted by exclusive time",
+        };
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = WIDTH,
+            h = height
+        );
+        let palette = palette(options.theme);
+        svg += &format!(
+            "<style>text{{font-family:Verdana,sans-serif;font-size:{}px;fill:{text};pointer-events:none}}rect:hover{{stroke:{text};stroke-width:0.5}}</style>\n",
+            FONT_SIZE,
+            text = palette.text
+        );
+        svg += &format!(
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+            palette.background
+        );
+        svg += &format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" style=\"font-size:17px\">{}</text>\n",
+            WIDTH / 2.0,
+            PADDING + 14.0,
+            title
+        );
+        if root.total > 0 {
+            let layout = Layout {
+                scale: (WIDTH - 2.0 * PADDING) / root.total as f64,
+                bottom: height - PADDING,
+                total: root.total,
+                weight: folded.weight,
+                options,
+            };
+            let mut x = PADDING;
+            for frame in &root.children {
+                Self::render(frame, x, 0, &layout, &mut svg);
+                x += frame.total as f64 * layout.scale;
+            }
+        }
+        svg += "</svg>\n";
+        Ok(svg)
+    }
+}
+
+/**
+Color of a frame, the same for a block name in every frame. <br>
+A color set for the name wins, the light theme uses the classic warm colors and the others their block fills.
+ */
+fn color(name: &str, options: &DiagramOptions) -> String {
+    if let Some(color) = options.block_colors.get(name) {
+        return color.clone();
+    }
+    if options.theme != Theme::Light {
+        return name_color(name, options.seed, palette(options.theme));
+    }
+    let hash = hash(name, options.seed);
+    format!(
+        "rgb({},{},{})",
+        205 + hash % 50,
+        (hash >> 8) % 230,
+        (hash >> 16) % 55
+    )
+}
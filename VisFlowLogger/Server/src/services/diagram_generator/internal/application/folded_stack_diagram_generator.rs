@@ -0,0 +1,23 @@
+use crate::services::diagram_generator::api::models::block::DGBlock;
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::DiagramOptions;
+use crate::services::diagram_generator::api::services::DiagramGenerator;
+use crate::services::diagram_generator::internal::common::filter::filter;
+use crate::services::diagram_generator::internal::common::folded_stack::FoldedStacks;
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+///Folded stacks as read by `flamegraph.pl`, inferno and speedscope
+pub struct FoldedStackDiagramGenerator;
+
+#[async_trait]
+impl DiagramGenerator for FoldedStackDiagramGenerator {
+    fn generate_diagram(
+        &self,
+        graph: HashMap<String, DGBlock>,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError> {
+        let graph = filter(graph, options)?;
+        Ok(FoldedStacks::new(&graph)?.to_folded())
+    }
+}
@@ -0,0 +1,81 @@
+use crate::services::diagram_generator::api::models::block::DGBlock;
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::DiagramOptions;
+use crate::services::diagram_generator::api::services::DiagramGenerator;
+use crate::services::diagram_generator::internal::application::svg_diagram_generator::SvgDiagramGenerator;
+use crate::services::diagram_generator::internal::common::escape::xml;
+use crate::services::diagram_generator::internal::common::filter::filter;
+use crate::services::diagram_generator::internal::common::style::palette;
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+const PAGE: &str = include_str!("../../../../../assets/viewer/viewer.html");
+const STYLE: &str = include_str!("../../../../../assets/viewer/viewer.css");
+const SCRIPT: &str = include_str!("../../../../../assets/viewer/viewer.js");
+
+/**
+Page with the SVG flowchart and the script to explore it: pan and zoom, collapse a block's calls,
+and the whole value and sequence of a flow on hover. <br>
+The page, its style and script are part of the binary, so it loads nothing else.
+ */
+pub struct HtmlDiagramGenerator;
+
+#[async_trait]
+impl DiagramGenerator for HtmlDiagramGenerator {
+    fn generate_diagram(
+        &self,
+        graph: HashMap<String, DGBlock>,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError> {
+        let graph = filter(graph, options)?;
+        let palette = palette(options.theme);
+        let title = graph
+            .get("START")
+            .map(|block| block.name.as_str())
+            .unwrap_or_default();
+        let title = xml(&format!("VisFlow · {}", title));
+        let svg = SvgDiagramGenerator::draw(&graph, options)?;
+        Ok(fill(
+            PAGE,
+            &[
+                ("title", &title),
+                ("background", palette.background),
+                ("text", palette.text),
+                ("line", palette.line),
+                ("node", palette.node),
+                ("style", STYLE),
+                ("script", SCRIPT),
+                ("svg", &svg),
+            ],
+        ))
+    }
+}
+
+///Replaces the `{{name}}` placeholders of the page in one pass, so nothing a value brings in,
+///like a block named `{{svg}}`, is taken for a placeholder. Unknown ones are kept as they are
+fn fill(page: &str, values: &[(&str, &str)]) -> String {
+    let mut filled = String::with_capacity(page.len());
+    let mut rest = page;
+    while let Some(start) = rest.find("{{") {
+        filled.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let value = placeholder.find("}}").and_then(|end| {
+            values
+                .iter()
+                .find(|(name, _)| *name == &placeholder[2..end])
+                .map(|(_, value)| (end + 2, *value))
+        });
+        match value {
+            Some((length, value)) => {
+                filled.push_str(value);
+                rest = &placeholder[length..];
+            }
+            None => {
+                filled.push_str("{{");
+                rest = &placeholder[2..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
@@ -1,89 +1,120 @@
-use crate::services::diagram_generator::api::models::block::{DGBlock, DGBlockFlowType};
+use crate::services::diagram_generator::api::models::block::{
+    DGBlock, DGBlockChange, DGBlockFlowType,
+};
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::DiagramOptions;
+use crate::services::diagram_generator::api::models::diagram_options::Shape;
 use crate::services::diagram_generator::api::services::DiagramGenerator;
+use crate::services::diagram_generator::internal::common::escape::{
+    mermaid, mermaid_tooltip, truncate,
+};
+use crate::services::diagram_generator::internal::common::filter::filter;
+use crate::services::diagram_generator::internal::common::label::{called_block, flow_label};
+use crate::services::diagram_generator::internal::common::style::{
+    block_colors, block_title, change_colors, flow_shape, is_failure, mermaid_theme, palette,
+};
 use async_trait::async_trait;
 use std::collections::HashMap;
-use uuid::Uuid;
 
 pub struct MermaidDiagramGenerator;
-impl MermaidDiagramGenerator {
-    fn generate_color() -> String {
-        let r = rand::random::<u8>();
-        let g = rand::random::<u8>();
-        let b = rand::random::<u8>();
-        format!("#{:02X}{:02X}{:02X}", r, g, b)
-    }
-}
 #[async_trait]
 impl DiagramGenerator for MermaidDiagramGenerator {
-    fn generate_diagram(&self, graph: HashMap<String, DGBlock>) -> Result<String, String> {
-        let mut syntax = String::from("flowchart TB\n");
-        let mut subgraph_colors = HashMap::new();
-        let start_color = "#FF5733";
+    fn generate_diagram(
+        &self,
+        graph: HashMap<String, DGBlock>,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError> {
+        let graph = filter(graph, options)?;
+        let palette = palette(options.theme);
+        let mut syntax = mermaid_theme(options.theme);
+        syntax += &format!("flowchart {}\n", options.direction.as_str());
+        let subgraph_colors = block_colors(&graph, options);
+        // Mermaid styles links by the order they are declared in
+        let mut links = 0;
+        let mut failed_links: Vec<usize> = Vec::new();
+        let mut changed_links: Vec<(usize, DGBlockChange)> = Vec::new();
+        let mut block_ids: Vec<&String> = graph.keys().collect();
+        block_ids.sort();
 
-        for (block_id, block) in &graph {
-            let color = subgraph_colors
-                .entry(block_id.clone())
-                .or_insert_with(MermaidDiagramGenerator::generate_color);
-            syntax += &String::from(format!("\tsubgraph {}[\"{}\"]\n", block_id, block.name));
+        for block_id in &block_ids {
+            let block = &graph[*block_id];
+            let color = &subgraph_colors[*block_id];
+            syntax += &format!(
+                "\tsubgraph {}[\"{}\"]\n",
+                block_id,
+                mermaid(&block_title(block))
+            );
             let mut prev_flow: Option<String> = None;
             for flow in &block.flow {
-                let mut to_append: String = String::from("");
-                match flow.flow_type {
-                    DGBlockFlowType::Log => {
-                        to_append = format!(
-                            "\t\t{}([\"{}\"])",
-                            flow.flow_id,
-                            flow.value.as_ref().unwrap()
-                        )
-                    }
-                    DGBlockFlowType::Call | DGBlockFlowType::CallStore => {
-                        let called_entity_flow =
-                            graph.get(flow.flow_pointer_id.as_ref().unwrap()).unwrap();
-                        to_append = if flow.flow_type == DGBlockFlowType::Call {
-                            format!("\t\t{}[\"{}\"]", flow.flow_id, called_entity_flow.name)
-                        } else {
-                            format!(
-                                "\t\t{}[/\"{}\"/]",
-                                flow.flow_id,
-                                flow.value.as_ref().unwrap()
-                            )
-                        };
-                    }
-                    DGBlockFlowType::ExternalCall | DGBlockFlowType::ExternalCallStore => {
-                        to_append = if flow.flow_type == DGBlockFlowType::ExternalCall {
-                            format!("\t\t{}[\\{}/]", flow.flow_id, flow.value.as_ref().unwrap())
-                        } else {
-                            format!(
-                                "\t\t{}[/\"{}\"\\]",
-                                flow.flow_id,
-                                flow.value.as_ref().unwrap()
-                            )
-                        };
-                    }
+                // What a diff changed is told apart first
+                let class = match flow.change {
+                    Some(change) => format!(":::{}", change_class(change)),
+                    None if is_failure(flow.flow_type) => ":::failed".to_string(),
+                    None => String::new(),
+                };
+                let label = flow_label(&graph, flow)?;
+                let shown = truncate(&label, options.max_label);
+                syntax += &format!(
+                    "\t\t{}{}{}\n",
+                    flow.flow_id,
+                    node(
+                        flow_shape(flow.flow_type, options),
+                        &mermaid(shown.as_deref().unwrap_or(&label))
+                    ),
+                    class
+                );
+                if shown.is_some() {
+                    // Shows the full text on hover, pages can define `vflLabel(id)` to show it on click too
+                    syntax += &format!(
+                        "\t\tclick {} vflLabel \"{}\"\n",
+                        flow.flow_id,
+                        mermaid_tooltip(&label)
+                    );
                 }
-                syntax += &to_append;
-                syntax += "\n";
                 // Basic internal flow arrow
                 if prev_flow.is_none() {
                     prev_flow = Option::from(flow.flow_id.clone());
                     continue;
                 }
-                syntax +=
-                    &String::from(format!("\t\t{} ==> {}\n", prev_flow.unwrap(), flow.flow_id));
+                syntax += &format!("\t\t{} ==> {}\n", prev_flow.unwrap(), flow.flow_id);
+                if matches!(
+                    flow.flow_type,
+                    DGBlockFlowType::Error | DGBlockFlowType::CallError
+                ) {
+                    failed_links.push(links);
+                }
+                links += 1;
                 prev_flow = Option::from(flow.flow_id.clone());
             }
             syntax += "\tend\n";
             // Add style for the subgraph
-            syntax += &format!("style {} fill:{}\n", block_id, color);
+            let mut style = format!("fill:{}", color);
+            if block.aborted {
+                // Blocks that never ended get a dashed border
+                style += ",stroke-dasharray:5 5";
+            }
+            if let Some(change) = block.change {
+                style += &format!(
+                    ",stroke:{},stroke-width:3px",
+                    change_colors(change, palette).0
+                );
+            } else if block.failed {
+                style += &format!(",stroke:{},stroke-width:3px", palette.failed);
+            }
+            syntax += &format!("style {} {}\n", block_id, style);
         }
 
-        for (_, v) in &graph {
-            for flow in &v.flow {
+        for block_id in &block_ids {
+            for flow in &graph[*block_id].flow {
+                let first_link = links;
                 let mut to_append: String = String::from("");
                 match flow.flow_type {
-                    DGBlockFlowType::Call | DGBlockFlowType::CallStore => {
-                        let called_entity_flow =
-                            graph.get(flow.flow_pointer_id.as_ref().unwrap()).unwrap();
+                    DGBlockFlowType::Call
+                    | DGBlockFlowType::CallStore
+                    | DGBlockFlowType::CallError
+                    | DGBlockFlowType::Loop => {
+                        let called_entity_flow = called_block(&graph, flow)?;
+                        let failed = flow.flow_type == DGBlockFlowType::CallError;
                         if let Some(caller_first_flow) = called_entity_flow.flow.first() {
                             to_append =
                                 format!("{} ...-o {}", flow.flow_id, caller_first_flow.flow_id);
@@ -94,51 +125,166 @@ impl DiagramGenerator for MermaidDiagramGenerator {
                                 flow.flow_pointer_id.as_ref().unwrap()
                             );
                         }
-                        if flow.flow_type == DGBlockFlowType::CallStore {
+                        if failed {
+                            failed_links.push(links);
+                        }
+                        links += 1;
+                        if !matches!(
+                            flow.flow_type,
+                            DGBlockFlowType::Call | DGBlockFlowType::Loop
+                        ) {
                             to_append += &String::from("\n");
-                            if let Some(last_flow) = called_entity_flow.flow.last() {
-                                to_append += &String::from(format!(
-                                    "{} ---> {}",
-                                    last_flow.flow_id, flow.flow_id
-                                ));
-                            } else {
-                                to_append = format!(
-                                    "{} ---> {}",
-                                    flow.flow_pointer_id.as_ref().unwrap(),
+                            let returned_from = called_entity_flow
+                                .flow
+                                .last()
+                                .map_or(flow.flow_pointer_id.as_ref().unwrap(), |f| &f.flow_id);
+                            if failed {
+                                // The error travels back to the caller
+                                to_append += &format!(
+                                    "{} -->|\"{}\"| {}",
+                                    returned_from,
+                                    error_label(flow.value.as_deref(), options),
                                     flow.flow_id
                                 );
+                                failed_links.push(links);
+                            } else {
+                                to_append += &format!("{} ---> {}", returned_from, flow.flow_id);
                             }
+                            links += 1;
                         }
                     }
+                    // Runs concurrently with the caller, so it is only linked to where it starts
+                    DGBlockFlowType::AsyncCall => {
+                        let called_entity_flow = called_block(&graph, flow)?;
+                        let called_flow_id = called_entity_flow
+                            .flow
+                            .first()
+                            .map_or(flow.flow_pointer_id.as_ref().unwrap(), |f| &f.flow_id);
+                        to_append = format!("{} -. async .-> {}", flow.flow_id, called_flow_id);
+                        links += 1;
+                    }
                     DGBlockFlowType::ExternalCallStore => {
                         to_append = format!("{} .-x {}", flow.flow_id, flow.flow_id);
+                        links += 1;
                     }
                     DGBlockFlowType::ExternalCall => {
-                        to_append += &String::from(format!(
-                            "{} ..-x {}([\"{}\"])",
-                            flow.flow_id,
-                            Uuid::new_v4().to_string(),
-                            "External Call"
-                        ));
+                        to_append += &format!(
+                            "{} ..-x external_{}([\"{}\"])",
+                            flow.flow_id, flow.flow_id, "External Call"
+                        );
+                        links += 1;
                     }
-                    DGBlockFlowType::Log => {}
+                    DGBlockFlowType::Log
+                    | DGBlockFlowType::Store
+                    | DGBlockFlowType::Error
+                    | DGBlockFlowType::Branch
+                    | DGBlockFlowType::LoopIteration => {}
                 }
                 syntax += &to_append;
                 syntax += "\n";
+                if let Some(change) = flow.change {
+                    changed_links.extend((first_link..links).map(|link| (link, change)));
+                }
             }
         }
 
-        let starting_flow = graph.get("START").unwrap();
-        syntax += &String::from(format!(
-            "\nBEGIN((\"START\")):::starting ==> {}\n",
-            starting_flow.flow.first().unwrap().flow_id
-        ));
-        syntax += &String::from(format!(
-            " {} ==> END((\"END\")):::starting\n",
-            starting_flow.flow.last().unwrap().flow_id
-        ));
-        syntax += &format!("classDef starting fill:{};\n", start_color);
+        let starting_flow = graph.get("START").ok_or(DiagramError::MissingStartBlock)?;
+        // A starting block without flows is linked as a whole
+        let first_flow_id = starting_flow
+            .flow
+            .first()
+            .map_or("START", |f| f.flow_id.as_str());
+        let last_flow_id = starting_flow
+            .flow
+            .last()
+            .map_or("START", |f| f.flow_id.as_str());
+        syntax += &format!("\nBEGIN((\"START\")):::starting ==> {}\n", first_flow_id);
+        syntax += &format!(" {} ==> END((\"END\")):::starting\n", last_flow_id);
+        if starting_flow.failed {
+            failed_links.push(links + 1);
+        }
+        syntax += &format!(
+            "classDef starting fill:{},color:{};\n",
+            palette.start, palette.start_text
+        );
+        syntax += &format!(
+            "classDef failed fill:{},stroke:{},color:{};\n",
+            palette.failed_fill, palette.failed, palette.failed_text
+        );
+        if !failed_links.is_empty() {
+            let failed_links: Vec<String> = failed_links.iter().map(usize::to_string).collect();
+            syntax += &format!(
+                "linkStyle {} stroke:{},stroke-width:3px;\n",
+                failed_links.join(","),
+                palette.failed
+            );
+        }
+        // Declared last, so the call of a block a diff added or removed keeps its color even when it threw
+        for change in [
+            DGBlockChange::Added,
+            DGBlockChange::Removed,
+            DGBlockChange::Changed,
+        ] {
+            let marked = graph
+                .values()
+                .any(|block| block.flow.iter().any(|flow| flow.change == Some(change)));
+            if marked {
+                let (border, fill) = change_colors(change, palette);
+                syntax += &format!(
+                    "classDef {} fill:{},stroke:{},color:{};\n",
+                    change_class(change),
+                    fill,
+                    border,
+                    palette.text
+                );
+            }
+            let links: Vec<String> = changed_links
+                .iter()
+                .filter(|(_, link_change)| *link_change == change)
+                .map(|(link, _)| link.to_string())
+                .collect();
+            if !links.is_empty() {
+                syntax += &format!(
+                    "linkStyle {} stroke:{},stroke-width:3px;\n",
+                    links.join(","),
+                    change_colors(change, palette).0
+                );
+            }
+        }
 
         Ok(syntax)
     }
 }
+
+///Class of the nodes a diff added, removed or changed
+fn change_class(change: DGBlockChange) -> &'static str {
+    match change {
+        DGBlockChange::Added => "added",
+        DGBlockChange::Removed => "removed",
+        DGBlockChange::Changed => "changed",
+    }
+}
+
+///Node in the Mermaid syntax of the shape
+fn node(shape: Shape, label: &str) -> String {
+    let (open, close) = match shape {
+        Shape::Rectangle => ("[", "]"),
+        Shape::Rounded => ("(", ")"),
+        Shape::Stadium => ("([", "])"),
+        Shape::Parallelogram => ("[/", "/]"),
+        Shape::Trapezoid => ("[/", "\\]"),
+        Shape::InvertedTrapezoid => ("[\\", "/]"),
+        Shape::Diamond => ("{", "}"),
+        Shape::Hexagon => ("{{", "}}"),
+        Shape::Circle => ("((", "))"),
+        Shape::Flag => (">", "]"),
+        Shape::Subroutine => ("[[", "]]"),
+    };
+    format!("{}\"{}\"{}", open, label, close)
+}
+
+///Label of the link an error takes back to the caller
+fn error_label(value: Option<&str>, options: &DiagramOptions) -> String {
+    let value = value.unwrap_or("throws");
+    mermaid(&truncate(value, options.max_label).unwrap_or_else(|| value.to_string()))
+}
@@ -0,0 +1,100 @@
+use crate::services::diagram_generator::api::models::block::DGBlock;
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::DiagramOptions;
+use crate::services::diagram_generator::api::services::DiagramGenerator;
+use crate::services::diagram_generator::internal::common::escape::mermaid;
+use crate::services::diagram_generator::internal::common::filter::filter;
+use crate::services::diagram_generator::internal::common::sequence::{Sequence, Step};
+use crate::services::diagram_generator::internal::common::style::{mermaid_theme, palette, rgb};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// Mermaid `sequenceDiagram` with the same steps as the PlantUML one
+pub struct MermaidSequenceDiagramGenerator;
+
+#[async_trait]
+impl DiagramGenerator for MermaidSequenceDiagramGenerator {
+    fn generate_diagram(
+        &self,
+        graph: HashMap<String, DGBlock>,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError> {
+        let graph = filter(graph, options)?;
+        let palette = palette(options.theme);
+        let sequence = Sequence::new(&graph)?;
+        let aliases: HashMap<&str, String> = sequence
+            .participants
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (*name, format!("p{}", index)))
+            .collect();
+        let alias = |name: &str| aliases[name].as_str();
+
+        let mut syntax = mermaid_theme(options.theme);
+        syntax += "sequenceDiagram\n";
+        for name in &sequence.participants {
+            let participant = format!("\tparticipant {} as {}\n", alias(name), mermaid(name));
+            // Participants have no fill of their own, so a colored one gets a box around it
+            syntax += &match options.block_colors.get(*name) {
+                Some(color) => format!("\tbox {}\n{}\tend\n", rgb(color), participant),
+                None => participant,
+            };
+        }
+        if sequence.external {
+            syntax += "\tparticipant external as External\n";
+        }
+        syntax += &format!("\tactivate {}\n", alias(sequence.root));
+        for step in &sequence.steps {
+            syntax += &match step {
+                Step::Call {
+                    from,
+                    to,
+                    asynchronous: false,
+                } => format!("\t{}->>+{}: call\n", alias(from), alias(to)),
+                Step::Call {
+                    from,
+                    to,
+                    asynchronous: true,
+                } => format!("\t{}-){}: async\n", alias(from), alias(to)),
+                Step::Return {
+                    from,
+                    to,
+                    label,
+                    failed,
+                } => format!(
+                    "\t{}{}-{}: {}\n",
+                    alias(from),
+                    if *failed { "--x" } else { "-->>" },
+                    alias(to),
+                    mermaid(label.as_deref().unwrap_or("return"))
+                ),
+                // Notes have no color of their own
+                Step::Note {
+                    over,
+                    text: note,
+                    failed: true,
+                } => format!(
+                    "\trect {}\n\tNote over {}: {}\n\tend\n",
+                    rgb(palette.failed_fill),
+                    alias(over),
+                    mermaid(note)
+                ),
+                Step::Note {
+                    over,
+                    text: note,
+                    failed: false,
+                } => format!("\tNote over {}: {}\n", alias(over), mermaid(note)),
+                Step::ExternalCall { from, label } => {
+                    format!("\t{}->>external: {}\n", alias(from), mermaid(label))
+                }
+                Step::ExternalReturn { to, label } => {
+                    format!("\texternal-->>{}: {}\n", alias(to), mermaid(label))
+                }
+                Step::LoopStart { label } => format!("\tloop {}\n", mermaid(label)),
+                Step::LoopEnd => "\tend\n".to_string(),
+            };
+        }
+        syntax += &format!("\tdeactivate {}\n", alias(sequence.root));
+        Ok(syntax)
+    }
+}
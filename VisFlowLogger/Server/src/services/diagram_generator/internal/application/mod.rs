@@ -1 +1,9 @@
-pub mod mermaid_diagram_generator;
\ No newline at end of file
+pub mod dot_diagram_generator;
+pub mod flame_graph_diagram_generator;
+pub mod folded_stack_diagram_generator;
+pub mod html_diagram_generator;
+pub mod mermaid_diagram_generator;
+pub mod mermaid_sequence_diagram_generator;
+pub mod plantuml_diagram_generator;
+pub mod png_rasterizer;
+pub mod svg_diagram_generator;
@@ -0,0 +1,127 @@
+use crate::services::diagram_generator::api::models::block::DGBlock;
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::{DiagramOptions, Theme};
+use crate::services::diagram_generator::api::services::DiagramGenerator;
+use crate::services::diagram_generator::internal::common::escape::plantuml;
+use crate::services::diagram_generator::internal::common::filter::filter;
+use crate::services::diagram_generator::internal::common::sequence::{Sequence, Step};
+use crate::services::diagram_generator::internal::common::style::{palette, Palette};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// PlantUML sequence diagram with a participant per block name
+pub struct PlantUmlDiagramGenerator;
+
+#[async_trait]
+impl DiagramGenerator for PlantUmlDiagramGenerator {
+    fn generate_diagram(
+        &self,
+        graph: HashMap<String, DGBlock>,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError> {
+        let graph = filter(graph, options)?;
+        let palette = palette(options.theme);
+        let sequence = Sequence::new(&graph)?;
+        let aliases: HashMap<&str, String> = sequence
+            .participants
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (*name, format!("p{}", index)))
+            .collect();
+        let alias = |name: &str| aliases[name].as_str();
+
+        let mut syntax = String::from("@startuml\n");
+        if options.theme != Theme::Light {
+            syntax += &skin(palette);
+        }
+        for name in &sequence.participants {
+            syntax += &format!(
+                "participant \"{}\" as {}{}\n",
+                plantuml(name),
+                alias(name),
+                options
+                    .block_colors
+                    .get(*name)
+                    .map_or(String::new(), |color| format!(" {}", color))
+            );
+        }
+        if sequence.external {
+            syntax += "participant \"External\" as external\n";
+        }
+        syntax += &format!("activate {}\n", alias(sequence.root));
+        for step in &sequence.steps {
+            syntax += &match step {
+                Step::Call {
+                    from,
+                    to,
+                    asynchronous: false,
+                } => format!("{} -> {} ++\n", alias(from), alias(to)),
+                Step::Call {
+                    from,
+                    to,
+                    asynchronous: true,
+                } => format!("{} ->> {} : async\n", alias(from), alias(to)),
+                Step::Return {
+                    from,
+                    to,
+                    label,
+                    failed,
+                } => format!(
+                    "{} -{}-> {} --{}\n",
+                    alias(from),
+                    if *failed {
+                        format!("[{}]", palette.failed)
+                    } else {
+                        String::new()
+                    },
+                    alias(to),
+                    label
+                        .as_deref()
+                        .map_or(String::new(), |label| format!(" : {}", plantuml(label)))
+                ),
+                Step::Note {
+                    over,
+                    text: note,
+                    failed,
+                } => format!(
+                    "note over {}{} : {}\n",
+                    alias(over),
+                    if *failed {
+                        format!(" {}", palette.failed_fill)
+                    } else {
+                        String::new()
+                    },
+                    plantuml(note)
+                ),
+                Step::ExternalCall { from, label } => {
+                    format!("{} -> external : {}\n", alias(from), plantuml(label))
+                }
+                Step::ExternalReturn { to, label } => {
+                    format!("external --> {} : {}\n", alias(to), plantuml(label))
+                }
+                Step::LoopStart { label } => format!("loop {}\n", plantuml(label)),
+                Step::LoopEnd => "end\n".to_string(),
+            };
+        }
+        syntax += &format!("deactivate {}\n@enduml\n", alias(sequence.root));
+        Ok(syntax)
+    }
+}
+
+///Colors of a theme other than PlantUML's own
+fn skin(palette: &Palette) -> String {
+    [
+        ("backgroundColor", palette.background),
+        ("defaultFontColor", palette.text),
+        ("ArrowColor", palette.line),
+        ("SequenceLifeLineBorderColor", palette.line),
+        ("ParticipantBackgroundColor", palette.node),
+        ("ParticipantBorderColor", palette.line),
+        ("NoteBackgroundColor", palette.node),
+        ("NoteBorderColor", palette.line),
+        ("SequenceGroupBorderColor", palette.line),
+    ]
+    .iter()
+    .map(|(name, color)| format!("skinparam {} {}\n", name, color))
+    .collect()
+}
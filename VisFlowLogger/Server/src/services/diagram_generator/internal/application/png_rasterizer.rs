@@ -0,0 +1,54 @@
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{fontdb, Options, Tree};
+use std::sync::{Arc, OnceLock};
+
+/// Largest image produced, larger diagrams are scaled down to it. About 160 MB of pixels.
+const MAX_PIXELS: f64 = 40_000_000.0;
+
+///System fonts, loaded on the first rasterization
+fn fonts() -> Arc<fontdb::Database> {
+    static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut fonts = fontdb::Database::new();
+            fonts.load_system_fonts();
+            Arc::new(fonts)
+        })
+        .clone()
+}
+
+///PNG of an SVG diagram, rendered in process. Fails without any system font, as the labels would be left out
+pub fn rasterize(svg: &str) -> Result<Vec<u8>, DiagramError> {
+    let fonts = fonts();
+    if fonts.is_empty() {
+        return Err(DiagramError::RenderFailed(
+            "No fonts installed on the server, install one like DejaVu Sans to draw PNG images"
+                .to_string(),
+        ));
+    }
+    let options = Options {
+        fontdb: fonts,
+        font_family: "DejaVu Sans".to_string(),
+        ..Options::default()
+    };
+    let tree =
+        Tree::from_str(svg, &options).map_err(|e| DiagramError::RenderFailed(e.to_string()))?;
+    let size = tree.size();
+    let scale = (MAX_PIXELS / (size.width() as f64 * size.height() as f64))
+        .sqrt()
+        .min(1.0) as f32;
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+    let mut pixmap = Pixmap::new(width, height).ok_or_else(|| {
+        DiagramError::RenderFailed(format!("Can't make a {}x{} image", width, height))
+    })?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    pixmap
+        .encode_png()
+        .map_err(|e| DiagramError::RenderFailed(e.to_string()))
+}
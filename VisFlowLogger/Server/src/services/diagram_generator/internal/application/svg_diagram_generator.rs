@@ -0,0 +1,386 @@
+use crate::services::diagram_generator::api::models::block::{DGBlock, DGBlockFlowType};
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::{
+    DiagramOptions, Direction, Shape,
+};
+use crate::services::diagram_generator::api::services::DiagramGenerator;
+use crate::services::diagram_generator::internal::common::escape::{sanitize, truncate, xml};
+use crate::services::diagram_generator::internal::common::filter::filter;
+use crate::services::diagram_generator::internal::common::label::{called_block, flow_label};
+use crate::services::diagram_generator::internal::common::layout::{
+    is_call, Layout, Rect, BLOCK_PADDING, BLOCK_TITLE_HEIGHT, NODE_WIDTH, TERMINAL_RADIUS,
+};
+use crate::services::diagram_generator::internal::common::style::{
+    block_colors, block_title, flow_shape, is_failure, palette, Palette,
+};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+const FONT_SIZE: f64 = 12.0;
+/// Rough width of a character, used to fit labels into boxes
+const CHAR_WIDTH: f64 = 7.0;
+
+/// Flowchart laid out and drawn by the server, with the same blocks, shapes and links as the Mermaid diagram
+pub struct SvgDiagramGenerator;
+
+impl SvgDiagramGenerator {
+    ///Outline of a flow's node, like the node shapes of the other flowcharts
+    fn shape(shape: Shape, r: &Rect, paint: &str, line: &str) -> String {
+        let (x, y, w, h) = (r.x, r.y, r.width, r.height);
+        let polygon = |points: &[(f64, f64)]| {
+            let points: Vec<String> = points
+                .iter()
+                .map(|(px, py)| format!("{:.1},{:.1}", px, py))
+                .collect();
+            format!("<polygon points=\"{}\" {}/>", points.join(" "), paint)
+        };
+        let rect = |rx: f64| {
+            format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"{:.1}\" {}/>",
+                x, y, w, h, rx, paint
+            )
+        };
+        match shape {
+            Shape::Rectangle => rect(0.0),
+            Shape::Rounded => rect(6.0),
+            Shape::Stadium => rect(h / 2.0),
+            Shape::Subroutine => format!(
+                "{}<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"none\" stroke=\"{}\"/>",
+                rect(0.0),
+                x + 3.0,
+                y + 3.0,
+                w - 6.0,
+                h - 6.0,
+                line
+            ),
+            Shape::Circle => format!(
+                "<ellipse cx=\"{:.1}\" cy=\"{:.1}\" rx=\"{:.1}\" ry=\"{:.1}\" {}/>",
+                r.center_x(),
+                r.center_y(),
+                w / 2.0,
+                h / 2.0,
+                paint
+            ),
+            Shape::Parallelogram => {
+                polygon(&[(x + 10.0, y), (x + w, y), (x + w - 10.0, y + h), (x, y + h)])
+            }
+            Shape::InvertedTrapezoid => polygon(&[
+                (x, y),
+                (x + w, y),
+                (x + w - 14.0, y + h),
+                (x + 14.0, y + h),
+            ]),
+            Shape::Trapezoid => polygon(&[
+                (x + 14.0, y),
+                (x + w - 14.0, y),
+                (x + w, y + h),
+                (x, y + h),
+            ]),
+            Shape::Diamond => polygon(&[
+                (x + w / 2.0, y),
+                (x + w, y + h / 2.0),
+                (x + w / 2.0, y + h),
+                (x, y + h / 2.0),
+            ]),
+            Shape::Hexagon => polygon(&[
+                (x + 12.0, y),
+                (x + w - 12.0, y),
+                (x + w, y + h / 2.0),
+                (x + w - 12.0, y + h),
+                (x + 12.0, y + h),
+                (x, y + h / 2.0),
+            ]),
+            Shape::Flag => polygon(&[
+                (x, y),
+                (x + w - 12.0, y),
+                (x + w, y + h / 2.0),
+                (x + w - 12.0, y + h),
+                (x, y + h),
+            ]),
+        }
+    }
+
+    ///Curved link between two boxes of different blocks, bending along the calls
+    fn link(direction: Direction, from: (f64, f64), to: (f64, f64), attributes: &str) -> String {
+        let (c1, c2) = match direction {
+            Direction::Tb => {
+                let bend = bend(from.0, to.0);
+                ((from.0 + bend, from.1), (to.0 - bend, to.1))
+            }
+            Direction::Lr => {
+                let bend = bend(from.1, to.1);
+                ((from.0, from.1 + bend), (to.0, to.1 - bend))
+            }
+        };
+        format!(
+            "<path d=\"M{:.1},{:.1} C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" fill=\"none\" {}/>\n",
+            from.0, from.1, c1.0, c1.1, c2.0, c2.1, to.0, to.1, attributes
+        )
+    }
+
+    fn line(from: (f64, f64), to: (f64, f64), attributes: &str) -> String {
+        format!(
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" {}/>\n",
+            from.0, from.1, to.0, to.1, attributes
+        )
+    }
+
+    fn terminal(center: (f64, f64), label: &str, palette: &Palette) -> String {
+        format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"{}\" stroke=\"{}\"/><text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" font-size=\"10\" fill=\"{}\">{}</text>\n",
+            center.0,
+            center.1,
+            TERMINAL_RADIUS,
+            palette.start,
+            palette.line,
+            center.0,
+            center.1 + 3.5,
+            palette.start_text,
+            label
+        )
+    }
+
+    ///Draws the graph as it is, without the filters of the options
+    pub fn draw(
+        graph: &HashMap<String, DGBlock>,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError> {
+        let palette = palette(options.theme);
+        let layout = Layout::new(graph, options.direction)?;
+        let colors = block_colors(graph, options);
+        let mut block_ids: Vec<&String> = graph.keys().collect();
+        block_ids.sort();
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" viewBox=\"0 0 {w:.0} {h:.0}\">\n",
+            w = layout.width,
+            h = layout.height
+        );
+        svg += "<defs>\n";
+        for (id, color) in [("arrow", palette.line), ("arrow-failed", palette.failed)] {
+            svg += &format!(
+                "<marker id=\"{}\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"10\" markerHeight=\"10\" markerUnits=\"userSpaceOnUse\" orient=\"auto\"><path d=\"M0,0 L10,5 L0,10 z\" fill=\"{}\"/></marker>\n",
+                id, color
+            );
+        }
+        svg += &format!(
+            "<marker id=\"call\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"10\" markerHeight=\"10\" markerUnits=\"userSpaceOnUse\"><circle cx=\"5\" cy=\"5\" r=\"4\" fill=\"{}\" stroke=\"{}\" stroke-width=\"1.5\"/></marker>\n",
+            palette.background, palette.line
+        );
+        svg += "</defs>\n";
+        svg += &format!(
+            "<g font-family=\"DejaVu Sans, Verdana, sans-serif\" font-size=\"{}\" fill=\"{}\">\n",
+            FONT_SIZE, palette.text
+        );
+        svg += &format!(
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+            palette.background
+        );
+
+        for block_id in &block_ids {
+            let block = &graph[*block_id];
+            let placed = &layout.blocks[*block_id];
+            let rect = placed.rect;
+            let title = block_title(block);
+            let (border, border_width) = if block.failed {
+                (palette.failed, 3.0)
+            } else {
+                (palette.line, 1.0)
+            };
+            // Blocks that never ended get a dashed border
+            let dash = if block.aborted {
+                " stroke-dasharray=\"6,4\""
+            } else {
+                ""
+            };
+            // Everything of a block is in its group, which the viewer page hides with the caller's
+            svg += &format!(
+                "<g class=\"block\" data-block=\"{}\" data-caller=\"{}\">\n",
+                xml(block_id),
+                xml(block.caller.as_deref().unwrap_or_default())
+            );
+            svg += &format!(
+                "<g class=\"title\"><title>{}</title><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"6\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"{}/>",
+                xml(&title),
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                colors[*block_id],
+                border,
+                border_width,
+                dash
+            );
+            svg += &format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" font-weight=\"bold\">{}</text></g>\n",
+                rect.x + BLOCK_PADDING,
+                rect.y + BLOCK_TITLE_HEIGHT / 2.0 + 6.0,
+                xml(&fit(&title, rect.width - 2.0 * BLOCK_PADDING))
+            );
+
+            for (flow, node) in block.flow.iter().zip(&placed.nodes) {
+                let label = flow_label(graph, flow)?;
+                let (fill, border, text) = if is_failure(flow.flow_type) {
+                    (palette.failed_fill, palette.failed, palette.failed_text)
+                } else {
+                    (palette.node, palette.line, palette.text)
+                };
+                let paint = format!("fill=\"{}\" stroke=\"{}\"", fill, border);
+                svg += &format!(
+                    "<g class=\"flow\" data-flow=\"{}\" data-type=\"{}\"{}{}><title>{}</title>{}<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" fill=\"{}\">{}</text></g>\n",
+                    xml(&flow.flow_id),
+                    flow.flow_type,
+                    flow.sequence
+                        .map(|sequence| format!(" data-sequence=\"{}\"", sequence))
+                        .unwrap_or_default(),
+                    flow.value
+                        .as_deref()
+                        .map(|value| format!(" data-value=\"{}\"", attribute(value)))
+                        .unwrap_or_default(),
+                    xml(&label),
+                    Self::shape(flow_shape(flow.flow_type, options), node, &paint, palette.line),
+                    node.center_x(),
+                    node.center_y() + FONT_SIZE / 3.0,
+                    text,
+                    xml(&fit(&label, NODE_WIDTH - 28.0))
+                );
+            }
+            // Basic internal flow arrow
+            for (i, pair) in block.flow.windows(2).enumerate() {
+                let (from, to) = (placed.nodes[i], placed.nodes[i + 1]);
+                svg += &Self::line(
+                    layout.flow_out(&from),
+                    layout.flow_in(&to),
+                    &stroke(palette, is_failure(pair[1].flow_type), 2.0, "", "arrow"),
+                );
+            }
+            svg += "</g>\n";
+        }
+
+        for block_id in &block_ids {
+            let placed = &layout.blocks[*block_id];
+            for (flow, node) in graph[*block_id].flow.iter().zip(&placed.nodes) {
+                if !is_call(&flow.flow_type) {
+                    continue;
+                }
+                let called_id = flow.flow_pointer_id.as_deref().unwrap_or_default();
+                called_block(graph, flow)?;
+                let called = &layout.blocks[called_id];
+                let (entry, exit) = (called.entry(), called.exit());
+                let failed = flow.flow_type == DGBlockFlowType::CallError;
+                let (label_x, label_y) = layout.call_out(node, 0.0);
+                svg += &format!(
+                    "<g class=\"call\" data-from=\"{}\" data-to=\"{}\">\n",
+                    xml(block_id),
+                    xml(called_id)
+                );
+                match flow.flow_type {
+                    // Runs concurrently with the caller, so it is only linked to where it starts
+                    DGBlockFlowType::AsyncCall => {
+                        svg += &Self::link(
+                            layout.direction,
+                            layout.call_out(node, 0.0),
+                            layout.call_in(&entry, 0.0),
+                            &stroke(palette, false, 1.5, " stroke-dasharray=\"6,4\"", "arrow"),
+                        );
+                        svg += &format!(
+                            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\">async</text>\n",
+                            label_x + 6.0,
+                            label_y - 4.0
+                        );
+                    }
+                    _ => {
+                        svg += &Self::link(
+                            layout.direction,
+                            layout.call_out(node, -6.0),
+                            layout.call_in(&entry, -6.0),
+                            &stroke(palette, failed, 1.5, " stroke-dasharray=\"2,3\"", "call"),
+                        );
+                    }
+                }
+                if failed {
+                    // The error travels back to the caller
+                    svg += &Self::link(
+                        layout.direction,
+                        layout.call_in(&exit, 6.0),
+                        layout.call_out(node, 6.0),
+                        &stroke(palette, true, 1.5, "", "arrow"),
+                    );
+                    svg += &format!(
+                        "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\" fill=\"{}\">{}</text>\n",
+                        label_x + 6.0,
+                        label_y + 20.0,
+                        palette.failed_text,
+                        xml(&fit(flow.value.as_deref().unwrap_or("throws"), 84.0))
+                    );
+                } else if flow.flow_type == DGBlockFlowType::CallStore {
+                    svg += &Self::link(
+                        layout.direction,
+                        layout.call_in(&exit, 6.0),
+                        layout.call_out(node, 6.0),
+                        &stroke(palette, false, 1.5, "", "arrow"),
+                    );
+                }
+                svg += "</g>\n";
+            }
+        }
+
+        let root = &layout.blocks["START"];
+        let (entry, exit) = (root.entry(), root.exit());
+        svg += &Self::terminal(layout.start, "START", palette);
+        svg += &Self::terminal(layout.end, "END", palette);
+        svg += &Self::line(
+            layout.terminal_side(layout.start, 1.0),
+            layout.flow_in(&entry),
+            &stroke(palette, false, 2.0, "", "arrow"),
+        );
+        svg += &Self::line(
+            layout.flow_out(&exit),
+            layout.terminal_side(layout.end, -1.0),
+            &stroke(palette, graph["START"].failed, 2.0, "", "arrow"),
+        );
+        svg += "</g>\n</svg>\n";
+        Ok(svg)
+    }
+}
+
+#[async_trait]
+impl DiagramGenerator for SvgDiagramGenerator {
+    fn generate_diagram(
+        &self,
+        graph: HashMap<String, DGBlock>,
+        options: &DiagramOptions,
+    ) -> Result<String, DiagramError> {
+        Self::draw(&filter(graph, options)?, options)
+    }
+}
+
+///Stroke and head of a link, in the failed color on the path an error took. Arrows change color with it.
+fn stroke(palette: &Palette, failed: bool, width: f64, extra: &str, head: &str) -> String {
+    let color = if failed { palette.failed } else { palette.line };
+    let head = if failed && head == "arrow" {
+        "arrow-failed"
+    } else {
+        head
+    };
+    format!(
+        "stroke=\"{}\" stroke-width=\"{}\"{} marker-end=\"url(#{})\"",
+        color, width, extra, head
+    )
+}
+
+///How far a link's control points reach, towards the box it goes to
+fn bend(from: f64, to: f64) -> f64 {
+    ((to - from).abs() / 2.0).max(30.0) * if to < from { -1.0 } else { 1.0 }
+}
+
+///Attribute with the whole text, line breaks included
+fn attribute(text: &str) -> String {
+    xml(text).replace('\n', "&#10;")
+}
+
+///Text shortened with `…` to fit the width, the full text is in the tooltip
+fn fit(text: &str, width: f64) -> String {
+    let text = sanitize(text);
+    truncate(&text, (width / CHAR_WIDTH) as usize).unwrap_or(text)
+}
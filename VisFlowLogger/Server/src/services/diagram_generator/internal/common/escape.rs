@@ -0,0 +1,104 @@
+//! Block names and log values can hold anything. Every diagram writes them through one of these functions,
+//! which drop control characters and encode whatever the syntax would read as its own.
+
+///Text without control characters or bidirectional overrides. Line breaks are kept as `\n`.
+pub fn sanitize(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .chars()
+        .map(|c| if c == '\r' { '\n' } else { c })
+        .filter(|c| *c == '\n' || !(c.is_control() || is_bidi_control(*c)))
+        .collect()
+}
+
+///Characters that reorder the text around them, which could make a label read differently than it is
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+///Text shortened to `max` characters with `…`, `None` when it fits or `max` is 0
+pub fn truncate(text: &str, max: usize) -> Option<String> {
+    if max == 0 || text.chars().count() <= max {
+        return None;
+    }
+    let kept: String = text.chars().take(max.saturating_sub(1)).collect();
+    Some(format!("{}…", kept))
+}
+
+///Mermaid label, for both flowcharts and sequence diagrams. Syntax characters become `#code;` entities.
+pub fn mermaid(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in sanitize(text).chars() {
+        match c {
+            '\n' => escaped.push_str("<br>"),
+            '"' | '#' | ';' | '<' | '>' | '&' | '`' | '%' | '|' | '\\' => {
+                escaped.push_str(&format!("#{};", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+///Mermaid tooltip, a quoted string that ends up as HTML in the page
+pub fn mermaid_tooltip(text: &str) -> String {
+    sanitize(text)
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', " ")
+}
+
+///Quoted DOT string. Backslashes are escaped too, so `\N` or `\l` in a value stay as written.
+pub fn dot(text: &str) -> String {
+    let escaped = sanitize(text)
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+/**
+PlantUML text, which runs to the end of the line and is read as Creole markup. <br>
+Tags, links, the `~` escape and quotes are written as `&#code;` entities,
+and so are doubled markers like `**` or `--` that would change the style.
+ */
+pub fn plantuml(text: &str) -> String {
+    let text = sanitize(text);
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let doubled = matches!(c, '*' | '_' | '/' | '-' | '=' | '\'') && chars.peek() == Some(&c);
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '&' | '<' | '>' | '\\' | '~' | '[' | ']' | '"' => {
+                escaped.push_str(&format!("&#{};", c as u32))
+            }
+            c if doubled => escaped.push_str(&format!("&#{};", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+///Text for SVG content and attribute values
+pub fn xml(text: &str) -> String {
+    sanitize(text)
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+///Frame of a folded stack, where `;` separates frames and the line ends the stack
+pub fn folded_frame(text: &str) -> String {
+    sanitize(text)
+        .chars()
+        .map(|c| match c {
+            ';' => ':',
+            '\n' => ' ',
+            c => c,
+        })
+        .collect()
+}
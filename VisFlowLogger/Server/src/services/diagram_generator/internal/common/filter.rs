@@ -0,0 +1,182 @@
+use crate::services::diagram_generator::api::models::block::{
+    DGBlock, DGBlockFlowType, DGBlockKind,
+};
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::DiagramOptions;
+use std::collections::HashMap;
+
+/**
+Graph with what the options leave out of the diagram removed, which every generator draws. <br>
+In order: the call tree of the focused block becomes the whole graph, direct recursion is folded into its first
+recursive call, calls below the depth limit are left out and `Log` flows are dropped.
+A block whose calls were left out is kept without its flows, and counts the blocks it stands for.
+ */
+pub fn filter(
+    mut graph: HashMap<String, DGBlock>,
+    options: &DiagramOptions,
+) -> Result<HashMap<String, DGBlock>, DiagramError> {
+    // Without a root the generator reports the graph as it is
+    if !graph.contains_key("START") {
+        return Ok(graph);
+    }
+    if let Some(focus) = &options.focus {
+        graph = focus_on(graph, focus)?;
+    }
+    if options.collapse_recursion {
+        fold_recursion(&mut graph);
+    }
+    if let Some(max_depth) = options.max_depth {
+        limit_depth(&mut graph, "START", 0, max_depth);
+    }
+    if options.hide_logs {
+        for block in graph.values_mut() {
+            block
+                .flow
+                .retain(|flow| flow.flow_type != DGBlockFlowType::Log);
+        }
+    }
+    Ok(graph)
+}
+
+///Keeps the call tree of a block, found by ID or else by the first block with the name in flow order, as START
+fn focus_on(
+    mut graph: HashMap<String, DGBlock>,
+    focus: &str,
+) -> Result<HashMap<String, DGBlock>, DiagramError> {
+    let root_id = if graph.contains_key(focus) {
+        focus.to_string()
+    } else {
+        let mut order = vec!["START".to_string()];
+        descendants(&graph, "START", &mut order);
+        // Blocks nothing calls, which only the lenient mode keeps, are searched last
+        let mut unreached: Vec<&String> = graph
+            .keys()
+            .filter(|block_id| !order.contains(block_id))
+            .collect();
+        unreached.sort();
+        let unreached: Vec<String> = unreached.into_iter().cloned().collect();
+        order
+            .into_iter()
+            .chain(unreached)
+            .find(|block_id| graph[block_id].name == focus)
+            .ok_or_else(|| DiagramError::InvalidOption(format!("no block {} to focus on", focus)))?
+    };
+    if root_id == "START" {
+        return Ok(graph);
+    }
+    let mut below = Vec::new();
+    descendants(&graph, &root_id, &mut below);
+    let mut root = graph.remove(&root_id).unwrap();
+    root.caller = None;
+    let mut focused: HashMap<String, DGBlock> = below
+        .into_iter()
+        .filter_map(|block_id| graph.remove_entry(&block_id))
+        .collect();
+    for block in focused.values_mut() {
+        if block.caller.as_deref() == Some(root_id.as_str()) {
+            block.caller = Some("START".to_string());
+        }
+    }
+    focused.insert("START".to_string(), root);
+    Ok(focused)
+}
+
+///Folds every call of a block to a block of the same name, and all that one called, into a single `×N` block
+fn fold_recursion(graph: &mut HashMap<String, DGBlock>) {
+    let mut order = vec!["START".to_string()];
+    descendants(graph, "START", &mut order);
+    for block_id in order {
+        // Gone with a recursion folded before
+        let Some(block) = graph.get(&block_id) else {
+            continue;
+        };
+        let recursive: Vec<String> = called_ids(block)
+            .filter(|called_id| {
+                graph.get(*called_id).is_some_and(|called| {
+                    called.kind == DGBlockKind::Block && called.name == block.name
+                })
+            })
+            .cloned()
+            .collect();
+        for called_id in recursive {
+            let name = graph[&called_id].name.clone();
+            let removed = cut(graph, &called_id);
+            let runs = 1 + removed
+                .iter()
+                .filter(|block| block.kind == DGBlockKind::Block && block.name == name)
+                .count();
+            let hidden = hidden(&removed) - (runs - 1);
+            let called = graph.get_mut(&called_id).unwrap();
+            called.runs = Some(runs);
+            called.hidden = (hidden > 0).then_some(hidden);
+        }
+    }
+}
+
+///Leaves out the blocks called more than `max_depth` calls below START. Loops count as a call.
+fn limit_depth(
+    graph: &mut HashMap<String, DGBlock>,
+    block_id: &str,
+    depth: usize,
+    max_depth: usize,
+) {
+    let Some(block) = graph.get(block_id) else {
+        return;
+    };
+    let called: Vec<String> = called_ids(block).cloned().collect();
+    if called.is_empty() {
+        return;
+    }
+    if depth >= max_depth {
+        let removed = cut(graph, block_id);
+        let block = graph.get_mut(block_id).unwrap();
+        block.hidden = Some(hidden(&removed) + block.hidden.unwrap_or_default());
+        return;
+    }
+    for called_id in called {
+        limit_depth(graph, &called_id, depth + 1, max_depth);
+    }
+}
+
+/**
+Removes every block below a block and its flows, which pointed at them. <br>
+The block fails when one of them did, so the path of an error still shows.
+ */
+fn cut(graph: &mut HashMap<String, DGBlock>, block_id: &str) -> Vec<DGBlock> {
+    let mut below = Vec::new();
+    descendants(graph, block_id, &mut below);
+    let removed: Vec<DGBlock> = below
+        .iter()
+        .filter_map(|block_id| graph.remove(block_id))
+        .collect();
+    let block = graph.get_mut(block_id).unwrap();
+    block.flow.clear();
+    block.failed |= removed.iter().any(|block| block.failed);
+    removed
+}
+
+///Number of blocks removed, with those they stood for themselves
+fn hidden(removed: &[DGBlock]) -> usize {
+    removed
+        .iter()
+        .map(|block| 1 + block.hidden.unwrap_or_default())
+        .sum()
+}
+
+///Blocks below a block, in flow order
+fn descendants(graph: &HashMap<String, DGBlock>, block_id: &str, found: &mut Vec<String>) {
+    let Some(block) = graph.get(block_id) else {
+        return;
+    };
+    for called_id in called_ids(block) {
+        found.push(called_id.clone());
+        descendants(graph, called_id, found);
+    }
+}
+
+fn called_ids(block: &DGBlock) -> impl Iterator<Item = &String> {
+    block
+        .flow
+        .iter()
+        .filter_map(|flow| flow.flow_pointer_id.as_ref())
+}
@@ -0,0 +1,89 @@
+use crate::services::diagram_generator::api::models::block::DGBlock;
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::internal::common::escape::folded_frame;
+use std::collections::{BTreeMap, HashMap};
+
+///What the weight of a stack counts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weight {
+    ///Entries logged in the block: its START, and every flow that isn't a call of another block
+    Entries,
+    ///Exclusive time of the block in microseconds
+    Microseconds,
+}
+
+/**
+Call stacks of a graph with the weight of their innermost block, sorted by stack. <br>
+Weighted by duration when the starting block has one, else by entry count.
+Stacks that weigh nothing are left out.
+ */
+pub struct FoldedStacks {
+    pub weight: Weight,
+    ///Frames from the starting block down, and the weight of the last one. Escaped only by `to_folded`
+    pub stacks: Vec<(Vec<String>, u64)>,
+}
+
+impl FoldedStacks {
+    pub fn new(graph: &HashMap<String, DGBlock>) -> Result<FoldedStacks, DiagramError> {
+        let start = graph.get("START").ok_or(DiagramError::MissingStartBlock)?;
+        let weight = if start.duration.is_some() {
+            Weight::Microseconds
+        } else {
+            Weight::Entries
+        };
+        let mut stacks = BTreeMap::new();
+        let mut path = Vec::new();
+        collect(graph, start, weight, &mut path, &mut stacks)?;
+        Ok(FoldedStacks {
+            weight,
+            stacks: stacks
+                .into_iter()
+                .filter(|(_, weight)| *weight > 0)
+                .collect(),
+        })
+    }
+
+    ///Brendan Gregg's folded format, one `frame;frame;frame weight` line per stack
+    pub fn to_folded(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|(stack, weight)| {
+                let frames: Vec<String> = stack.iter().map(|name| folded_frame(name)).collect();
+                format!("{} {}\n", frames.join(";"), weight)
+            })
+            .collect()
+    }
+}
+
+fn collect(
+    graph: &HashMap<String, DGBlock>,
+    block: &DGBlock,
+    weight: Weight,
+    path: &mut Vec<String>,
+    stacks: &mut BTreeMap<Vec<String>, u64>,
+) -> Result<(), DiagramError> {
+    path.push(block.frame());
+    let own = match weight {
+        Weight::Entries => {
+            1 + block
+                .flow
+                .iter()
+                .filter(|flow| flow.flow_pointer_id.is_none())
+                .count() as u64
+        }
+        Weight::Microseconds => block.duration.map_or(0, |d| d.exclusive_us),
+    };
+    *stacks.entry(path.clone()).or_default() += own;
+    for block_id in block
+        .flow
+        .iter()
+        .filter_map(|flow| flow.flow_pointer_id.as_ref())
+    {
+        let called = graph
+            .get(block_id)
+            .ok_or_else(|| DiagramError::UnknownBlock(block_id.clone()))?;
+        collect(graph, called, weight, path, stacks)?;
+    }
+    path.pop();
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use crate::services::diagram_generator::api::models::block::{
+    DGBlock, DGBlockFlow, DGBlockFlowType,
+};
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::internal::common::style::folded_label;
+use std::collections::HashMap;
+
+pub fn called_block<'a>(
+    graph: &'a HashMap<String, DGBlock>,
+    flow: &DGBlockFlow,
+) -> Result<&'a DGBlock, DiagramError> {
+    let block_id = flow.flow_pointer_id.clone().unwrap_or_default();
+    graph
+        .get(&block_id)
+        .ok_or(DiagramError::UnknownBlock(block_id))
+}
+
+///Text of a flow's node: the called block for calls, the value for the rest
+pub fn flow_label(
+    graph: &HashMap<String, DGBlock>,
+    flow: &DGBlockFlow,
+) -> Result<String, DiagramError> {
+    let value = flow.value.clone().unwrap_or_default();
+    Ok(match flow.flow_type {
+        DGBlockFlowType::Call | DGBlockFlowType::CallError | DGBlockFlowType::AsyncCall => {
+            let called = called_block(graph, flow)?;
+            match folded_label(called) {
+                Some(folded) => format!("{} {}", called.name, folded),
+                None => called.name.clone(),
+            }
+        }
+        DGBlockFlowType::Loop => format!(
+            "{} ×{}",
+            called_block(graph, flow)?.name,
+            flow.value.as_deref().unwrap_or("0")
+        ),
+        DGBlockFlowType::LoopIteration if value.is_empty() => "iteration".to_string(),
+        DGBlockFlowType::LoopIteration => format!("iteration {}", value),
+        DGBlockFlowType::CallStore
+        | DGBlockFlowType::Store
+        | DGBlockFlowType::Log
+        | DGBlockFlowType::Error
+        | DGBlockFlowType::Branch
+        | DGBlockFlowType::ExternalCall
+        | DGBlockFlowType::ExternalCallStore => value,
+    })
+}
@@ -0,0 +1,266 @@
+use crate::services::diagram_generator::api::models::block::{DGBlock, DGBlockFlowType};
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::api::models::diagram_options::Direction;
+use std::collections::{HashMap, HashSet};
+
+pub const MARGIN: f64 = 20.0;
+pub const NODE_WIDTH: f64 = 180.0;
+pub const NODE_HEIGHT: f64 = 36.0;
+/// Space between two flows of a block, where the internal arrow goes
+pub const NODE_GAP: f64 = 24.0;
+pub const BLOCK_PADDING: f64 = 12.0;
+pub const BLOCK_TITLE_HEIGHT: f64 = 24.0;
+/// Space between a block and the blocks it calls, where the call links go
+const CALL_GAP: f64 = 90.0;
+/// Space between the call trees of two callees of the same block
+const TREE_GAP: f64 = 24.0;
+/// The START and END nodes
+pub const TERMINAL_RADIUS: f64 = 18.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn center_x(&self) -> f64 {
+        self.x + self.width / 2.0
+    }
+
+    pub fn center_y(&self) -> f64 {
+        self.y + self.height / 2.0
+    }
+
+    pub fn right(&self) -> f64 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.y + self.height
+    }
+}
+
+pub struct BlockLayout {
+    pub rect: Rect,
+    /// One box per flow, in flow order
+    pub nodes: Vec<Rect>,
+}
+
+impl BlockLayout {
+    ///Box the links into the block point at. Empty blocks are linked at their frame.
+    pub fn entry(&self) -> Rect {
+        self.nodes.first().copied().unwrap_or(self.rect)
+    }
+
+    pub fn exit(&self) -> Rect {
+        self.nodes.last().copied().unwrap_or(self.rect)
+    }
+}
+
+/**
+Position of every block and flow of a graph. <br>
+Flows follow each other along the direction, top to bottom or left to right. A called block goes beside its caller,
+level with the flow that called it and after the call trees of the callees before it, so boxes never overlap. <br>
+Blocks are placed on two axes: `u` runs along the flows and `v` along the calls.
+ */
+pub struct Layout {
+    pub direction: Direction,
+    pub width: f64,
+    pub height: f64,
+    pub blocks: HashMap<String, BlockLayout>,
+    pub start: (f64, f64),
+    pub end: (f64, f64),
+}
+
+impl Layout {
+    pub fn new(
+        graph: &HashMap<String, DGBlock>,
+        direction: Direction,
+    ) -> Result<Layout, DiagramError> {
+        if !graph.contains_key("START") {
+            return Err(DiagramError::MissingStartBlock);
+        }
+        let mut layout = Layout {
+            direction,
+            width: 0.0,
+            height: 0.0,
+            blocks: HashMap::new(),
+            start: (0.0, 0.0),
+            end: (0.0, 0.0),
+        };
+        let top = MARGIN + 2.0 * TERMINAL_RADIUS + NODE_GAP;
+        let mut visited = HashSet::new();
+        let mut last_u = layout.place(graph, "START", top, MARGIN, &mut visited)?;
+
+        let root = &layout.blocks["START"];
+        let root_v = match direction {
+            Direction::Tb => root.entry().center_x(),
+            Direction::Lr => root.entry().center_y(),
+        };
+        let root_end = match direction {
+            Direction::Tb => root.rect.bottom(),
+            Direction::Lr => root.rect.right(),
+        };
+        let end_u = root_end + NODE_GAP + TERMINAL_RADIUS;
+        layout.start = layout.point(MARGIN + TERMINAL_RADIUS, root_v);
+        layout.end = layout.point(end_u, root_v);
+        last_u = last_u.max(end_u + TERMINAL_RADIUS);
+
+        // Blocks nothing calls, which only the lenient mode keeps, go after the rest
+        let mut unreached: Vec<&String> = graph
+            .keys()
+            .filter(|block_id| !visited.contains(block_id.as_str()))
+            .collect();
+        unreached.sort();
+        for block_id in unreached {
+            if !visited.contains(block_id.as_str()) {
+                last_u = layout.place(graph, block_id, last_u + TREE_GAP, MARGIN, &mut visited)?;
+            }
+        }
+
+        let rects = || layout.blocks.values().map(|block| block.rect);
+        layout.width = rects().map(|rect| rect.right()).fold(0.0, f64::max) + MARGIN;
+        layout.height = rects().map(|rect| rect.bottom()).fold(0.0, f64::max) + MARGIN;
+        let (end_x, end_y) = layout.end;
+        layout.width = layout.width.max(end_x + TERMINAL_RADIUS + MARGIN);
+        layout.height = layout.height.max(end_y + TERMINAL_RADIUS + MARGIN);
+        Ok(layout)
+    }
+
+    ///Places a block and its call tree with the block's corner at `u`, `v`. Returns where the tree ends along `u`.
+    fn place<'a>(
+        &mut self,
+        graph: &'a HashMap<String, DGBlock>,
+        block_id: &'a str,
+        u: f64,
+        v: f64,
+        visited: &mut HashSet<&'a str>,
+    ) -> Result<f64, DiagramError> {
+        visited.insert(block_id);
+        let block = &graph[block_id];
+        // The title is above the flows, which is along them top to bottom and across them left to right
+        let (node_u, node_v, head_u, head_v) = match self.direction {
+            Direction::Tb => (
+                NODE_HEIGHT,
+                NODE_WIDTH,
+                BLOCK_TITLE_HEIGHT + BLOCK_PADDING,
+                BLOCK_PADDING,
+            ),
+            Direction::Lr => (
+                NODE_WIDTH,
+                NODE_HEIGHT,
+                BLOCK_PADDING,
+                BLOCK_TITLE_HEIGHT + BLOCK_PADDING,
+            ),
+        };
+        let node_start = |i: usize| u + head_u + i as f64 * (node_u + NODE_GAP);
+        let nodes: Vec<Rect> = (0..block.flow.len())
+            .map(|i| self.rect(node_start(i), v + head_v, node_u, node_v))
+            .collect();
+        let content_u = match block.flow.len() {
+            0 => node_u / 2.0,
+            flows => node_start(flows - 1) + node_u - u - head_u,
+        };
+        let block_u = head_u + content_u + BLOCK_PADDING;
+        let block_v = head_v + node_v + BLOCK_PADDING;
+        let rect = self.rect(u, v, block_u, block_v);
+
+        let mut last_u = u + block_u;
+        let mut free_u = u;
+        for (i, flow) in block.flow.iter().enumerate() {
+            if !is_call(&flow.flow_type) {
+                continue;
+            }
+            let called_id = flow.flow_pointer_id.as_deref().unwrap_or_default();
+            let Some((called_id, _)) = graph.get_key_value(called_id) else {
+                return Err(DiagramError::UnknownBlock(called_id.to_string()));
+            };
+            if visited.contains(called_id.as_str()) {
+                continue;
+            }
+            // Level the callee's first flow with the call
+            let called_u = free_u.max(node_start(i) - head_u);
+            let called_last_u =
+                self.place(graph, called_id, called_u, v + block_v + CALL_GAP, visited)?;
+            free_u = called_last_u + TREE_GAP;
+            last_u = last_u.max(called_last_u);
+        }
+        self.blocks
+            .insert(block_id.to_string(), BlockLayout { rect, nodes });
+        Ok(last_u)
+    }
+
+    fn rect(&self, u: f64, v: f64, size_u: f64, size_v: f64) -> Rect {
+        let ((x, y), (width, height)) = match self.direction {
+            Direction::Tb => ((v, u), (size_v, size_u)),
+            Direction::Lr => ((u, v), (size_u, size_v)),
+        };
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn point(&self, u: f64, v: f64) -> (f64, f64) {
+        match self.direction {
+            Direction::Tb => (v, u),
+            Direction::Lr => (u, v),
+        }
+    }
+
+    ///Where the arrow to the next flow leaves a box
+    pub fn flow_out(&self, rect: &Rect) -> (f64, f64) {
+        match self.direction {
+            Direction::Tb => (rect.center_x(), rect.bottom()),
+            Direction::Lr => (rect.right(), rect.center_y()),
+        }
+    }
+
+    pub fn flow_in(&self, rect: &Rect) -> (f64, f64) {
+        match self.direction {
+            Direction::Tb => (rect.center_x(), rect.y),
+            Direction::Lr => (rect.x, rect.center_y()),
+        }
+    }
+
+    ///Where a call link leaves a box, moved by `offset` along the box so calls and returns don't overlap
+    pub fn call_out(&self, rect: &Rect, offset: f64) -> (f64, f64) {
+        match self.direction {
+            Direction::Tb => (rect.right(), rect.center_y() + offset),
+            Direction::Lr => (rect.center_x() + offset, rect.bottom()),
+        }
+    }
+
+    pub fn call_in(&self, rect: &Rect, offset: f64) -> (f64, f64) {
+        match self.direction {
+            Direction::Tb => (rect.x, rect.center_y() + offset),
+            Direction::Lr => (rect.center_x() + offset, rect.y),
+        }
+    }
+
+    ///Side of a terminal node facing the starting block
+    pub fn terminal_side(&self, center: (f64, f64), towards: f64) -> (f64, f64) {
+        let offset = TERMINAL_RADIUS * towards;
+        match self.direction {
+            Direction::Tb => (center.0, center.1 + offset),
+            Direction::Lr => (center.0 + offset, center.1),
+        }
+    }
+}
+
+///Flows that point at the block they called
+pub fn is_call(flow_type: &DGBlockFlowType) -> bool {
+    matches!(
+        flow_type,
+        DGBlockFlowType::Call
+            | DGBlockFlowType::CallStore
+            | DGBlockFlowType::CallError
+            | DGBlockFlowType::AsyncCall
+            | DGBlockFlowType::Loop
+    )
+}
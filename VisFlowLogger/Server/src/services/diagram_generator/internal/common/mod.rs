@@ -0,0 +1,7 @@
+pub mod escape;
+pub mod filter;
+pub mod folded_stack;
+pub mod label;
+pub mod layout;
+pub mod sequence;
+pub mod style;
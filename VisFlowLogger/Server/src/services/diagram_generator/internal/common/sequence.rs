@@ -0,0 +1,166 @@
+use crate::services::diagram_generator::api::models::block::{DGBlock, DGBlockFlowType};
+use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+use crate::services::diagram_generator::internal::common::label::called_block;
+use crate::services::diagram_generator::internal::common::style::folded_label;
+use std::collections::HashMap;
+
+///One message or note of a sequence diagram. Participants are block names.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Step<'a> {
+    ///Activates `to`, unless the call is asynchronous
+    Call {
+        from: &'a str,
+        to: &'a str,
+        asynchronous: bool,
+    },
+    ///Deactivates `from`
+    Return {
+        from: &'a str,
+        to: &'a str,
+        label: Option<String>,
+        failed: bool,
+    },
+    Note {
+        over: &'a str,
+        text: String,
+        failed: bool,
+    },
+    ///Call to something that doesn't log, drawn as the `External` participant
+    ExternalCall {
+        from: &'a str,
+        label: &'a str,
+    },
+    ExternalReturn {
+        to: &'a str,
+        label: &'a str,
+    },
+    LoopStart {
+        label: String,
+    },
+    LoopEnd,
+}
+
+///Steps of a graph in flow order, walked from `START`
+pub struct Sequence<'a> {
+    ///Block names in the order they first take part
+    pub participants: Vec<&'a str>,
+    ///Whether an `External` participant is needed
+    pub external: bool,
+    pub root: &'a str,
+    pub steps: Vec<Step<'a>>,
+}
+
+impl<'a> Sequence<'a> {
+    pub fn new(graph: &'a HashMap<String, DGBlock>) -> Result<Sequence<'a>, DiagramError> {
+        let start = graph.get("START").ok_or(DiagramError::MissingStartBlock)?;
+        let mut sequence = Sequence {
+            participants: vec![start.name.as_str()],
+            external: false,
+            root: &start.name,
+            steps: Vec::new(),
+        };
+        sequence.walk(graph, start, &start.name)?;
+        Ok(sequence)
+    }
+
+    ///Adds the steps of `block`. Loops have no participant of their own, they run in `participant`.
+    fn walk(
+        &mut self,
+        graph: &'a HashMap<String, DGBlock>,
+        block: &'a DGBlock,
+        participant: &'a str,
+    ) -> Result<(), DiagramError> {
+        //Calls the diagram options left out are only counted
+        if let Some(folded) = folded_label(block) {
+            self.steps.push(Step::Note {
+                over: participant,
+                text: folded,
+                failed: false,
+            });
+        }
+        for flow in &block.flow {
+            let value = flow.value.as_deref().unwrap_or_default();
+            let note = |text: String, failed: bool| Step::Note {
+                over: participant,
+                text,
+                failed,
+            };
+            match flow.flow_type {
+                DGBlockFlowType::Log => self.steps.push(note(value.to_string(), false)),
+                DGBlockFlowType::Store => {
+                    self.steps.push(note(format!("stored: {}", value), false))
+                }
+                DGBlockFlowType::Error => self.steps.push(note(format!("error: {}", value), true)),
+                DGBlockFlowType::Branch => {
+                    self.steps.push(note(format!("branch: {}", value), false))
+                }
+                DGBlockFlowType::LoopIteration => {
+                    let text = if value.is_empty() {
+                        "iteration".to_string()
+                    } else {
+                        format!("iteration {}", value)
+                    };
+                    self.steps.push(note(text, false));
+                }
+                DGBlockFlowType::ExternalCall => {
+                    self.external = true;
+                    self.steps.push(Step::ExternalCall {
+                        from: participant,
+                        label: value,
+                    });
+                }
+                DGBlockFlowType::ExternalCallStore => {
+                    self.external = true;
+                    self.steps.push(Step::ExternalReturn {
+                        to: participant,
+                        label: value,
+                    });
+                }
+                DGBlockFlowType::Loop => {
+                    let called = called_block(graph, flow)?;
+                    self.steps.push(Step::LoopStart {
+                        label: format!("{} ×{}", called.name, value),
+                    });
+                    self.walk(graph, called, participant)?;
+                    self.steps.push(Step::LoopEnd);
+                }
+                DGBlockFlowType::AsyncCall => {
+                    let called = called_block(graph, flow)?;
+                    self.call(participant, called, true);
+                    self.walk(graph, called, &called.name)?;
+                }
+                DGBlockFlowType::Call | DGBlockFlowType::CallStore | DGBlockFlowType::CallError => {
+                    let called = called_block(graph, flow)?;
+                    self.call(participant, called, false);
+                    self.walk(graph, called, &called.name)?;
+                    let label = match flow.flow_type {
+                        DGBlockFlowType::CallStore => Some(value.to_string()),
+                        DGBlockFlowType::CallError => {
+                            Some(flow.value.clone().unwrap_or("throws".to_string()))
+                        }
+                        _ if called.aborted => Some("(aborted)".to_string()),
+                        _ => None,
+                    };
+                    self.steps.push(Step::Return {
+                        from: &called.name,
+                        to: participant,
+                        label,
+                        failed: flow.flow_type == DGBlockFlowType::CallError,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, from: &'a str, called: &'a DGBlock, asynchronous: bool) {
+        if !self.participants.contains(&called.name.as_str()) {
+            self.participants.push(&called.name);
+        }
+        self.steps.push(Step::Call {
+            from,
+            to: &called.name,
+            asynchronous,
+        });
+    }
+}
@@ -0,0 +1,328 @@
+use crate::services::diagram_generator::api::models::block::{
+    DGBlock, DGBlockChange, DGBlockFlowType,
+};
+use crate::services::diagram_generator::api::models::diagram_options::{
+    DiagramOptions, Shape, Theme,
+};
+use std::collections::HashMap;
+
+///Colors of a theme
+pub struct Palette {
+    pub background: &'static str,
+    pub text: &'static str,
+    ///Links, borders and arrow heads
+    pub line: &'static str,
+    ///Fill of the flow nodes
+    pub node: &'static str,
+    ///Fill and text of the START and END nodes
+    pub start: &'static str,
+    pub start_text: &'static str,
+    ///Failed blocks and the path an error took
+    pub failed: &'static str,
+    ///Fill and text of failed nodes
+    pub failed_fill: &'static str,
+    pub failed_text: &'static str,
+    ///Borders and fills of what a diff added, removed or changed
+    pub added: &'static str,
+    pub added_fill: &'static str,
+    pub removed: &'static str,
+    pub removed_fill: &'static str,
+    pub changed: &'static str,
+    pub changed_fill: &'static str,
+    pub blocks: BlockFills,
+    ///Blocks without a duration when others have one
+    pub unmeasured: &'static str,
+    ///Heat colors from blocks that took no time of their own to the slowest one, as RGB
+    pub cold: (u8, u8, u8),
+    pub hot: (u8, u8, u8),
+}
+
+///How blocks get their fill when the trace has no times
+pub enum BlockFills {
+    ///Hashed from the name, with every channel in `min..=min + 127`
+    Hashed { min: u8 },
+    ///Picked from the list by the hash of the name
+    Fixed(&'static [&'static str]),
+}
+
+const LIGHT: Palette = Palette {
+    background: "#FFFFFF",
+    text: "#000000",
+    line: "#333333",
+    node: "#FFFFFF",
+    start: "#FF5733",
+    start_text: "#000000",
+    failed: "#D50000",
+    failed_fill: "#FFCDD2",
+    failed_text: "#B71C1C",
+    added: "#2E7D32",
+    added_fill: "#C8E6C9",
+    removed: "#C62828",
+    removed_fill: "#FFCDD2",
+    changed: "#EF6C00",
+    changed_fill: "#FFE0B2",
+    blocks: BlockFills::Hashed { min: 128 },
+    unmeasured: "#EEEEEE",
+    cold: (255, 249, 196),
+    hot: (229, 57, 53),
+};
+
+const DARK: Palette = Palette {
+    background: "#1E1E1E",
+    text: "#E0E0E0",
+    line: "#B0B0B0",
+    node: "#2D2D2D",
+    start: "#C2410C",
+    start_text: "#FFFFFF",
+    failed: "#FF5252",
+    failed_fill: "#5C1A1A",
+    failed_text: "#FFCDD2",
+    added: "#66BB6A",
+    added_fill: "#1B3A1E",
+    removed: "#FF5252",
+    removed_fill: "#5C1A1A",
+    changed: "#FFA726",
+    changed_fill: "#4A3210",
+    blocks: BlockFills::Hashed { min: 24 },
+    unmeasured: "#3A3A3A",
+    cold: (62, 58, 40),
+    hot: (183, 28, 28),
+};
+
+const HIGH_CONTRAST: Palette = Palette {
+    background: "#000000",
+    text: "#FFFFFF",
+    line: "#FFFFFF",
+    node: "#000000",
+    start: "#FFFF00",
+    start_text: "#000000",
+    failed: "#FF3B3B",
+    failed_fill: "#000000",
+    failed_text: "#FF3B3B",
+    added: "#00FF00",
+    added_fill: "#000000",
+    removed: "#FF3B3B",
+    removed_fill: "#000000",
+    changed: "#FFA500",
+    changed_fill: "#000000",
+    blocks: BlockFills::Fixed(&["#1A1A1A"]),
+    unmeasured: "#1A1A1A",
+    cold: (26, 26, 26),
+    hot: (128, 0, 0),
+};
+
+///Okabe-Ito, with vermillion kept for failures and blue for heat
+const COLOR_BLIND: Palette = Palette {
+    background: "#FFFFFF",
+    text: "#000000",
+    line: "#333333",
+    node: "#FFFFFF",
+    start: "#F0E442",
+    start_text: "#000000",
+    failed: "#D55E00",
+    failed_fill: "#F6D0B3",
+    failed_text: "#7A3600",
+    added: "#009E73",
+    added_fill: "#BFE7DC",
+    removed: "#D55E00",
+    removed_fill: "#F6D0B3",
+    changed: "#E69F00",
+    changed_fill: "#F9E3B3",
+    blocks: BlockFills::Fixed(&[
+        "#E69F00", "#56B4E9", "#009E73", "#F0E442", "#0072B2", "#CC79A7", "#999999",
+    ]),
+    unmeasured: "#EEEEEE",
+    cold: (222, 235, 247),
+    hot: (49, 130, 189),
+};
+
+pub fn palette(theme: Theme) -> &'static Palette {
+    match theme {
+        Theme::Light => &LIGHT,
+        Theme::Dark => &DARK,
+        Theme::HighContrast => &HIGH_CONTRAST,
+        Theme::ColorBlind => &COLOR_BLIND,
+    }
+}
+
+///Node shape of a flow type, from the options or the default every flowchart shares
+pub fn flow_shape(flow_type: DGBlockFlowType, options: &DiagramOptions) -> Shape {
+    if let Some(shape) = options.shapes.get(&flow_type) {
+        return *shape;
+    }
+    match flow_type {
+        DGBlockFlowType::Log => Shape::Stadium,
+        DGBlockFlowType::Call | DGBlockFlowType::CallError => Shape::Rectangle,
+        DGBlockFlowType::CallStore | DGBlockFlowType::Store => Shape::Parallelogram,
+        DGBlockFlowType::AsyncCall => Shape::Subroutine,
+        DGBlockFlowType::Error => Shape::Flag,
+        DGBlockFlowType::ExternalCall => Shape::InvertedTrapezoid,
+        DGBlockFlowType::ExternalCallStore => Shape::Trapezoid,
+        DGBlockFlowType::Branch => Shape::Diamond,
+        DGBlockFlowType::Loop => Shape::Hexagon,
+        DGBlockFlowType::LoopIteration => Shape::Circle,
+    }
+}
+
+///Flows drawn in the failed colors
+pub fn is_failure(flow_type: DGBlockFlowType) -> bool {
+    matches!(
+        flow_type,
+        DGBlockFlowType::Error | DGBlockFlowType::CallError
+    )
+}
+
+///Title of a block's subgraph: its name or loop label, whether it was aborted and how long it ran
+pub fn block_title(block: &DGBlock) -> String {
+    let mut title = block.frame();
+    if let Some(folded) = folded_label(block) {
+        title += &format!(" {}", folded);
+    }
+    if block.aborted {
+        title += " (aborted)";
+    }
+    if let Some(duration) = block.duration {
+        title += &format!(" · {}", duration.label());
+    }
+    title
+}
+
+///What the diagram options folded into a block, like `×5 +2 hidden`
+pub fn folded_label(block: &DGBlock) -> Option<String> {
+    let label = match (block.runs, block.hidden) {
+        (None, None) => return None,
+        (Some(runs), None) => format!("×{}", runs),
+        (None, Some(hidden)) => format!("+{} hidden", hidden),
+        (Some(runs), Some(hidden)) => format!("×{} +{} hidden", runs, hidden),
+    };
+    Some(label)
+}
+
+/**
+Fill of every block by key. <br>
+Blocks a diff added or removed get the colors of their change. Otherwise a color set for the block name wins,
+then it is hashed from the name, or set by the time spent in the block once the trace has times.
+ */
+pub fn block_colors(
+    graph: &HashMap<String, DGBlock>,
+    options: &DiagramOptions,
+) -> HashMap<String, String> {
+    let palette = palette(options.theme);
+    let max_exclusive_us = graph
+        .values()
+        .filter_map(|block| block.duration)
+        .map(|duration| duration.exclusive_us)
+        .max();
+    graph
+        .iter()
+        .map(|(block_id, block)| {
+            if let Some(change) = block.change {
+                return (
+                    block_id.clone(),
+                    change_colors(change, palette).1.to_string(),
+                );
+            }
+            let color = match (max_exclusive_us, block.duration) {
+                _ if options.block_colors.contains_key(&block.name) => {
+                    options.block_colors[&block.name].clone()
+                }
+                (None, _) => name_color(&block.name, options.seed, palette),
+                (Some(max), Some(duration)) => heat_color(duration.exclusive_us, max, palette),
+                (Some(_), None) => palette.unmeasured.to_string(),
+            };
+            (block_id.clone(), color)
+        })
+        .collect()
+}
+
+///Border and fill of what a diff added, removed or changed
+pub fn change_colors(change: DGBlockChange, palette: &Palette) -> (&'static str, &'static str) {
+    match change {
+        DGBlockChange::Added => (palette.added, palette.added_fill),
+        DGBlockChange::Removed => (palette.removed, palette.removed_fill),
+        DGBlockChange::Changed => (palette.changed, palette.changed_fill),
+    }
+}
+
+///FNV-1a of the text, after the seed if there is one
+pub fn hash(text: &str, seed: Option<u64>) -> u64 {
+    let seed = seed.map(u64::to_le_bytes);
+    seed.iter()
+        .flatten()
+        .chain(text.as_bytes())
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+///Color of a block in the theme, the same for a name in every diagram
+pub fn name_color(name: &str, seed: Option<u64>, palette: &Palette) -> String {
+    let hash = hash(name, seed);
+    match palette.blocks {
+        BlockFills::Hashed { min } => {
+            let channel = |shift: u32| min + ((hash >> shift) & 0x7F) as u8;
+            format!("#{:02X}{:02X}{:02X}", channel(0), channel(8), channel(16))
+        }
+        BlockFills::Fixed(colors) => colors[(hash % colors.len() as u64) as usize].to_string(),
+    }
+}
+
+///`rgb(r, g, b)` of a `#RRGGBB` or `#RGB` color, for syntaxes that don't take hex
+pub fn rgb(hex: &str) -> String {
+    let hex: String = match hex.len() {
+        4 => hex[1..].chars().flat_map(|c| [c, c]).collect(),
+        _ => hex[1..].to_string(),
+    };
+    let channel =
+        |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or("00"), 16).unwrap_or_default();
+    format!("rgb({}, {}, {})", channel(0), channel(2), channel(4))
+}
+
+///From the theme's cold color for blocks that took no time of their own to its hot color for the slowest one
+fn heat_color(exclusive_us: u64, max_exclusive_us: u64, palette: &Palette) -> String {
+    let heat = exclusive_us as f64 / max_exclusive_us.max(1) as f64;
+    let channel =
+        |cold: u8, hot: u8| (cold as f64 + (hot as f64 - cold as f64) * heat).round() as u8;
+    format!(
+        "#{:02X}{:02X}{:02X}",
+        channel(palette.cold.0, palette.hot.0),
+        channel(palette.cold.1, palette.hot.1),
+        channel(palette.cold.2, palette.hot.2)
+    )
+}
+
+///Mermaid `init` directive with the theme's colors. Empty for the light theme, which is Mermaid's own.
+pub fn mermaid_theme(theme: Theme) -> String {
+    if theme == Theme::Light {
+        return String::new();
+    }
+    let palette = palette(theme);
+    let variables = [
+        ("background", palette.background),
+        ("primaryColor", palette.node),
+        ("primaryTextColor", palette.text),
+        ("primaryBorderColor", palette.line),
+        ("lineColor", palette.line),
+        ("textColor", palette.text),
+        ("titleColor", palette.text),
+        ("clusterBorder", palette.line),
+        ("noteBkgColor", palette.node),
+        ("noteTextColor", palette.text),
+        ("actorBkg", palette.node),
+        ("actorTextColor", palette.text),
+        ("actorBorder", palette.line),
+        ("actorLineColor", palette.line),
+        ("signalColor", palette.line),
+        ("signalTextColor", palette.text),
+        ("labelTextColor", palette.text),
+        ("loopTextColor", palette.text),
+    ];
+    let variables: Vec<String> = variables
+        .iter()
+        .map(|(name, color)| format!("\"{}\": \"{}\"", name, color))
+        .collect();
+    format!(
+        "%%{{init: {{\"theme\": \"base\", \"themeVariables\": {{{}}}}}}}%%\n",
+        variables.join(", ")
+    )
+}
@@ -1 +1,4 @@
 pub mod application;
+mod common;
+#[cfg(test)]
+mod unit_test;
@@ -0,0 +1,1047 @@
+use crate::services::diagram_generator::api::models::block::DGBlock;
+use crate::services::diagram_generator::api::models::diagram_options::DiagramOptions;
+use crate::services::diagram_generator::api::services::DiagramGenerator;
+use crate::services::diagram_generator::internal::application::mermaid_diagram_generator::MermaidDiagramGenerator;
+use crate::services::graph_generator::api::models::vis_flow_log_entry::{
+    VisFlowLogEntry, VisFlowLogEntryLogType,
+};
+use crate::services::graph_generator::api::services::graph_generator::GraphGenerator;
+use crate::services::graph_generator::test_fixture;
+use std::collections::HashMap;
+
+fn graph(entries: &[(VisFlowLogEntryLogType, &str, Option<&str>)]) -> HashMap<String, DGBlock> {
+    test_fixture::graph(entries)
+        .into_iter()
+        .map(|(key, block)| (key, DGBlock::from(block)))
+        .collect()
+}
+
+#[test]
+fn mermaid_highlights_the_failing_path() {
+    use VisFlowLogEntryLogType::{End, Error, Log, Start, Throw};
+    let graph = graph(&[
+        (Start, "main", None),
+        (Start, "load", None),
+        (Log, "load", Some("reading")),
+        (Throw, "load", Some("file not found")),
+        (Error, "main", Some("retrying")),
+        (End, "main", None),
+    ]);
+    let load_id = graph
+        .iter()
+        .find(|(_, block)| block.name == "load")
+        .map(|(id, _)| id.clone())
+        .unwrap();
+    let diagram = MermaidDiagramGenerator
+        .generate_diagram(graph, &DiagramOptions::default())
+        .unwrap();
+
+    let load_style = diagram
+        .lines()
+        .find(|line| line.starts_with(&format!("style {}", load_id)))
+        .unwrap();
+    assert!(load_style.ends_with(",stroke:#D50000,stroke-width:3px"));
+    assert!(diagram.contains("[\"load\"]:::failed"));
+    assert!(diagram.contains(">\"retrying\"]:::failed"));
+    assert!(diagram.contains("-->|\"file not found\"|"));
+
+    //The call into "load", the error back out and the arrows into both failed nodes are red
+    let links: Vec<&str> = diagram
+        .lines()
+        .filter(|line| {
+            ["==>", "...-o", "--->", "-->|", ".-x", "-. async .->"]
+                .iter()
+                .any(|arrow| line.contains(arrow))
+        })
+        .collect();
+    let link_style = diagram
+        .lines()
+        .find(|line| line.starts_with("linkStyle "))
+        .unwrap();
+    let red: Vec<usize> = link_style["linkStyle ".len()..]
+        .split(' ')
+        .next()
+        .unwrap()
+        .split(',')
+        .map(|index| index.parse().unwrap())
+        .collect();
+    let mut red: Vec<&str> = red
+        .iter()
+        .map(|index| {
+            let link = links[*index];
+            ["...-o", "-->|", "==> END", "==>"]
+                .into_iter()
+                .find(|arrow| link.contains(arrow))
+                .unwrap()
+        })
+        .collect();
+    red.sort();
+    assert_eq!(red, vec!["-->|", "...-o", "==>", "==> END"]);
+}
+
+#[test]
+fn mermaid_draws_branches_and_loops() {
+    use VisFlowLogEntryLogType::{Branch, End, LoopEnd, LoopIter, LoopStart, Start};
+    let graph = graph(&[
+        (Start, "main", None),
+        (Branch, "main", Some("cache miss")),
+        (LoopStart, "main", Some("pages")),
+        (LoopIter, "main", Some("1")),
+        (Start, "fetch", None),
+        (End, "fetch", None),
+        (LoopIter, "main", None),
+        (LoopEnd, "main", None),
+        (End, "main", None),
+    ]);
+    let diagram = MermaidDiagramGenerator
+        .generate_diagram(graph, &DiagramOptions::default())
+        .unwrap();
+
+    assert!(diagram.contains("{\"cache miss\"}"));
+    assert!(diagram.contains("{{\"pages ×2\"}}"));
+    assert!(diagram.contains("[\"loop: pages\"]"));
+    assert!(diagram.contains("((\"iteration 1\"))"));
+    assert!(diagram.contains("((\"iteration\"))"));
+    //The loop is entered like a call but returns nothing
+    assert_eq!(diagram.matches("...-o").count(), 2);
+    assert_eq!(diagram.matches("--->").count(), 0);
+}
+
+#[test]
+fn mermaid_labels_durations_and_colors_blocks_by_heat() {
+    use crate::services::diagram_generator::api::models::block::DGBlockDuration;
+    use VisFlowLogEntryLogType::{End, Start};
+    let mut graph = graph(&[
+        (Start, "main", None),
+        (Start, "load", None),
+        (End, "load", None),
+        (Start, "parse", None),
+        (End, "parse", None),
+        (End, "main", None),
+    ]);
+    let mut id_of = HashMap::new();
+    for (id, block) in graph.iter_mut() {
+        id_of.insert(block.name.clone(), id.clone());
+        block.duration = match block.name.as_str() {
+            "main" => Some(DGBlockDuration {
+                inclusive_us: 12_300,
+                exclusive_us: 2_100,
+            }),
+            "load" => Some(DGBlockDuration {
+                inclusive_us: 10_200,
+                exclusive_us: 10_200,
+            }),
+            _ => None,
+        };
+    }
+    let diagram = MermaidDiagramGenerator
+        .generate_diagram(graph, &DiagramOptions::default())
+        .unwrap();
+
+    assert!(diagram.contains("[\"main · 12.3 ms (self 2.1 ms)\"]"));
+    assert!(diagram.contains("[\"load · 10.2 ms\"]"));
+    let style = |name: &str| {
+        diagram
+            .lines()
+            .find(|line| line.starts_with(&format!("style {}", id_of[name])))
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(
+        style("load"),
+        format!("style {} fill:#E53935", id_of["load"])
+    );
+    assert_eq!(
+        style("parse"),
+        format!("style {} fill:#EEEEEE", id_of["parse"])
+    );
+}
+
+#[test]
+fn folded_stacks_count_entries_without_times() {
+    use crate::services::diagram_generator::internal::application::folded_stack_diagram_generator::FoldedStackDiagramGenerator;
+    use VisFlowLogEntryLogType::{End, Log, LoopEnd, LoopStart, Start, Store};
+    let graph = graph(&[
+        (Start, "main", None),
+        (Start, "load;file", None),
+        (Log, "load;file", Some("reading")),
+        (End, "load;file", None),
+        (Store, "main", Some("bytes")),
+        (LoopStart, "main", Some("retry")),
+        (Start, "load;file", None),
+        (End, "load;file", None),
+        (LoopEnd, "main", None),
+        (End, "main", None),
+    ]);
+    let folded = FoldedStackDiagramGenerator
+        .generate_diagram(graph, &DiagramOptions::default())
+        .unwrap();
+    assert_eq!(
+        folded,
+        "main 1\nmain;load:file 2\nmain;loop: retry 1\nmain;loop: retry;load:file 1\n"
+    );
+}
+
+#[test]
+fn flame_graph_is_weighted_by_exclusive_time() {
+    use crate::services::diagram_generator::api::models::block::DGBlockDuration;
+    use crate::services::diagram_generator::internal::application::flame_graph_diagram_generator::FlameGraphDiagramGenerator;
+    use crate::services::diagram_generator::internal::common::folded_stack::{
+        FoldedStacks, Weight,
+    };
+    use VisFlowLogEntryLogType::{End, Start};
+    let mut graph = graph(&[
+        (Start, "main", None),
+        (Start, "<load>", None),
+        (End, "<load>", None),
+        (Start, "idle", None),
+        (End, "idle", None),
+        (End, "main", None),
+    ]);
+    for block in graph.values_mut() {
+        let (inclusive_us, exclusive_us) = match block.name.as_str() {
+            "main" => (4_000, 1_000),
+            "<load>" => (3_000, 3_000),
+            _ => (0, 0),
+        };
+        block.duration = Some(DGBlockDuration {
+            inclusive_us,
+            exclusive_us,
+        });
+    }
+    let folded = FoldedStacks::new(&graph).unwrap();
+    assert_eq!(folded.weight, Weight::Microseconds);
+    assert_eq!(
+        folded.to_folded(),
+        "main 1000\nmain;<load> 3000\n",
+        "stacks without time are left out"
+    );
+
+    let svg = FlameGraphDiagramGenerator
+        .generate_diagram(graph, &DiagramOptions::default())
+        .unwrap();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains("<title>main (4.0 ms, 100.00%)</title>"));
+    assert!(svg.contains("<title>&lt;load&gt; (3.0 ms, 75.00%)</title>"));
+    assert_eq!(svg.matches("<g>").count(), 2);
+}
+
+#[test]
+fn dot_has_a_cluster_per_block_and_mermaid_shapes() {
+    use crate::services::diagram_generator::internal::application::dot_diagram_generator::DotDiagramGenerator;
+    use VisFlowLogEntryLogType::{Branch, End, LoopEnd, LoopIter, LoopStart, Start, Throw};
+    let graph = graph(&[
+        (Start, "main", None),
+        (Branch, "main", Some("say \"hi\"")),
+        (LoopStart, "main", Some("pages")),
+        (LoopIter, "main", None),
+        (LoopEnd, "main", None),
+        (Start, "load", None),
+        (Throw, "load", Some("gone")),
+        (End, "main", None),
+    ]);
+    let load_id = graph
+        .iter()
+        .find(|(_, block)| block.name == "load")
+        .map(|(id, _)| id.clone())
+        .unwrap();
+    let dot = DotDiagramGenerator
+        .generate_diagram(graph, &DiagramOptions::default())
+        .unwrap();
+
+    assert!(dot.starts_with("digraph VisFlow {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert_eq!(dot.matches("subgraph \"cluster_").count(), 3);
+    assert!(dot.contains("label=\"loop: pages\";"));
+    assert!(dot
+        .contains("shape=diamond, style=filled, fillcolor=\"#FFFFFF\", label=\"say \\\"hi\\\"\""));
+    assert!(dot.contains("shape=hexagon, style=filled, fillcolor=\"#FFFFFF\", label=\"pages ×1\""));
+    assert!(dot.contains("shape=circle, style=filled, fillcolor=\"#FFFFFF\", label=\"iteration\""));
+    //The thrown error goes back from the empty block's placeholder to the caller, in red
+    assert!(dot.contains(&format!("\"{}\" [shape=point, style=invis];", load_id)));
+    assert!(dot.contains(&format!("\"{}\" -> ", load_id)));
+    assert!(dot.contains("[label=\"gone\", color=\"#D50000\", penwidth=3];"));
+    //main ends right after the call, so the error leaves the trace
+    assert!(dot.contains("-> END [penwidth=2, color=\"#D50000\"];"));
+}
+
+fn sequence_trace() -> HashMap<String, DGBlock> {
+    use VisFlowLogEntryLogType::{
+        End, ExternalCall, ExternalCallStore, Log, LoopEnd, LoopIter, LoopStart, Start, Store,
+        Throw,
+    };
+    graph(&[
+        (Start, "main", None),
+        (Start, "load", None),
+        (Log, "load", Some("reading #1; fast")),
+        (ExternalCall, "load", Some("SELECT 1")),
+        (ExternalCallStore, "load", Some("1 row")),
+        (End, "load", None),
+        (Store, "main", Some("config")),
+        (LoopStart, "main", Some("retry")),
+        (LoopIter, "main", None),
+        (Start, "send", None),
+        (Throw, "send", Some("timeout")),
+        (LoopEnd, "main", None),
+        (End, "main", None),
+    ])
+}
+
+#[test]
+fn plantuml_walks_calls_in_flow_order() {
+    use crate::services::diagram_generator::internal::application::plantuml_diagram_generator::PlantUmlDiagramGenerator;
+    let diagram = PlantUmlDiagramGenerator
+        .generate_diagram(sequence_trace(), &DiagramOptions::default())
+        .unwrap();
+    assert_eq!(
+        diagram,
+        "@startuml
+participant \"main\" as p0
+participant \"load\" as p1
+participant \"send\" as p2
+participant \"External\" as external
+activate p0
+p0 -> p1 ++
+note over p1 : reading #1; fast
+p1 -> external : SELECT 1
+external --> p1 : 1 row
+p1 --> p0 -- : config
+loop retry ×1
+note over p0 : iteration
+p0 -> p2 ++
+p2 -[#D50000]-> p0 -- : timeout
+end
+deactivate p0
+@enduml
+"
+    );
+}
+
+#[test]
+fn mermaid_sequence_escapes_labels() {
+    use crate::services::diagram_generator::internal::application::mermaid_sequence_diagram_generator::MermaidSequenceDiagramGenerator;
+    let diagram = MermaidSequenceDiagramGenerator
+        .generate_diagram(sequence_trace(), &DiagramOptions::default())
+        .unwrap();
+    assert!(diagram.starts_with("sequenceDiagram\n\tparticipant p0 as main\n"));
+    assert!(diagram.contains("\tp0->>+p1: call\n"));
+    assert!(diagram.contains("\tNote over p1: reading #35;1#59; fast\n"));
+    assert!(diagram.contains("\tp1-->>-p0: config\n"));
+    assert!(diagram.contains("\tloop retry ×1\n"));
+    assert!(diagram.contains("\tp2--x-p0: timeout\n"));
+    assert!(diagram.ends_with("\tend\n\tdeactivate p0\n"));
+}
+
+#[test]
+fn layout_places_callees_beside_their_call_without_overlaps() {
+    use crate::services::diagram_generator::api::models::diagram_options::Direction;
+    use crate::services::diagram_generator::internal::common::layout::{
+        Layout, BLOCK_PADDING, BLOCK_TITLE_HEIGHT,
+    };
+    let graph = sequence_trace();
+    for direction in [Direction::Tb, Direction::Lr] {
+        let layout = Layout::new(&graph, direction).unwrap();
+        assert_eq!(layout.blocks.len(), graph.len());
+
+        let rects: Vec<_> = layout.blocks.values().map(|block| block.rect).collect();
+        for (i, a) in rects.iter().enumerate() {
+            assert!(a.right() <= layout.width && a.bottom() <= layout.height);
+            for b in &rects[i + 1..] {
+                let apart =
+                    a.right() <= b.x || b.right() <= a.x || a.bottom() <= b.y || b.bottom() <= a.y;
+                assert!(apart, "{:?} overlaps {:?}", a, b);
+            }
+        }
+        for (block_id, block) in &graph {
+            let caller = &layout.blocks[block_id];
+            for (flow, node) in block.flow.iter().zip(&caller.nodes) {
+                if let Some(called) = flow.flow_pointer_id.as_ref() {
+                    let called = &layout.blocks[called];
+                    let (beside, level) = match direction {
+                        Direction::Tb => (
+                            called.rect.x > caller.rect.right(),
+                            called.rect.y + BLOCK_TITLE_HEIGHT + BLOCK_PADDING >= node.y,
+                        ),
+                        Direction::Lr => (
+                            called.rect.y > caller.rect.bottom(),
+                            called.rect.x + BLOCK_PADDING >= node.x,
+                        ),
+                    };
+                    assert!(beside, "{:?}: callees go beside their caller", direction);
+                    assert!(
+                        level,
+                        "{:?}: a callee never starts before its call",
+                        direction
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn svg_is_drawn_and_rasterized_without_external_tools() {
+    use crate::services::diagram_generator::internal::application::png_rasterizer::rasterize;
+    use crate::services::diagram_generator::internal::application::svg_diagram_generator::SvgDiagramGenerator;
+    let svg = SvgDiagramGenerator
+        .generate_diagram(sequence_trace(), &DiagramOptions::default())
+        .unwrap();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>\n"));
+    assert!(svg.contains("<title>loop: retry</title>"));
+    assert!(svg.contains("<title>reading #1; fast</title>"));
+    assert!(svg.contains(">retry ×1</text>"));
+    assert!(
+        svg.contains("stroke=\"#D50000\" stroke-width=\"3\""),
+        "the failed block is outlined in red"
+    );
+    assert_eq!(
+        svg.matches("<circle").count(),
+        3,
+        "START, END and the call marker"
+    );
+
+    let png = rasterize(&svg).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    assert!(rasterize("not svg").is_err());
+}
+
+#[test]
+fn viewer_page_is_self_contained_with_whole_values_and_sequences() {
+    use crate::services::diagram_generator::internal::application::html_diagram_generator::HtmlDiagramGenerator;
+    use VisFlowLogEntryLogType::{End, Log, Start, Throw};
+    let long_value = format!("{}\nsecond <line> & \"more\"", "x".repeat(200));
+    let graph = graph(&[
+        (Start, "main", None),
+        (Start, "load", None),
+        (Log, "load", Some(&long_value)),
+        (Throw, "load", Some("file not found")),
+        (End, "main", None),
+    ]);
+    let load_id = graph
+        .iter()
+        .find(|(_, block)| block.name == "load")
+        .map(|(id, _)| id.clone())
+        .unwrap();
+    let page = HtmlDiagramGenerator
+        .generate_diagram(graph, &DiagramOptions::default())
+        .unwrap();
+
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(!page.contains("{{"), "every placeholder is filled");
+    for external in [" src=", "<link", "url(http", "@import"] {
+        assert!(!page.contains(external), "{} loads from outside", external);
+    }
+    assert!(page.contains("<title>VisFlow · main</title>"));
+    assert!(page.contains(&format!(
+        "<g class=\"block\" data-block=\"{}\" data-caller=\"START\">",
+        load_id
+    )));
+    assert!(page.contains(&format!(
+        "<g class=\"call\" data-from=\"START\" data-to=\"{}\">",
+        load_id
+    )));
+    // The node is shortened, the attribute has the whole value with its line break
+    let value = format!(
+        "data-value=\"{}&#10;second &lt;line&gt; &amp; &quot;more&quot;\"",
+        "x".repeat(200)
+    );
+    assert!(page.contains(&format!("data-type=\"Log\" data-sequence=\"2\" {}", value)));
+    assert!(
+        page.contains("data-type=\"CallError\" data-sequence=\"1\" data-value=\"file not found\""),
+        "a call has the sequence of the block's START"
+    );
+    assert!(page.contains("function zoom("));
+}
+
+#[test]
+fn viewer_page_keeps_placeholders_in_block_names() {
+    use crate::services::diagram_generator::internal::application::html_diagram_generator::HtmlDiagramGenerator;
+    use VisFlowLogEntryLogType::{End, Start};
+    let graph = graph(&[
+        (Start, "{{svg}}", None),
+        (Start, "{{script}}", None),
+        (End, "{{script}}", None),
+        (End, "{{svg}}", None),
+    ]);
+    let page = HtmlDiagramGenerator
+        .generate_diagram(graph, &DiagramOptions::default())
+        .unwrap();
+
+    assert!(page.contains("<title>VisFlow · {{svg}}</title>"));
+    assert_eq!(page.matches("<svg").count(), 1);
+    assert_eq!(page.matches("function zoom(").count(), 1);
+}
+
+fn deep_trace() -> HashMap<String, DGBlock> {
+    use VisFlowLogEntryLogType::{End, Log, Start, Throw};
+    graph(&[
+        (Start, "main", None),
+        (Log, "main", Some("begin")),
+        (Start, "compound", None),
+        (Log, "compound", Some("year 1")),
+        (Start, "compound", None),
+        (Log, "compound", Some("year 2")),
+        (Start, "compound", None),
+        (Start, "round", None),
+        (Throw, "round", Some("overflow")),
+        (End, "compound", None),
+        (End, "compound", None),
+        (End, "compound", None),
+        (Start, "report", None),
+        (Start, "format", None),
+        (Start, "pad", None),
+        (End, "pad", None),
+        (End, "format", None),
+        (End, "report", None),
+        (End, "main", None),
+    ])
+}
+
+#[test]
+fn huge_traces_are_folded_cut_and_focused() {
+    use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+    use crate::services::diagram_generator::internal::common::filter::filter;
+    let names = |graph: &HashMap<String, DGBlock>| {
+        let mut names: Vec<String> = graph.values().map(|block| block.name.clone()).collect();
+        names.sort();
+        names
+    };
+    let by_name = |graph: &HashMap<String, DGBlock>, name: &str| {
+        graph
+            .values()
+            .filter(|block| block.name == name)
+            .map(|block| (block.runs, block.hidden, block.flow.len(), block.failed))
+            .collect::<Vec<_>>()
+    };
+
+    let folded = filter(
+        deep_trace(),
+        &DiagramOptions {
+            collapse_recursion: true,
+            ..DiagramOptions::default()
+        },
+    )
+    .unwrap();
+    // The outer call keeps its flows, the two inner ones and what they called are one failed block.
+    // Every compound ends right after the failed call, so the error reaches the outer one too.
+    let mut compound = by_name(&folded, "compound");
+    compound.sort();
+    assert_eq!(
+        compound,
+        vec![(None, None, 2, true), (Some(2), Some(1), 0, true)]
+    );
+    assert_eq!(
+        names(&folded),
+        ["compound", "compound", "format", "main", "pad", "report"]
+    );
+    let mermaid = MermaidDiagramGenerator
+        .generate_diagram(
+            deep_trace(),
+            &DiagramOptions {
+                collapse_recursion: true,
+                ..DiagramOptions::default()
+            },
+        )
+        .unwrap();
+    assert!(mermaid.contains("[\"compound ×2 +1 hidden\"]"));
+
+    let cut = filter(
+        deep_trace(),
+        &DiagramOptions {
+            max_depth: Some(1),
+            hide_logs: true,
+            ..DiagramOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(names(&cut), ["compound", "main", "report"]);
+    assert_eq!(by_name(&cut, "compound"), vec![(None, Some(3), 0, true)]);
+    assert_eq!(by_name(&cut, "report"), vec![(None, Some(2), 0, false)]);
+    assert_eq!(
+        by_name(&cut, "main"),
+        vec![(None, None, 2, false)],
+        "only the calls are left"
+    );
+
+    let report_id = deep_trace()
+        .into_iter()
+        .find(|(_, block)| block.name == "report")
+        .map(|(id, _)| id)
+        .unwrap();
+    for focus in ["report".to_string(), report_id] {
+        let focused = filter(
+            deep_trace(),
+            &DiagramOptions {
+                focus: Some(focus),
+                ..DiagramOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(names(&focused), ["format", "pad", "report"]);
+        assert_eq!(focused["START"].name, "report");
+        assert_eq!(focused["START"].caller, None);
+        let format = focused
+            .values()
+            .find(|block| block.name == "format")
+            .unwrap();
+        assert_eq!(format.caller.as_deref(), Some("START"));
+    }
+    let unknown = filter(
+        deep_trace(),
+        &DiagramOptions {
+            focus: Some("missing".to_string()),
+            ..DiagramOptions::default()
+        },
+    );
+    assert!(matches!(unknown, Err(DiagramError::InvalidOption(_))));
+}
+
+#[test]
+fn diagrams_are_the_same_for_the_same_trace() {
+    use crate::services::diagram_generator::api::services::{new, DiagramType};
+    use clap::ValueEnum;
+    let seeded = DiagramOptions {
+        seed: Some(7),
+        ..DiagramOptions::default()
+    };
+    for diagram_type in DiagramType::value_variants() {
+        let generator = new(*diagram_type);
+        let first = generator
+            .generate_diagram(sequence_trace(), &DiagramOptions::default())
+            .unwrap();
+        let second = generator
+            .generate_diagram(sequence_trace(), &DiagramOptions::default())
+            .unwrap();
+        assert!(
+            first == second,
+            "{:?} differs between runs",
+            diagram_type.to_possible_value()
+        );
+        assert_eq!(
+            generator
+                .generate_diagram(sequence_trace(), &seeded)
+                .unwrap(),
+            generator
+                .generate_diagram(sequence_trace(), &seeded)
+                .unwrap()
+        );
+    }
+
+    let mermaid = |options: &DiagramOptions| {
+        MermaidDiagramGenerator
+            .generate_diagram(sequence_trace(), options)
+            .unwrap()
+    };
+    let plain = mermaid(&DiagramOptions::default());
+    assert!(plain.contains("\tsubgraph b1[\"load\"]\n"));
+    assert!(plain.contains("\t\tf2([\"reading #35;1#59; fast\"])\n"));
+    assert!(plain.contains("f3 ..-x external_f3([\"External Call\"])"));
+    assert_ne!(plain, mermaid(&seeded), "a seed changes the colors");
+    assert_eq!(
+        plain.replace(|c: char| c.is_ascii_hexdigit(), ""),
+        mermaid(&seeded).replace(|c: char| c.is_ascii_hexdigit(), ""),
+        "and nothing else"
+    );
+}
+
+#[test]
+fn themes_and_overrides_reach_every_generator() {
+    use crate::services::diagram_generator::api::models::block::DGBlockFlowType;
+    use crate::services::diagram_generator::api::models::diagram_error::DiagramError;
+    use crate::services::diagram_generator::api::models::diagram_options::{
+        Direction, Shape, Theme,
+    };
+    use crate::services::diagram_generator::api::services::{new, DiagramType};
+    use clap::ValueEnum;
+    let options = DiagramOptions {
+        theme: Theme::Dark,
+        direction: Direction::Lr,
+        block_colors: DiagramOptions::parse_block_colors("load:#123456, retry:#abc").unwrap(),
+        shapes: DiagramOptions::parse_shapes("CallStore:hexagon,Log:rounded").unwrap(),
+        ..DiagramOptions::default()
+    };
+    assert_eq!(options.shapes[&DGBlockFlowType::Log], Shape::Rounded);
+    for diagram_type in DiagramType::value_variants() {
+        let light = new(*diagram_type)
+            .generate_diagram(sequence_trace(), &DiagramOptions::default())
+            .unwrap();
+        let themed = new(*diagram_type)
+            .generate_diagram(sequence_trace(), &options)
+            .unwrap();
+        // Folded stacks are plain text without any colors
+        if *diagram_type != DiagramType::Folded {
+            assert!(
+                light != themed,
+                "{:?} ignores the options",
+                diagram_type.to_possible_value()
+            );
+        }
+    }
+
+    let generate = |diagram_type| new(diagram_type).generate_diagram(sequence_trace(), &options);
+    let mermaid = generate(DiagramType::Mermaid).unwrap();
+    assert!(mermaid.starts_with("%%{init: {\"theme\": \"base\""));
+    assert!(mermaid.contains("flowchart LR\n"));
+    assert!(mermaid.contains("fill:#123456"));
+    assert!(
+        mermaid.contains("{{\"config\"}}"),
+        "stored calls are hexagons"
+    );
+    let dot = generate(DiagramType::Dot).unwrap();
+    assert!(dot.contains("rankdir=LR"));
+    assert!(dot.contains("bgcolor=\"#1E1E1E\""));
+    assert!(dot.contains("shape=hexagon, style=filled, fillcolor=\"#2D2D2D\", label=\"config\""));
+    let svg = generate(DiagramType::Svg).unwrap();
+    assert!(svg.contains("fill=\"#1E1E1E\""));
+    assert!(svg.contains("fill=\"#abc\""));
+    let plantuml = generate(DiagramType::Plantuml).unwrap();
+    assert!(plantuml.contains("skinparam backgroundColor #1E1E1E\n"));
+    assert!(plantuml.contains("participant \"load\" as p1 #123456\n"));
+    let sequence = generate(DiagramType::MermaidSequence).unwrap();
+    assert!(sequence.contains("\tbox rgb(18, 52, 86)\n\tparticipant p1 as load\n\tend\n"));
+    let flame_graph = generate(DiagramType::Flamegraph).unwrap();
+    assert!(flame_graph.contains("fill=\"#123456\""));
+
+    for (colors, shapes) in [
+        ("load:red", ""),
+        ("load", ""),
+        ("", "Call:blob"),
+        ("", "Jump:circle"),
+    ] {
+        let error = DiagramOptions::parse_block_colors(colors)
+            .and_then(|_| DiagramOptions::parse_shapes(shapes))
+            .unwrap_err();
+        assert!(matches!(error, DiagramError::InvalidOption(_)));
+    }
+}
+
+///Text that is markup in at least one diagram syntax, or not text at all
+fn hostile_text() -> impl proptest::strategy::Strategy<Value = String> {
+    use proptest::prelude::*;
+    let pieces = prop::sample::select(vec![
+        "\"",
+        "'",
+        "[",
+        "]",
+        "{",
+        "}",
+        "(",
+        ")",
+        "|",
+        "<",
+        ">",
+        "&",
+        "#",
+        ";",
+        "%",
+        "`",
+        "\\",
+        ":",
+        "\n",
+        "\r",
+        "\t",
+        "\u{0}",
+        "\u{202E}",
+        " ",
+        "end",
+        "subgraph",
+        "-->",
+        "==>",
+        "@enduml",
+        "**",
+        "--",
+        "//",
+        "~",
+        "<img:x.png>",
+        "[[http://x]]",
+        "<br>",
+        "#quot;",
+        "&lt;",
+        "%%{",
+        "×",
+        "a",
+    ]);
+    prop_oneof![
+        any::<String>(),
+        prop::collection::vec(pieces, 0..8).prop_map(|pieces| pieces.concat()),
+    ]
+}
+
+///Syntax of a line without the text inside quotes, or after the `:` of a message, note or participant
+fn skeleton(line: &str, backslash_escapes: bool) -> String {
+    let mut skeleton = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quoted && backslash_escapes => {
+                chars.next();
+            }
+            '"' => {
+                quoted = !quoted;
+                skeleton.push(c);
+            }
+            _ if quoted => {}
+            c => skeleton.push(c),
+        }
+    }
+    assert!(!quoted, "unterminated string in {:?}", line);
+    let trimmed = skeleton.trim_start();
+    if let Some(rest) = trimmed.strip_prefix("loop ") {
+        return format!("loop {}", rest.is_empty());
+    }
+    match skeleton.split_once(" as ") {
+        Some((participant, _)) if trimmed.starts_with("participant") => participant.to_string(),
+        _ => skeleton
+            .split_once(':')
+            .map_or(skeleton.clone(), |(syntax, _)| syntax.to_string()),
+    }
+}
+
+///Text left after taking out `#code;` and `&#code;` entities
+fn without_entities(text: &str) -> String {
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('#') {
+        let code = &rest[start + 1..];
+        let digits = code.chars().take_while(char::is_ascii_digit).count();
+        if digits > 0 && code[digits..].starts_with(';') {
+            plain += rest[..start].trim_end_matches('&');
+            rest = &code[digits + 1..];
+        } else {
+            plain += &rest[..=start];
+            rest = code;
+        }
+    }
+    plain + rest
+}
+
+proptest::proptest! {
+    #![proptest_config(proptest::test_runner::Config::with_cases(64))]
+
+    #[test]
+    fn any_log_content_gives_valid_diagrams(
+        names in proptest::collection::vec(hostile_text(), 3),
+        entries in proptest::collection::vec(
+            (0..12usize, 0..3usize, proptest::option::of(hostile_text())),
+            0..24,
+        ),
+    ) {
+        check_any_log_content(&names, &entries)?;
+    }
+}
+
+///Every diagram of a trace with hostile names and values has the syntax of the same trace with plain ones
+fn check_any_log_content(
+    names: &[String],
+    entries: &[(usize, usize, Option<String>)],
+) -> Result<(), proptest::test_runner::TestCaseError> {
+    use crate::services::diagram_generator::api::services::{new, DiagramType};
+    use crate::services::graph_generator::api::services::graph_generator::new as graph_generator;
+    use clap::ValueEnum;
+    use VisFlowLogEntryLogType::*;
+    let types = [
+        Log,
+        Store,
+        ExternalCall,
+        ExternalCallStore,
+        Start,
+        End,
+        Error,
+        Throw,
+        Branch,
+        LoopStart,
+        LoopIter,
+        LoopEnd,
+    ];
+    if names[0] == names[1] || names[1] == names[2] || names[0] == names[2] {
+        return Ok(());
+    }
+    let plain_names = ["n0", "n1", "n2"];
+    // The same trace twice, once with hostile text and once with plain text
+    let trace = |names: &[&str], plain: bool| {
+        let first = (4, 0, None);
+        let entries = std::iter::once(&first)
+            .chain(entries)
+            .enumerate()
+            .map(|(index, (log_type, block, value))| VisFlowLogEntry {
+                block_name: names[*block].to_string(),
+                log_type: types[*log_type],
+                log_value: value.as_ref().map(|value| {
+                    if plain {
+                        "v".to_string()
+                    } else {
+                        value.clone()
+                    }
+                }),
+                sequence: index as u32,
+                span_id: None,
+                parent_span_id: None,
+                timestamp: None,
+            })
+            .collect();
+        graph_generator()
+            .generate_lenient_graph(entries)
+            .map(|graph| {
+                graph
+                    .into_iter()
+                    .map(|(key, block)| (key, DGBlock::from(block)))
+                    .collect::<HashMap<String, DGBlock>>()
+            })
+    };
+    let hostile_names: Vec<&str> = names.iter().map(String::as_str).collect();
+    if trace(&hostile_names, false).is_err() {
+        return Ok(());
+    }
+    let hostile = || trace(&hostile_names, false).unwrap();
+    let plain = || trace(&plain_names, true).unwrap();
+    // Colors hashed from the names would differ between the two
+    let options = |names: &[&str]| DiagramOptions {
+        block_colors: names
+            .iter()
+            .map(|name| (name.to_string(), "#ABCDEF".to_string()))
+            .collect(),
+        ..DiagramOptions::default()
+    };
+
+    for diagram_type in DiagramType::value_variants() {
+        let generator = new(*diagram_type);
+        let name = diagram_type
+            .to_possible_value()
+            .unwrap()
+            .get_name()
+            .to_string();
+        // Shortened and filtered, hostile names may repeat and look recursive
+        let shortened = DiagramOptions {
+            max_label: 12,
+            max_depth: Some(1),
+            collapse_recursion: true,
+            hide_logs: true,
+            ..options(&hostile_names)
+        };
+        let diagram = generator
+            .generate_diagram(hostile(), &options(&hostile_names))
+            .unwrap();
+        generator.generate_diagram(hostile(), &shortened).unwrap();
+        let expected = generator
+            .generate_diagram(plain(), &options(&plain_names))
+            .unwrap();
+        match diagram_type {
+            DiagramType::Svg | DiagramType::Flamegraph => {
+                let parsed =
+                    resvg::usvg::Tree::from_str(&diagram, &resvg::usvg::Options::default());
+                proptest::prop_assert!(parsed.is_ok(), "{:?}", parsed.err());
+            }
+            DiagramType::Html => {
+                // The page's own script is the only one, and the diagram in it is valid SVG
+                proptest::prop_assert_eq!(diagram.matches("<script").count(), 1);
+                let start = diagram.find("<svg").unwrap();
+                let end = diagram.rfind("</svg>").unwrap() + "</svg>".len();
+                let parsed = resvg::usvg::Tree::from_str(
+                    &diagram[start..end],
+                    &resvg::usvg::Options::default(),
+                );
+                proptest::prop_assert!(parsed.is_ok(), "{:?}", parsed.err());
+            }
+            DiagramType::Folded => {
+                // Stacks with the same names are merged, so only the total weight is the same
+                let total = |folded: &str| -> u64 {
+                    folded
+                        .lines()
+                        .filter_map(|line| line.rsplit_once(' ')?.1.parse::<u64>().ok())
+                        .sum()
+                };
+                proptest::prop_assert_eq!(total(&diagram), total(&expected));
+                for line in diagram.lines() {
+                    let weight = line
+                        .rsplit_once(' ')
+                        .map(|(_, weight)| weight.parse::<u64>());
+                    proptest::prop_assert!(matches!(weight, Some(Ok(_))), "{:?}", line);
+                }
+            }
+            _ => {
+                let backslash_escapes = *diagram_type == DiagramType::Dot;
+                let lines: Vec<String> = diagram
+                    .lines()
+                    .map(|line| skeleton(line, backslash_escapes))
+                    .collect();
+                let expected: Vec<String> = expected
+                    .lines()
+                    .map(|line| skeleton(line, backslash_escapes))
+                    .collect();
+                proptest::prop_assert_eq!(lines, expected, "{}", name);
+                // Sequence diagrams don't quote messages, they run to the end of the line
+                let forbidden: &[char] = match diagram_type {
+                    DiagramType::Plantuml => &['<', '>', '[', ']', '~', '"', '\\'],
+                    DiagramType::MermaidSequence => &['<', '>', ';', '%', '`', '"', '#'],
+                    _ => &[],
+                };
+                for line in diagram.lines() {
+                    let line = line.trim_start();
+                    let text = match line.strip_prefix("loop ") {
+                        Some(label) => Some(label),
+                        None if line.starts_with("participant \"") => None,
+                        None if line.starts_with("participant") => {
+                            line.split_once(" as ").map(|(_, name)| name)
+                        }
+                        None => line.split_once(':').map(|(_, text)| text),
+                    };
+                    let Some(text) = text else {
+                        continue;
+                    };
+                    let text = without_entities(&text.replace("<br>", "").replace("\\n", ""));
+                    proptest::prop_assert!(
+                        !text.contains(forbidden),
+                        "{} leaks markup: {:?}",
+                        name,
+                        line
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn labels_are_shortened_with_the_full_text_in_a_tooltip() {
+    use crate::services::diagram_generator::internal::application::dot_diagram_generator::DotDiagramGenerator;
+    use crate::services::diagram_generator::internal::common::escape::{plantuml, sanitize};
+    use VisFlowLogEntryLogType::{End, Log, Start};
+    let long = "x\"y] --> z; a very long log message";
+    let graph = || {
+        graph(&[
+            (Start, "main", None),
+            (Log, "main", Some(long)),
+            (End, "main", None),
+        ])
+    };
+    let options = DiagramOptions {
+        max_label: 10,
+        ..DiagramOptions::default()
+    };
+    let mermaid = MermaidDiagramGenerator
+        .generate_diagram(graph(), &options)
+        .unwrap();
+    assert!(mermaid.contains("\t\tf1([\"x#34;y] --#62; …\"])\n"));
+    assert!(
+        mermaid.contains("\t\tclick f1 vflLabel \"x&quot;y] --&gt; z; a very long log message\"\n")
+    );
+    let dot = DotDiagramGenerator
+        .generate_diagram(graph(), &options)
+        .unwrap();
+    assert!(
+        dot.contains("label=\"x\\\"y] --> …\", tooltip=\"x\\\"y] --> z; a very long log message\"")
+    );
+    let whole = MermaidDiagramGenerator
+        .generate_diagram(graph(), &DiagramOptions::default())
+        .unwrap();
+    assert!(!whole.contains("click"));
+
+    assert_eq!(sanitize("a\r\nb\rc\u{0}\u{202E}d"), "a\nb\ncd");
+    assert_eq!(
+        plantuml("**bold** <b>x</b> a*b"),
+        "&#42;*bold&#42;* &#60;b&#62;x&#60;/b&#62; a*b"
+    );
+}
@@ -1,11 +1,33 @@
-use crate::services::diagram_generator::api::services::DiagramGenerator;
+use crate::services::diagram_generator::api::services::{DiagramGenerator, DiagramType};
 use std::sync::Arc;
 
-//JointJS, Mermaid, GoJS
+//JointJS, Mermaid, PlantUML, Graphviz, GoJS, flame graphs, SVG and PNG drawn by the server, and the HTML viewer page
 pub mod api;
 mod internal;
 
 #[derive(Clone)]
 pub struct AvailableServices {
     pub(crate) mermaid: Arc<dyn DiagramGenerator>,
+    pub(crate) mermaid_sequence: Arc<dyn DiagramGenerator>,
+    pub(crate) plantuml: Arc<dyn DiagramGenerator>,
+    pub(crate) dot: Arc<dyn DiagramGenerator>,
+    pub(crate) folded: Arc<dyn DiagramGenerator>,
+    pub(crate) flamegraph: Arc<dyn DiagramGenerator>,
+    pub(crate) svg: Arc<dyn DiagramGenerator>,
+    pub(crate) html: Arc<dyn DiagramGenerator>,
+}
+
+impl AvailableServices {
+    pub fn get(&self, diagram_type: DiagramType) -> Arc<dyn DiagramGenerator> {
+        match diagram_type {
+            DiagramType::Mermaid => self.mermaid.clone(),
+            DiagramType::MermaidSequence => self.mermaid_sequence.clone(),
+            DiagramType::Plantuml => self.plantuml.clone(),
+            DiagramType::Dot => self.dot.clone(),
+            DiagramType::Folded => self.folded.clone(),
+            DiagramType::Flamegraph => self.flamegraph.clone(),
+            DiagramType::Svg => self.svg.clone(),
+            DiagramType::Html => self.html.clone(),
+        }
+    }
 }
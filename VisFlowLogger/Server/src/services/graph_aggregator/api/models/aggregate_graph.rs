@@ -0,0 +1,75 @@
+use crate::services::graph_generator::api::models::vis_flow::GGBlockKind;
+use serde::Serialize;
+
+/// Call graph merged from the graphs of many operations.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct AggregateGraph {
+    /// IDs of the operations merged into it, in the order they were given.
+    pub operations: Vec<String>,
+    /// Every block, by name, with how often and how long it ran.
+    pub blocks: Vec<AggregateBlock>,
+    /// Caller → callee edges between block names, weighted by the number of calls.
+    pub calls: Vec<AggregateCall>,
+    /// Call tree merged by path from the root, one node per distinct path, in call order.
+    pub paths: Vec<AggregatePath>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AggregateBlock {
+    pub name: String,
+    pub kind: GGBlockKind,
+    /// Times it ran in all operations.
+    pub runs: usize,
+    /// Operations it ran in.
+    pub operations: usize,
+    /// Distinct call paths that reached it.
+    pub paths: usize,
+    /// Runs that threw or logged an error.
+    pub failed: usize,
+    /// Set when some of its runs had times.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<AggregateDuration>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AggregateCall {
+    /// Frame names as in `AggregatePath::path`.
+    pub caller: String,
+    pub callee: String,
+    pub calls: usize,
+    /// Operations with at least one of these calls.
+    pub operations: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AggregatePath {
+    /// Frame names from the root to the block. Loops are named `loop: label`.
+    pub path: Vec<String>,
+    /// Name of the block at the end of the path.
+    pub name: String,
+    pub kind: GGBlockKind,
+    /// The block ran in another span than its caller in at least one run.
+    pub asynchronous: bool,
+    pub runs: usize,
+    pub operations: usize,
+    pub failed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<AggregateDuration>,
+}
+
+/// Durations of the runs that had times, in microseconds.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct AggregateDuration {
+    pub samples: usize,
+    /// From START to END, including the blocks it called.
+    pub inclusive: DurationStats,
+    /// Time spent in the block itself.
+    pub exclusive: DurationStats,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct DurationStats {
+    pub min_us: u64,
+    pub avg_us: u64,
+    pub max_us: u64,
+}
@@ -0,0 +1 @@
+pub mod aggregate_graph;
\ No newline at end of file
@@ -0,0 +1,24 @@
+use crate::services::graph_aggregator::api::models::aggregate_graph::AggregateGraph;
+use crate::services::graph_aggregator::internal::application::graph_aggregator_impl::GraphAggregatorImpl;
+use crate::services::graph_generator::api::models::vis_flow::GGBlock;
+use std::collections::HashMap;
+
+pub trait GraphAggregator: Send + Sync {
+    /**
+    Merges the graphs of many operations, given with their IDs. <br>
+    Blocks are matched by their path of block names from the root, so the same call from the same place
+    in two runs is one node. Graphs without a START block are skipped.
+    */
+    fn aggregate(&self, graphs: Vec<(String, HashMap<String, GGBlock>)>) -> AggregateGraph;
+
+    /**
+    Graph of the merged call tree, for the diagram generators. <br>
+    Every distinct path is a block with its number of runs and its average duration, and fails when any run failed.
+    Roots with different names are called by a START block named `operations`.
+    */
+    fn to_graph(&self, aggregate: &AggregateGraph) -> HashMap<String, GGBlock>;
+}
+
+pub fn new() -> impl GraphAggregator {
+    GraphAggregatorImpl {}
+}
@@ -0,0 +1 @@
+pub mod graph_aggregator;
\ No newline at end of file
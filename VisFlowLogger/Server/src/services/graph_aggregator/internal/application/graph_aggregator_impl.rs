@@ -0,0 +1,299 @@
+use crate::services::graph_aggregator::api::models::aggregate_graph::{
+    AggregateBlock, AggregateCall, AggregateDuration, AggregateGraph, AggregatePath, DurationStats,
+};
+use crate::services::graph_aggregator::api::services::graph_aggregator::GraphAggregator;
+use crate::services::graph_generator::api::models::vis_flow::{
+    GGBlock, GGBlockDuration, GGBlockFlow, GGBlockFlowType, GGBlockKind,
+};
+use std::collections::{HashMap, HashSet};
+
+pub struct GraphAggregatorImpl;
+
+impl GraphAggregator for GraphAggregatorImpl {
+    fn aggregate(&self, graphs: Vec<(String, HashMap<String, GGBlock>)>) -> AggregateGraph {
+        let mut merger = Merger::default();
+        for (operation, (operation_id, graph)) in graphs.iter().enumerate() {
+            let Some(root) = graph.get("START") else {
+                continue;
+            };
+            merger.operations.push(operation_id.clone());
+            let mut path = Vec::new();
+            merger.visit(graph, root, false, operation, &mut path);
+        }
+        merger.finish()
+    }
+
+    fn to_graph(&self, aggregate: &AggregateGraph) -> HashMap<String, GGBlock> {
+        let roots = aggregate
+            .paths
+            .iter()
+            .filter(|path| path.path.len() == 1)
+            .count();
+        // A single root is the START block itself, several are called by one
+        let mut ids: HashMap<&[String], String> = HashMap::new();
+        let mut graph = HashMap::new();
+        if roots > 1 {
+            graph.insert(
+                "START".to_string(),
+                block(
+                    "operations",
+                    GGBlockKind::Block,
+                    None,
+                    aggregate.operations.len(),
+                    false,
+                    None,
+                ),
+            );
+        }
+        for (index, path) in aggregate.paths.iter().enumerate() {
+            let block_id = if roots == 1 && path.path.len() == 1 {
+                "START".to_string()
+            } else {
+                format!("p{}", index)
+            };
+            let caller_id = match &path.path[..path.path.len() - 1] {
+                [] if roots == 1 => None,
+                [] => Some("START".to_string()),
+                parent => Some(ids[parent].clone()),
+            };
+            if let Some(caller_id) = &caller_id {
+                let flow_type = match path.kind {
+                    GGBlockKind::Loop => GGBlockFlowType::Loop,
+                    GGBlockKind::Block if path.asynchronous => GGBlockFlowType::AsyncCall,
+                    GGBlockKind::Block => GGBlockFlowType::Call,
+                };
+                let caller: &mut GGBlock = graph.get_mut(caller_id).unwrap();
+                caller.flow.push(GGBlockFlow {
+                    flow_pointer_id: Some(block_id.clone()),
+                    flow_id: format!("{}_call", block_id),
+                    //The loop's node counts its runs like the label of any other merged block
+                    value: (flow_type == GGBlockFlowType::Loop).then(|| path.runs.to_string()),
+                    flow_type,
+                    sequence: None,
+                    change: None,
+                });
+            }
+            graph.insert(
+                block_id.clone(),
+                block(
+                    &path.name,
+                    path.kind,
+                    caller_id,
+                    path.runs,
+                    path.failed > 0,
+                    path.duration,
+                ),
+            );
+            ids.insert(&path.path, block_id);
+        }
+        graph
+    }
+}
+
+fn block(
+    name: &str,
+    kind: GGBlockKind,
+    caller: Option<String>,
+    runs: usize,
+    failed: bool,
+    duration: Option<AggregateDuration>,
+) -> GGBlock {
+    GGBlock {
+        caller,
+        name: name.to_string(),
+        flow: vec![],
+        aborted: false,
+        failed,
+        kind,
+        span_id: None,
+        sequence: None,
+        started_at: None,
+        ended_at: None,
+        duration: duration.map(|duration| GGBlockDuration {
+            inclusive_us: duration.inclusive.avg_us,
+            exclusive_us: duration.exclusive.avg_us,
+        }),
+        runs: Some(runs),
+        change: None,
+    }
+}
+
+///Runs of one block name, one call or one path
+#[derive(Default)]
+struct Runs {
+    runs: usize,
+    operations: HashSet<usize>,
+    failed: usize,
+    durations: Vec<(u64, u64)>,
+}
+
+impl Runs {
+    fn add(&mut self, block: &GGBlock, operation: usize) {
+        self.runs += 1;
+        self.operations.insert(operation);
+        self.failed += block.failed as usize;
+        if let Some(duration) = block.duration {
+            self.durations
+                .push((duration.inclusive_us, duration.exclusive_us));
+        }
+    }
+
+    fn duration(&self) -> Option<AggregateDuration> {
+        let stats = |values: Vec<u64>| DurationStats {
+            min_us: values.iter().copied().min().unwrap_or_default(),
+            avg_us: values.iter().sum::<u64>() / values.len() as u64,
+            max_us: values.iter().copied().max().unwrap_or_default(),
+        };
+        if self.durations.is_empty() {
+            return None;
+        }
+        Some(AggregateDuration {
+            samples: self.durations.len(),
+            inclusive: stats(
+                self.durations
+                    .iter()
+                    .map(|(inclusive, _)| *inclusive)
+                    .collect(),
+            ),
+            exclusive: stats(
+                self.durations
+                    .iter()
+                    .map(|(_, exclusive)| *exclusive)
+                    .collect(),
+            ),
+        })
+    }
+}
+
+///Runs of a block name and the paths that reached it
+struct BlockRuns {
+    name: String,
+    kind: GGBlockKind,
+    runs: Runs,
+    paths: HashSet<Vec<String>>,
+}
+
+///Runs of one node of the merged call tree
+struct PathRuns {
+    path: Vec<String>,
+    name: String,
+    kind: GGBlockKind,
+    asynchronous: bool,
+    runs: Runs,
+}
+
+///Everything seen so far, in the order it was first seen
+#[derive(Default)]
+struct Merger {
+    operations: Vec<String>,
+    blocks: Vec<BlockRuns>,
+    block_index: HashMap<(String, GGBlockKind), usize>,
+    calls: Vec<((String, String), Runs)>,
+    call_index: HashMap<(String, String), usize>,
+    paths: Vec<PathRuns>,
+    path_index: HashMap<Vec<String>, usize>,
+}
+
+impl Merger {
+    fn visit(
+        &mut self,
+        graph: &HashMap<String, GGBlock>,
+        block: &GGBlock,
+        asynchronous: bool,
+        operation: usize,
+        path: &mut Vec<String>,
+    ) {
+        path.push(block.frame());
+        let index = *self.path_index.entry(path.clone()).or_insert_with(|| {
+            self.paths.push(PathRuns {
+                path: path.clone(),
+                name: block.name.clone(),
+                kind: block.kind,
+                asynchronous: false,
+                runs: Runs::default(),
+            });
+            self.paths.len() - 1
+        });
+        let path_runs = &mut self.paths[index];
+        path_runs.asynchronous |= asynchronous;
+        path_runs.runs.add(block, operation);
+
+        let key = (block.name.clone(), block.kind);
+        let index = *self.block_index.entry(key).or_insert_with(|| {
+            self.blocks.push(BlockRuns {
+                name: block.name.clone(),
+                kind: block.kind,
+                runs: Runs::default(),
+                paths: HashSet::new(),
+            });
+            self.blocks.len() - 1
+        });
+        let block_runs = &mut self.blocks[index];
+        block_runs.runs.add(block, operation);
+        block_runs.paths.insert(path.clone());
+
+        for flow in &block.flow {
+            let Some(called) = flow
+                .flow_pointer_id
+                .as_ref()
+                .and_then(|called_id| graph.get(called_id))
+            else {
+                continue;
+            };
+            let key = (block.frame(), called.frame());
+            let index = *self.call_index.entry(key.clone()).or_insert_with(|| {
+                self.calls.push((key, Runs::default()));
+                self.calls.len() - 1
+            });
+            let (_, runs) = &mut self.calls[index];
+            runs.runs += 1;
+            runs.operations.insert(operation);
+            let asynchronous = flow.flow_type == GGBlockFlowType::AsyncCall;
+            self.visit(graph, called, asynchronous, operation, path);
+        }
+        path.pop();
+    }
+
+    fn finish(self) -> AggregateGraph {
+        AggregateGraph {
+            operations: self.operations,
+            blocks: self
+                .blocks
+                .into_iter()
+                .map(|block| AggregateBlock {
+                    duration: block.runs.duration(),
+                    name: block.name,
+                    kind: block.kind,
+                    runs: block.runs.runs,
+                    operations: block.runs.operations.len(),
+                    paths: block.paths.len(),
+                    failed: block.runs.failed,
+                })
+                .collect(),
+            calls: self
+                .calls
+                .into_iter()
+                .map(|((caller, callee), runs)| AggregateCall {
+                    caller,
+                    callee,
+                    calls: runs.runs,
+                    operations: runs.operations.len(),
+                })
+                .collect(),
+            paths: self
+                .paths
+                .into_iter()
+                .map(|path| AggregatePath {
+                    duration: path.runs.duration(),
+                    path: path.path,
+                    name: path.name,
+                    kind: path.kind,
+                    asynchronous: path.asynchronous,
+                    runs: path.runs.runs,
+                    operations: path.runs.operations.len(),
+                    failed: path.runs.failed,
+                })
+                .collect(),
+        }
+    }
+}
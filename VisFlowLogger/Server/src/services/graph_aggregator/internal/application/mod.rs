@@ -0,0 +1 @@
+pub mod graph_aggregator_impl;
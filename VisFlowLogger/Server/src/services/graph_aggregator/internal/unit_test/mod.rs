@@ -0,0 +1,167 @@
+use crate::services::diagram_generator::api::models::block::DGBlock;
+use crate::services::diagram_generator::api::models::diagram_options::DiagramOptions;
+use crate::services::diagram_generator::api::services::DiagramType;
+use crate::services::graph_aggregator::api::models::aggregate_graph::{
+    AggregateCall, DurationStats,
+};
+use crate::services::graph_aggregator::api::services::graph_aggregator::GraphAggregator;
+use crate::services::graph_generator::api::models::vis_flow::{GGBlock, GGBlockFlowType};
+use crate::services::graph_generator::api::models::vis_flow_log_entry::VisFlowLogEntryLogType;
+use crate::services::graph_generator::test_fixture;
+use chrono::DateTime;
+use std::collections::HashMap;
+
+///Graph of a trace whose entries are written at the given millisecond
+fn graph(entries: &[(VisFlowLogEntryLogType, &str, i64)]) -> HashMap<String, GGBlock> {
+    let trace: Vec<_> = entries
+        .iter()
+        .map(|(log_type, block_name, _)| (*log_type, *block_name, None))
+        .collect();
+    let mut trace = test_fixture::entries(&trace);
+    for (entry, (_, _, millis)) in trace.iter_mut().zip(entries) {
+        entry.timestamp = DateTime::from_timestamp_millis(*millis);
+    }
+    test_fixture::graph_of(trace)
+}
+
+///`main` calls `load` and then `save`, or `load` twice, taking `load_ms` for each load
+fn checkout(twice: bool, load_ms: i64) -> HashMap<String, GGBlock> {
+    use VisFlowLogEntryLogType::{End, Start};
+    let second = if twice { "load" } else { "save" };
+    graph(&[
+        (Start, "main", 0),
+        (Start, "load", 0),
+        (End, "load", load_ms),
+        (Start, second, load_ms),
+        (End, second, load_ms + 10),
+        (End, "main", load_ms + 10),
+    ])
+}
+
+#[test]
+fn runs_are_merged_by_path_with_call_counts_and_durations() {
+    let aggregator = crate::services::graph_aggregator::api::services::graph_aggregator::new();
+    let aggregate = aggregator.aggregate(vec![
+        ("op1".to_string(), checkout(false, 10)),
+        ("op2".to_string(), checkout(true, 30)),
+        ("empty".to_string(), HashMap::new()),
+    ]);
+
+    assert_eq!(aggregate.operations, vec!["op1", "op2"]);
+    let paths: Vec<(String, usize, usize)> = aggregate
+        .paths
+        .iter()
+        .map(|path| (path.path.join(">"), path.runs, path.operations))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            ("main".to_string(), 2, 2),
+            ("main>load".to_string(), 3, 2),
+            ("main>save".to_string(), 1, 1),
+        ]
+    );
+    assert_eq!(
+        aggregate.calls,
+        vec![
+            AggregateCall {
+                caller: "main".to_string(),
+                callee: "load".to_string(),
+                calls: 3,
+                operations: 2,
+            },
+            AggregateCall {
+                caller: "main".to_string(),
+                callee: "save".to_string(),
+                calls: 1,
+                operations: 1,
+            },
+        ]
+    );
+    // The second load ran 10ms, the first ones 10ms and 30ms
+    let load = &aggregate.blocks[1];
+    assert_eq!((load.name.as_str(), load.runs, load.paths), ("load", 3, 1));
+    let duration = load.duration.unwrap();
+    assert_eq!(duration.samples, 3);
+    assert_eq!(
+        duration.inclusive,
+        DurationStats {
+            min_us: 10_000,
+            avg_us: 16_666,
+            max_us: 30_000,
+        }
+    );
+    let main = aggregate.paths[0].duration.unwrap();
+    assert_eq!(
+        (main.inclusive.min_us, main.inclusive.max_us),
+        (20_000, 40_000)
+    );
+    assert_eq!(main.exclusive.max_us, 0);
+}
+
+#[test]
+fn merged_tree_is_drawn_by_any_diagram_generator() {
+    let aggregator = crate::services::graph_aggregator::api::services::graph_aggregator::new();
+    let aggregate = aggregator.aggregate(vec![
+        ("op1".to_string(), checkout(false, 10)),
+        ("op2".to_string(), checkout(true, 30)),
+    ]);
+    let graph = aggregator.to_graph(&aggregate);
+    assert_eq!(graph.len(), 3);
+    assert_eq!(graph["START"].name, "main");
+    assert_eq!(graph["START"].runs, Some(2));
+    let diagram_graph = || -> HashMap<String, DGBlock> {
+        aggregator
+            .to_graph(&aggregate)
+            .into_iter()
+            .map(|(key, block)| (key, DGBlock::from(block)))
+            .collect()
+    };
+
+    let options = DiagramOptions::default();
+    let mermaid = crate::services::diagram_generator::api::services::new(DiagramType::Mermaid)
+        .generate_diagram(diagram_graph(), &options)
+        .unwrap();
+    assert!(mermaid.contains("load ×3"), "{}", mermaid);
+    assert!(mermaid.contains("save ×1"), "{}", mermaid);
+    for diagram_type in [
+        DiagramType::MermaidSequence,
+        DiagramType::Plantuml,
+        DiagramType::Dot,
+        DiagramType::Folded,
+        DiagramType::Flamegraph,
+        DiagramType::Svg,
+        DiagramType::Html,
+    ] {
+        crate::services::diagram_generator::api::services::new(diagram_type)
+            .generate_diagram(diagram_graph(), &options)
+            .unwrap();
+    }
+}
+
+#[test]
+fn different_roots_are_called_by_one_start() {
+    use VisFlowLogEntryLogType::{End, Start};
+    let aggregator = crate::services::graph_aggregator::api::services::graph_aggregator::new();
+    let aggregate = aggregator.aggregate(vec![
+        ("op1".to_string(), checkout(false, 10)),
+        (
+            "op2".to_string(),
+            graph(&[(Start, "retry", 0), (End, "retry", 5)]),
+        ),
+    ]);
+    let graph = aggregator.to_graph(&aggregate);
+    let root = &graph["START"];
+    assert_eq!((root.name.as_str(), root.runs), ("operations", Some(2)));
+    let called: Vec<&str> = root
+        .flow
+        .iter()
+        .filter(|flow| flow.flow_type == GGBlockFlowType::Call)
+        .map(|flow| graph[flow.flow_pointer_id.as_ref().unwrap()].name.as_str())
+        .collect();
+    assert_eq!(called, vec!["main", "retry"]);
+    assert!(graph
+        .values()
+        .filter(|block| block.name != "operations")
+        .all(|block| block.caller.is_some()));
+}
@@ -0,0 +1,10 @@
+use crate::services::graph_aggregator::api::services::graph_aggregator::GraphAggregator;
+use std::sync::Arc;
+
+pub mod api;
+mod internal;
+
+#[derive(Clone)]
+pub struct AvailableServices {
+    pub graph_aggregator: Arc<dyn GraphAggregator>,
+}
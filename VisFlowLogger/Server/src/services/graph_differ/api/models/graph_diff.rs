@@ -0,0 +1,43 @@
+use crate::services::graph_generator::api::models::vis_flow::GGBlockFlowType;
+use serde::Serialize;
+
+/// Differences between the graph of an older and a newer operation, in flow order.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct GraphDiff {
+    pub changes: Vec<GraphChange>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GraphChange {
+    pub change: ChangeKind,
+    /// Frames from the root to the call, or to the block of the flow. Loops are named `loop: label`,
+    /// and the Nth call of a frame by the same block is `frame#N`.
+    pub path: Vec<String>,
+    /// Type of the flow in the newer operation, or in the older one when it was removed.
+    pub flow_type: GGBlockFlowType,
+    /// Value of the flow in the older operation. For calls, the value stored from them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// Value of the flow in the newer operation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    /// Sequence numbers of the entries, to find them in the logs of each operation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_sequence: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_sequence: Option<u32>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// A call only the newer operation made, with everything below it.
+    AddedCall,
+    /// A call only the older operation made, with everything below it.
+    RemovedCall,
+    /// A log with another value, or only in one of the operations.
+    ChangedLog,
+    /// A stored value that differs, or is only in one of the operations.
+    ChangedStore,
+    /// Any other flow that differs, like an error, a branch, a loop's iterations or a call that threw.
+    ChangedFlow,
+}
@@ -0,0 +1 @@
+pub mod graph_diff;
\ No newline at end of file
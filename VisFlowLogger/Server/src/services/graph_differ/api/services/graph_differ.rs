@@ -0,0 +1,34 @@
+use crate::services::graph_differ::api::models::graph_diff::GraphDiff;
+use crate::services::graph_differ::internal::application::graph_differ_impl::GraphDifferImpl;
+use crate::services::graph_generator::api::models::vis_flow::GGBlock;
+use std::collections::HashMap;
+
+pub trait GraphDiffer: Send + Sync {
+    /**
+    Compares the graph of an older operation with the graph of a newer one. <br>
+    The START blocks are compared with each other, and the calls of two compared blocks are aligned by
+    the name of the called block and how many calls of that name came before, so the same call from the same path
+    in both runs is compared. Logs and values are aligned in flow order.
+    */
+    fn diff(
+        &self,
+        before: &HashMap<String, GGBlock>,
+        after: &HashMap<String, GGBlock>,
+    ) -> GraphDiff;
+
+    /**
+    Graph of both operations for the diagram generators. <br>
+    It is the newer graph, with what only the older one had put back in place,
+    and every added, removed or changed block and flow marked with its `change`.
+    Blocks and flows taken from the older graph have their keys prefixed with `before_`.
+    */
+    fn to_graph(
+        &self,
+        before: &HashMap<String, GGBlock>,
+        after: &HashMap<String, GGBlock>,
+    ) -> HashMap<String, GGBlock>;
+}
+
+pub fn new() -> impl GraphDiffer {
+    GraphDifferImpl {}
+}
@@ -0,0 +1 @@
+pub mod graph_differ;
\ No newline at end of file
@@ -0,0 +1,479 @@
+use crate::services::graph_differ::api::models::graph_diff::{ChangeKind, GraphChange, GraphDiff};
+use crate::services::graph_differ::api::services::graph_differ::GraphDiffer;
+use crate::services::graph_generator::api::models::vis_flow::{
+    GGBlock, GGBlockChange, GGBlockFlow, GGBlockFlowType, GGBlockKind,
+};
+use std::collections::HashMap;
+
+///Prefix of the keys of blocks and flows taken from the older graph
+const BEFORE: &str = "before_";
+///Largest table `align` builds, 16 MB. Blocks that differ in more flows are aligned by position
+const MAX_ALIGN_CELLS: usize = 4 * 1024 * 1024;
+
+pub struct GraphDifferImpl;
+
+impl GraphDiffer for GraphDifferImpl {
+    fn diff(
+        &self,
+        before: &HashMap<String, GGBlock>,
+        after: &HashMap<String, GGBlock>,
+    ) -> GraphDiff {
+        let mut differ = Differ::new(before, after);
+        differ.run();
+        GraphDiff {
+            changes: differ.changes,
+        }
+    }
+
+    fn to_graph(
+        &self,
+        before: &HashMap<String, GGBlock>,
+        after: &HashMap<String, GGBlock>,
+    ) -> HashMap<String, GGBlock> {
+        let mut differ = Differ::new(before, after);
+        differ.run();
+        differ.graph
+    }
+}
+
+///What a flow is aligned by: the call of a frame by its occurrence in the block, anything else by its value
+#[derive(PartialEq)]
+enum Key<'a> {
+    Call(String, usize),
+    Value(GGBlockFlowType, Option<&'a str>),
+}
+
+///A flow of both blocks, or of only one of them
+enum Step {
+    Both(usize, usize),
+    Before(usize),
+    After(usize),
+}
+
+///Walks both graphs from START, collecting the changes and the graph of both
+struct Differ<'a> {
+    before: &'a HashMap<String, GGBlock>,
+    after: &'a HashMap<String, GGBlock>,
+    changes: Vec<GraphChange>,
+    graph: HashMap<String, GGBlock>,
+}
+
+impl<'a> Differ<'a> {
+    fn new(before: &'a HashMap<String, GGBlock>, after: &'a HashMap<String, GGBlock>) -> Self {
+        Differ {
+            before,
+            after,
+            changes: Vec::new(),
+            graph: HashMap::new(),
+        }
+    }
+
+    fn run(&mut self) {
+        match (self.before.get("START"), self.after.get("START")) {
+            (Some(_), Some(root)) => {
+                let mut path = vec![root.frame()];
+                self.compare("START", "START", None, &mut path);
+            }
+            (None, Some(root)) => {
+                self.changes.push(call_change(
+                    ChangeKind::AddedCall,
+                    vec![root.frame()],
+                    root,
+                    None,
+                ));
+                self.copy(false, "START", "START", None, GGBlockChange::Added);
+            }
+            (Some(root), None) => {
+                self.changes.push(call_change(
+                    ChangeKind::RemovedCall,
+                    vec![root.frame()],
+                    root,
+                    None,
+                ));
+                self.copy(true, "START", "START", None, GGBlockChange::Removed);
+            }
+            (None, None) => {}
+        }
+    }
+
+    ///Compares two blocks found at the same path, which become one block of the graph under the newer key
+    fn compare(
+        &mut self,
+        before_id: &str,
+        after_id: &str,
+        caller: Option<String>,
+        path: &mut Vec<String>,
+    ) {
+        let (before, after) = (self.before, self.after);
+        let old = &before[before_id];
+        let new = &after[after_id];
+        let old_keys = keys(before, old);
+        let new_keys = keys(after, new);
+        let steps = align(&old_keys, &new_keys);
+        let pairs = pair(&steps, old, new);
+        let mut flow = Vec::new();
+        for step in &steps {
+            match *step {
+                Step::Both(i, j) => {
+                    let (old_flow, new_flow) = (&old.flow[i], &new.flow[j]);
+                    let mut merged = new_flow.clone();
+                    if (old_flow.flow_type, &old_flow.value)
+                        != (new_flow.flow_type, &new_flow.value)
+                    {
+                        merged.change = Some(GGBlockChange::Changed);
+                        let is_store = [old_flow.flow_type, new_flow.flow_type]
+                            .contains(&GGBlockFlowType::CallStore);
+                        let kind = if is_store {
+                            ChangeKind::ChangedStore
+                        } else {
+                            ChangeKind::ChangedFlow
+                        };
+                        let mut call_path = path.clone();
+                        call_path.push(segment(&new_keys[j]));
+                        self.changes.push(flow_change(
+                            kind,
+                            call_path,
+                            Some(old_flow),
+                            Some(new_flow),
+                        ));
+                    }
+                    flow.push(merged);
+                    if let Key::Call(..) = new_keys[j] {
+                        let old_called = old_flow.flow_pointer_id.as_deref().unwrap();
+                        let new_called = new_flow.flow_pointer_id.as_deref().unwrap();
+                        path.push(segment(&new_keys[j]));
+                        self.compare(old_called, new_called, Some(after_id.to_string()), path);
+                        path.pop();
+                    }
+                }
+                Step::Before(i) => {
+                    let old_flow = &old.flow[i];
+                    flow.push(marked(old_flow, true, GGBlockChange::Removed));
+                    if let Key::Call(..) = old_keys[i] {
+                        let called_id = old_flow.flow_pointer_id.as_deref().unwrap();
+                        let mut call_path = path.clone();
+                        call_path.push(segment(&old_keys[i]));
+                        self.changes.push(call_change(
+                            ChangeKind::RemovedCall,
+                            call_path,
+                            &before[called_id],
+                            Some(old_flow),
+                        ));
+                        let copy_id = format!("{}{}", BEFORE, called_id);
+                        self.copy(
+                            true,
+                            called_id,
+                            &copy_id,
+                            Some(after_id.to_string()),
+                            GGBlockChange::Removed,
+                        );
+                        continue;
+                    }
+                    let new_flow = pairs.get(&i).map(|j| &new.flow[*j]);
+                    self.changes.push(flow_change(
+                        value_kind(old_flow.flow_type),
+                        path.clone(),
+                        Some(old_flow),
+                        new_flow,
+                    ));
+                }
+                Step::After(j) => {
+                    let new_flow = &new.flow[j];
+                    flow.push(marked(new_flow, false, GGBlockChange::Added));
+                    if let Key::Call(..) = new_keys[j] {
+                        let called_id = new_flow.flow_pointer_id.as_deref().unwrap();
+                        let mut call_path = path.clone();
+                        call_path.push(segment(&new_keys[j]));
+                        self.changes.push(call_change(
+                            ChangeKind::AddedCall,
+                            call_path,
+                            &after[called_id],
+                            Some(new_flow),
+                        ));
+                        self.copy(
+                            false,
+                            called_id,
+                            called_id,
+                            Some(after_id.to_string()),
+                            GGBlockChange::Added,
+                        );
+                        continue;
+                    }
+                    // A changed value was reported with the older one
+                    if !pairs.values().any(|paired| *paired == j) {
+                        self.changes.push(flow_change(
+                            value_kind(new_flow.flow_type),
+                            path.clone(),
+                            None,
+                            Some(new_flow),
+                        ));
+                    }
+                }
+            }
+        }
+        let block = block(new, caller, flow, None);
+        self.graph.insert(after_id.to_string(), block);
+    }
+
+    ///Puts a block of one graph and every block below it in the graph of both, marked with the change
+    fn copy(
+        &mut self,
+        from_before: bool,
+        block_id: &str,
+        copy_id: &str,
+        caller: Option<String>,
+        change: GGBlockChange,
+    ) {
+        let source = if from_before { self.before } else { self.after };
+        let original = &source[block_id];
+        let flow = original
+            .flow
+            .iter()
+            .map(|flow| marked(flow, from_before, change))
+            .collect();
+        self.graph.insert(
+            copy_id.to_string(),
+            block(original, caller, flow, Some(change)),
+        );
+        for called_id in original
+            .flow
+            .iter()
+            .filter_map(|flow| flow.flow_pointer_id.as_deref())
+            .filter(|called_id| source.contains_key(*called_id))
+        {
+            let called_copy_id = if from_before {
+                format!("{}{}", BEFORE, called_id)
+            } else {
+                called_id.to_string()
+            };
+            self.copy(
+                from_before,
+                called_id,
+                &called_copy_id,
+                Some(copy_id.to_string()),
+                change,
+            );
+        }
+    }
+}
+
+///Flow of a block put in the graph of both. Flows of the older graph get prefixed keys, like the blocks they call.
+fn marked(flow: &GGBlockFlow, from_before: bool, change: GGBlockChange) -> GGBlockFlow {
+    let mut flow = flow.clone();
+    if from_before {
+        flow.flow_id = format!("{}{}", BEFORE, flow.flow_id);
+        flow.flow_pointer_id = flow
+            .flow_pointer_id
+            .map(|called_id| format!("{}{}", BEFORE, called_id));
+    }
+    flow.change = Some(change);
+    flow
+}
+
+fn block(
+    original: &GGBlock,
+    caller: Option<String>,
+    flow: Vec<GGBlockFlow>,
+    change: Option<GGBlockChange>,
+) -> GGBlock {
+    GGBlock {
+        caller,
+        name: original.name.clone(),
+        flow,
+        aborted: original.aborted,
+        failed: original.failed,
+        kind: original.kind,
+        span_id: original.span_id.clone(),
+        sequence: original.sequence,
+        started_at: original.started_at,
+        ended_at: original.ended_at,
+        duration: original.duration,
+        runs: original.runs,
+        change,
+    }
+}
+
+///Keys of the flows of a block. Calls of a block missing from the graph are compared by value.
+fn keys<'a>(graph: &HashMap<String, GGBlock>, block: &'a GGBlock) -> Vec<Key<'a>> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    block
+        .flow
+        .iter()
+        .map(|flow| {
+            match flow
+                .flow_pointer_id
+                .as_ref()
+                .and_then(|called_id| graph.get(called_id))
+            {
+                Some(called) => {
+                    let frame = called.frame();
+                    let occurrence = occurrences.entry(frame.clone()).or_default();
+                    *occurrence += 1;
+                    Key::Call(frame, *occurrence)
+                }
+                None => Key::Value(flow.flow_type, flow.value.as_deref()),
+            }
+        })
+        .collect()
+}
+
+/**
+Longest common subsequence of the keys, as steps in flow order. <br>
+Within a difference the older flows come first. The common start and end are skipped before
+the table is built, so long blocks that changed in one place stay cheap.
+When the rest would need more than `MAX_ALIGN_CELLS`, its flows are paired by position instead.
+ */
+fn align(old: &[Key], new: &[Key]) -> Vec<Step> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_middle, new_middle) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    let (n, m) = (old_middle.len(), new_middle.len());
+    let mut steps: Vec<Step> = (0..prefix).map(|i| Step::Both(i, i)).collect();
+    if (n + 1).saturating_mul(m + 1) > MAX_ALIGN_CELLS {
+        for k in 0..n.max(m) {
+            match (old_middle.get(k), new_middle.get(k)) {
+                (Some(a), Some(b)) if a == b => steps.push(Step::Both(prefix + k, prefix + k)),
+                (a, b) => {
+                    if a.is_some() {
+                        steps.push(Step::Before(prefix + k));
+                    }
+                    if b.is_some() {
+                        steps.push(Step::After(prefix + k));
+                    }
+                }
+            }
+        }
+        steps.extend(
+            (0..suffix).map(|k| Step::Both(old.len() - suffix + k, new.len() - suffix + k)),
+        );
+        return steps;
+    }
+    // lengths[i][j] is the length of the common subsequence of old_middle[i..] and new_middle[j..]
+    let mut lengths = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if old_middle[i] == new_middle[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_middle[i] == new_middle[j] {
+            steps.push(Step::Both(prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            steps.push(Step::Before(prefix + i));
+            i += 1;
+        } else {
+            steps.push(Step::After(prefix + j));
+            j += 1;
+        }
+    }
+    steps.extend((0..suffix).map(|k| Step::Both(old.len() - suffix + k, new.len() - suffix + k)));
+    steps
+}
+
+///Values of the same flow type in a run of differences, the Nth older with the Nth newer, as changed values
+fn pair(steps: &[Step], old: &GGBlock, new: &GGBlock) -> HashMap<usize, usize> {
+    let mut pairs = HashMap::new();
+    let mut removed: Vec<usize> = Vec::new();
+    let mut added: Vec<usize> = Vec::new();
+    let mut close = |removed: &mut Vec<usize>, added: &mut Vec<usize>| {
+        for i in removed.drain(..) {
+            let flow_type = old.flow[i].flow_type;
+            if let Some(position) = added
+                .iter()
+                .position(|j| new.flow[*j].flow_type == flow_type)
+            {
+                pairs.insert(i, added.remove(position));
+            }
+        }
+        added.clear();
+    };
+    for step in steps {
+        match *step {
+            Step::Both(..) => close(&mut removed, &mut added),
+            Step::Before(i) if old.flow[i].flow_pointer_id.is_none() => removed.push(i),
+            Step::After(j) if new.flow[j].flow_pointer_id.is_none() => added.push(j),
+            Step::Before(_) | Step::After(_) => {}
+        }
+    }
+    close(&mut removed, &mut added);
+    pairs
+}
+
+fn value_kind(flow_type: GGBlockFlowType) -> ChangeKind {
+    match flow_type {
+        GGBlockFlowType::Log => ChangeKind::ChangedLog,
+        GGBlockFlowType::CallStore
+        | GGBlockFlowType::Store
+        | GGBlockFlowType::ExternalCallStore => ChangeKind::ChangedStore,
+        _ => ChangeKind::ChangedFlow,
+    }
+}
+
+fn flow_change(
+    change: ChangeKind,
+    path: Vec<String>,
+    old: Option<&GGBlockFlow>,
+    new: Option<&GGBlockFlow>,
+) -> GraphChange {
+    GraphChange {
+        change,
+        path,
+        flow_type: new.or(old).map(|flow| flow.flow_type).unwrap(),
+        before: old.and_then(|flow| flow.value.clone()),
+        after: new.and_then(|flow| flow.value.clone()),
+        before_sequence: old.and_then(|flow| flow.sequence),
+        after_sequence: new.and_then(|flow| flow.sequence),
+    }
+}
+
+///Change of a whole call, with the value stored from it. The root has no call flow.
+fn call_change(
+    change: ChangeKind,
+    path: Vec<String>,
+    called: &GGBlock,
+    call: Option<&GGBlockFlow>,
+) -> GraphChange {
+    let removed = change == ChangeKind::RemovedCall;
+    let flow_type = call.map_or(
+        match called.kind {
+            GGBlockKind::Block => GGBlockFlowType::Call,
+            GGBlockKind::Loop => GGBlockFlowType::Loop,
+        },
+        |flow| flow.flow_type,
+    );
+    let value = call.and_then(|flow| flow.value.clone());
+    GraphChange {
+        change,
+        path,
+        flow_type,
+        before: value.clone().filter(|_| removed),
+        after: value.filter(|_| !removed),
+        before_sequence: called.sequence.filter(|_| removed),
+        after_sequence: called.sequence.filter(|_| !removed),
+    }
+}
+
+///Path segment of a call, with its occurrence after the first one
+fn segment(key: &Key) -> String {
+    match key {
+        Key::Call(frame, 1) => frame.clone(),
+        Key::Call(frame, occurrence) => format!("{}#{}", frame, occurrence),
+        Key::Value(..) => String::new(),
+    }
+}
@@ -0,0 +1,3 @@
+pub mod application;
+#[cfg(test)]
+mod unit_test;
@@ -0,0 +1,196 @@
+use crate::services::diagram_generator::api::models::block::DGBlock;
+use crate::services::diagram_generator::api::models::diagram_options::DiagramOptions;
+use crate::services::diagram_generator::api::services::DiagramType;
+use crate::services::graph_differ::api::models::graph_diff::ChangeKind;
+use crate::services::graph_differ::api::services::graph_differ::GraphDiffer;
+use crate::services::graph_generator::api::models::vis_flow::{GGBlock, GGBlockChange};
+use crate::services::graph_generator::api::models::vis_flow_log_entry::VisFlowLogEntryLogType;
+use crate::services::graph_generator::test_fixture::graph;
+use std::collections::HashMap;
+
+///The good run loads 42 and saves it, the bad one loads 41, retries and never saves
+fn runs() -> (HashMap<String, GGBlock>, HashMap<String, GGBlock>) {
+    use VisFlowLogEntryLogType::{End, Log, Start, Store};
+    let good = graph(&[
+        (Start, "main", None),
+        (Log, "main", Some("checkout")),
+        (Start, "load", None),
+        (End, "load", None),
+        (Store, "main", Some("42")),
+        (Start, "save", None),
+        (End, "save", None),
+        (Log, "main", Some("done")),
+        (End, "main", None),
+    ]);
+    let bad = graph(&[
+        (Start, "main", None),
+        (Log, "main", Some("checkout")),
+        (Start, "load", None),
+        (End, "load", None),
+        (Store, "main", Some("41")),
+        (Start, "retry", None),
+        (Log, "retry", Some("again")),
+        (End, "retry", None),
+        (Log, "main", Some("gave up")),
+        (End, "main", None),
+    ]);
+    (good, bad)
+}
+
+#[test]
+fn diff_reports_calls_logs_and_stored_values() {
+    let differ = crate::services::graph_differ::api::services::graph_differ::new();
+    let (good, bad) = runs();
+    let changes: Vec<(ChangeKind, String, Option<String>, Option<String>)> = differ
+        .diff(&good, &bad)
+        .changes
+        .into_iter()
+        .map(|change| {
+            (
+                change.change,
+                change.path.join(">"),
+                change.before,
+                change.after,
+            )
+        })
+        .collect();
+    let text = |value: &str| Some(value.to_string());
+    assert_eq!(
+        changes,
+        vec![
+            (
+                ChangeKind::ChangedStore,
+                "main>load".to_string(),
+                text("42"),
+                text("41")
+            ),
+            (ChangeKind::RemovedCall, "main>save".to_string(), None, None),
+            (
+                ChangeKind::ChangedLog,
+                "main".to_string(),
+                text("done"),
+                text("gave up")
+            ),
+            (ChangeKind::AddedCall, "main>retry".to_string(), None, None),
+        ]
+    );
+    assert!(differ.diff(&good, &runs().0).changes.is_empty());
+}
+
+#[test]
+fn repeated_calls_are_aligned_by_occurrence() {
+    use VisFlowLogEntryLogType::{End, Start};
+    let differ = crate::services::graph_differ::api::services::graph_differ::new();
+    let twice = graph(&[
+        (Start, "main", None),
+        (Start, "load", None),
+        (End, "load", None),
+        (Start, "load", None),
+        (End, "load", None),
+        (End, "main", None),
+    ]);
+    let once = graph(&[
+        (Start, "main", None),
+        (Start, "load", None),
+        (End, "load", None),
+        (End, "main", None),
+    ]);
+    let diff = differ.diff(&twice, &once);
+    assert_eq!(diff.changes.len(), 1);
+    assert_eq!(diff.changes[0].change, ChangeKind::RemovedCall);
+    assert_eq!(diff.changes[0].path, vec!["main", "load#2"]);
+    assert_eq!(diff.changes[0].before_sequence, Some(3));
+}
+
+#[test]
+fn blocks_too_long_to_align_are_compared_by_position() {
+    use VisFlowLogEntryLogType::{End, Log, Start};
+    let differ = crate::services::graph_differ::api::services::graph_differ::new();
+    let values: Vec<String> = (0..2100).map(|i| i.to_string()).collect();
+    let block = |first: &str, last: &str| {
+        let mut trace = vec![(Start, "main", None), (Log, "main", Some(first))];
+        trace.extend(
+            values
+                .iter()
+                .map(|value| (Log, "main", Some(value.as_str()))),
+        );
+        trace.extend([(Log, "main", Some(last)), (End, "main", None)]);
+        graph(&trace)
+    };
+    let changed_logs = |before: &HashMap<String, GGBlock>, after: &HashMap<String, GGBlock>| {
+        let changes = differ.diff(before, after).changes;
+        assert!(changes
+            .iter()
+            .all(|change| change.change == ChangeKind::ChangedLog));
+        changes.len()
+    };
+
+    // Both blocks differ at their ends, so the 2102 logs between are too many to align and are
+    // paired by position, which still matches the unchanged ones
+    assert_eq!(changed_logs(&block("a", "b"), &block("c", "d")), 2);
+    // Shifted by one, no log is at the same position any more
+    let shifted = {
+        let mut trace = vec![(Start, "main", None)];
+        trace.extend(
+            values
+                .iter()
+                .map(|value| (Log, "main", Some(value.as_str()))),
+        );
+        trace.extend([(Log, "main", Some("x")), (Log, "main", Some("y"))]);
+        trace.push((End, "main", None));
+        graph(&trace)
+    };
+    assert_eq!(changed_logs(&block("a", "b"), &shifted), 2102);
+}
+
+#[test]
+fn diff_diagrams_mark_additions_and_removals() {
+    let differ = crate::services::graph_differ::api::services::graph_differ::new();
+    let (good, bad) = runs();
+    let graph = differ.to_graph(&good, &bad);
+    let changes = |change: GGBlockChange| -> Vec<&str> {
+        let mut names: Vec<&str> = graph
+            .values()
+            .filter(|block| block.change == Some(change))
+            .map(|block| block.name.as_str())
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(changes(GGBlockChange::Removed), vec!["save"]);
+    assert_eq!(changes(GGBlockChange::Added), vec!["retry"]);
+    assert!(graph
+        .iter()
+        .filter(|(_, block)| block.change == Some(GGBlockChange::Removed))
+        .all(|(block_id, block)| block_id.starts_with("before_")
+            && block.caller.as_deref() == Some("START")));
+
+    let diagram = |diagram_type: DiagramType| {
+        let graph: HashMap<String, DGBlock> = differ
+            .to_graph(&good, &bad)
+            .into_iter()
+            .map(|(key, block)| (key, DGBlock::from(block)))
+            .collect();
+        crate::services::diagram_generator::api::services::new(diagram_type)
+            .generate_diagram(graph, &DiagramOptions::default())
+            .unwrap()
+    };
+    let mermaid = diagram(DiagramType::Mermaid);
+    assert!(mermaid.contains("([\"again\"]):::added"), "{}", mermaid);
+    assert!(mermaid.contains("([\"done\"]):::removed"), "{}", mermaid);
+    assert!(mermaid.contains("[/\"41\"/]:::changed"), "{}", mermaid);
+    assert!(mermaid.contains("classDef added fill:#C8E6C9,stroke:#2E7D32"));
+    assert!(mermaid.contains("classDef removed fill:#FFCDD2,stroke:#C62828"));
+    assert!(mermaid.contains("style before_"), "{}", mermaid);
+    let dot = diagram(DiagramType::Dot);
+    assert!(
+        dot.contains("fillcolor=\"#C8E6C9\", color=\"#2E7D32\""),
+        "{}",
+        dot
+    );
+    assert!(
+        dot.contains("fillcolor=\"#FFCDD2\", color=\"#C62828\""),
+        "{}",
+        dot
+    );
+}
@@ -0,0 +1,10 @@
+use crate::services::graph_differ::api::services::graph_differ::GraphDiffer;
+use std::sync::Arc;
+
+pub mod api;
+mod internal;
+
+#[derive(Clone)]
+pub struct AvailableServices {
+    pub graph_differ: Arc<dyn GraphDiffer>,
+}
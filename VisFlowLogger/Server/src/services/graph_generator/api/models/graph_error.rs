@@ -0,0 +1,79 @@
+use std::fmt::Display;
+
+///Why a list of entries doesn't form a valid graph
+#[derive(Debug, PartialEq, Eq)]
+pub enum GraphError {
+    ///A graph needs at least the START and END of the starting block
+    NotEnoughEntries(usize),
+    ///An END doesn't belong to the block that is currently open
+    UnbalancedBlock { expected: String, found: String },
+    ///Blocks were still open after the last entry, innermost last
+    UnclosedBlocks(Vec<String>),
+    ///STORE is only allowed right after a block call
+    StoreWithoutCall { block_name: String },
+    ///An entry of a span that has no open block, other than a START
+    EntryOutsideBlock { block_name: String },
+    ///LOOP_ITER or LOOP_END while the innermost open block isn't a loop
+    NotInLoop { block_name: String },
+    ///A block ended while a loop inside it was still running
+    UnclosedLoop { label: String },
+    ///A stored log has a `log_type` the generator doesn't know
+    InvalidLogType(String),
+}
+
+impl GraphError {
+    ///Machine-readable name of the error, stable across releases
+    pub fn code(&self) -> &'static str {
+        match self {
+            GraphError::NotEnoughEntries(_) => "NotEnoughEntries",
+            GraphError::UnbalancedBlock { .. } => "UnbalancedBlock",
+            GraphError::UnclosedBlocks(_) => "UnclosedBlocks",
+            GraphError::StoreWithoutCall { .. } => "StoreWithoutCall",
+            GraphError::EntryOutsideBlock { .. } => "EntryOutsideBlock",
+            GraphError::NotInLoop { .. } => "NotInLoop",
+            GraphError::UnclosedLoop { .. } => "UnclosedLoop",
+            GraphError::InvalidLogType(_) => "InvalidLogType",
+        }
+    }
+}
+
+impl Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::NotEnoughEntries(count) => write!(
+                f,
+                "GraphGenerator needs at least two entries, got {}",
+                count
+            ),
+            GraphError::UnbalancedBlock { expected, found } => write!(
+                f,
+                "Expected the end of block {} but found the end of {}",
+                expected, found
+            ),
+            GraphError::UnclosedBlocks(blocks) => {
+                write!(f, "Blocks were never closed: {}", blocks.join(" > "))
+            }
+            GraphError::StoreWithoutCall { block_name } => write!(
+                f,
+                "Store entry in block {} while the previous block flow was either empty or not a block call",
+                block_name
+            ),
+            GraphError::EntryOutsideBlock { block_name } => write!(
+                f,
+                "Entry of block {} in a span where no block is open",
+                block_name
+            ),
+            GraphError::NotInLoop { block_name } => write!(
+                f,
+                "Loop entry in block {} while no loop was running",
+                block_name
+            ),
+            GraphError::UnclosedLoop { label } => {
+                write!(f, "Block ended while loop {} was still running", label)
+            }
+            GraphError::InvalidLogType(log_type) => write!(f, "Invalid log_type: {}", log_type),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
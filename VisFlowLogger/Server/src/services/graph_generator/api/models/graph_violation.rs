@@ -0,0 +1,43 @@
+use crate::services::graph_generator::api::models::graph_error::GraphError;
+use serde::Serialize;
+
+///One rule a trace breaks, pointing at the entry where it was detected
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct GraphViolation {
+    ///Same code as the `GraphError` that `generate_graph` would return for it
+    pub code: &'static str,
+    pub message: String,
+    ///Position of the entry in the trace. None when the trace as a whole is at fault
+    pub index: Option<usize>,
+    pub sequence: Option<u32>,
+    ///Block name the rule expected, if any
+    pub expected: Option<String>,
+    ///Block name that was found instead
+    pub actual: Option<String>,
+    ///Blocks open when the violation was detected, outermost first
+    pub open_blocks: Vec<String>,
+}
+
+impl GraphViolation {
+    pub fn new(error: GraphError, at: Option<(usize, u32)>, open_blocks: Vec<String>) -> Self {
+        let (expected, actual) = match &error {
+            GraphError::UnbalancedBlock { expected, found } => {
+                (Some(expected.clone()), Some(found.clone()))
+            }
+            GraphError::StoreWithoutCall { block_name }
+            | GraphError::EntryOutsideBlock { block_name }
+            | GraphError::NotInLoop { block_name } => (None, Some(block_name.clone())),
+            GraphError::UnclosedLoop { label } => (None, Some(label.clone())),
+            _ => (None, None),
+        };
+        GraphViolation {
+            code: error.code(),
+            message: error.to_string(),
+            index: at.map(|(index, _)| index),
+            sequence: at.map(|(_, sequence)| sequence),
+            expected,
+            actual,
+            open_blocks,
+        }
+    }
+}
@@ -1,2 +1,4 @@
+pub mod graph_error;
+pub mod graph_violation;
 pub mod vis_flow_log_entry;
 pub mod vis_flow;
@@ -1,22 +1,63 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display};
 
-#[derive(Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct GGBlock {
     /// Key of a block that called it.
     pub caller: Option<String>,
     pub name: String,
     pub flow: Vec<GGBlockFlow>,
+    /// The block never ended and was closed by the lenient graph mode.
+    #[serde(default)]
+    pub aborted: bool,
+    /// The block threw, logged an error, or ended right after a call that threw and so passed the error on.
+    #[serde(default)]
+    pub failed: bool,
+    #[serde(default)]
+    pub kind: GGBlockKind,
+    /// Span the block ran in, if its entries named one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    /// Sequence number of the entry that started the block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u32>,
+    /// Client time of the entry that started the block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    /// Client time of the entry that ended the block. Missing for aborted blocks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Set when both `started_at` and `ended_at` are known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<GGBlockDuration>,
+    /// Runs of the block merged into it by the aggregation of many operations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runs: Option<usize>,
+    /// Set in the graph of a diff between two operations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<GGBlockChange>,
 }
 
+impl GGBlock {
+    /// Name of the block in paths and calls. A loop is named by its label.
+    pub fn frame(&self) -> String {
+        match self.kind {
+            GGBlockKind::Block => self.name.clone(),
+            GGBlockKind::Loop => format!("loop: {}", self.name),
+        }
+    }
+}
 
 impl Display for GGBlock {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "VisFlowBlock {{ caller: {:?}, name: {}, flow: [{}] }}",
+            "VisFlowBlock {{ caller: {:?}, name: {}, aborted: {}, failed: {}, flow: [{}] }}",
             self.caller,
             self.name,
+            self.aborted,
+            self.failed,
             self.flow
                 .iter()
                 .map(|b| format!("{}", b))
@@ -26,12 +67,27 @@ impl Display for GGBlock {
     }
 }
 
-#[derive(Eq, PartialEq, Hash, Serialize, Deserialize, Clone)]
+/// How long a block ran, in microseconds.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct GGBlockDuration {
+    /// From START to END, including the blocks it called.
+    pub inclusive_us: u64,
+    /// Time spent in the block itself.
+    pub exclusive_us: u64,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Serialize, Deserialize, Clone)]
 pub struct GGBlockFlow {
     pub flow_pointer_id: Option<String>,
     pub flow_id: String,
     pub flow_type: GGBlockFlowType,
     pub value: Option<String>,
+    /// Sequence number of the entry that added the flow. Calls have the one that started the block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u32>,
+    /// Set in the graph of a diff between two operations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<GGBlockChange>,
 }
 
 impl Display for GGBlockFlow {
@@ -44,13 +100,27 @@ impl Display for GGBlockFlow {
     }
 }
 
-#[derive(Eq, PartialEq, Hash, Serialize, Deserialize, Clone)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum GGBlockFlowType {
     Call,
     CallStore,
     Log,
     ExternalCall,
     ExternalCallStore,
+    /// Stored value without a preceding call, kept by the lenient graph mode.
+    Store,
+    /// Call of a block that ran in another span, concurrently with the rest of the caller.
+    AsyncCall,
+    /// Error logged by the block.
+    Error,
+    /// Call of a block that threw. The value is the error.
+    CallError,
+    /// Decision taken by the block. The value is the condition.
+    Branch,
+    /// Call of a loop block. The value is the number of iterations.
+    Loop,
+    /// Start of the next iteration in a loop block.
+    LoopIteration,
 }
 
 impl Display for GGBlockFlowType {
@@ -61,7 +131,33 @@ impl Display for GGBlockFlowType {
             GGBlockFlowType::Log => "Log",
             GGBlockFlowType::ExternalCall => "ExternalCall",
             GGBlockFlowType::ExternalCallStore => "ExternalCallStore",
+            GGBlockFlowType::Store => "Store",
+            GGBlockFlowType::AsyncCall => "AsyncCall",
+            GGBlockFlowType::Error => "Error",
+            GGBlockFlowType::CallError => "CallError",
+            GGBlockFlowType::Branch => "Branch",
+            GGBlockFlowType::Loop => "Loop",
+            GGBlockFlowType::LoopIteration => "LoopIteration",
         };
         write!(f, "{}", type_str)
     }
 }
+
+/// A function call, or a loop inside one. The name of a loop is its label.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum GGBlockKind {
+    #[default]
+    Block,
+    Loop,
+}
+
+/// How a block or flow of a diff differs between the older and the newer operation.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum GGBlockChange {
+    /// Only in the newer operation.
+    Added,
+    /// Only in the older operation.
+    Removed,
+    /// A call of both operations with another value or outcome, shown as in the newer one.
+    Changed,
+}
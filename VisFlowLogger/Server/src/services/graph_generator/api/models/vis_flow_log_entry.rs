@@ -1,3 +1,6 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum VisFlowLogEntryLogType {
     Log,
     Store,
@@ -5,9 +8,60 @@ pub enum VisFlowLogEntryLogType {
     ExternalCallStore,
     Start,
     End,
+    ///An error in the block, which keeps running
+    Error,
+    ///The block ends abnormally and the error propagates to its caller.
+    ///A caller that ENDs right after the call passes it on as well, anything logged in between handles it.
+    Throw,
+    ///A decision, the value is the condition and the branch taken
+    Branch,
+    ///Start of a loop, the value is its label
+    LoopStart,
+    ///Start of the next iteration of the innermost loop
+    LoopIter,
+    LoopEnd,
 }
 pub struct VisFlowLogEntry {
     pub block_name: String,
     pub log_type: VisFlowLogEntryLogType,
     pub log_value: Option<String>,
+    ///Order of the entry within its operation, used to point at it in diagnostics
+    pub sequence: u32,
+    ///Task or thread the entry was written from. Entries of one span form their own call stack
+    pub span_id: Option<String>,
+    ///Span whose open block started `span_id`. Falls back to the span of the first entry
+    pub parent_span_id: Option<String>,
+    ///Time the client wrote the entry. Blocks whose START and END both have one get a duration
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+impl VisFlowLogEntryLogType {
+    ///Parses the `log_type` string sent by clients and stored by persistence
+    pub fn from_log_type(log_type: &str) -> Option<Self> {
+        match log_type {
+            "LOG" => Some(VisFlowLogEntryLogType::Log),
+            "START" => Some(VisFlowLogEntryLogType::Start),
+            "END" => Some(VisFlowLogEntryLogType::End),
+            "STORE" => Some(VisFlowLogEntryLogType::Store),
+            "EXTERNAL_CALL" => Some(VisFlowLogEntryLogType::ExternalCall),
+            "EXTERNAL_CALL_STORE" => Some(VisFlowLogEntryLogType::ExternalCallStore),
+            "ERROR" => Some(VisFlowLogEntryLogType::Error),
+            "THROW" => Some(VisFlowLogEntryLogType::Throw),
+            "BRANCH" => Some(VisFlowLogEntryLogType::Branch),
+            "LOOP_START" => Some(VisFlowLogEntryLogType::LoopStart),
+            "LOOP_ITER" => Some(VisFlowLogEntryLogType::LoopIter),
+            "LOOP_END" => Some(VisFlowLogEntryLogType::LoopEnd),
+            _ => None,
+        }
+    }
+}
+
+impl VisFlowLogEntry {
+    ///Name of the loop a LOOP_START opens
+    pub fn loop_label(&self) -> &str {
+        match self.log_value.as_deref() {
+            Some(label) if !label.is_empty() => label,
+            _ => "loop",
+        }
+    }
 }
@@ -1,6 +1,8 @@
+use crate::services::graph_generator::api::models::graph_error::GraphError;
+use crate::services::graph_generator::api::models::graph_violation::GraphViolation;
+use crate::services::graph_generator::api::models::vis_flow::GGBlock;
 use crate::services::graph_generator::api::models::vis_flow_log_entry::VisFlowLogEntry;
 use crate::services::graph_generator::internal::application::graph_generator_impl::GraphGeneratorImpl;
-use crate::services::graph_generator::api::models::vis_flow::GGBlock;
 use async_trait::async_trait;
 use std::collections::HashMap;
 
@@ -21,7 +23,23 @@ pub trait GraphGenerator: Send + Sync {
     fn generate_graph(
         &self,
         entries: Vec<VisFlowLogEntry>,
-    ) -> Result<HashMap<String, GGBlock>, String>;
+    ) -> Result<HashMap<String, GGBlock>, GraphError>;
+
+    /**
+    Like `generate_graph`, but repairs broken traces instead of rejecting them. <br>
+    Missing ENDs are synthesized and the blocks they end are marked `aborted`,
+    a STORE without a call is kept as a standalone `Store` flow.
+    */
+    fn generate_lenient_graph(
+        &self,
+        entries: Vec<VisFlowLogEntry>,
+    ) -> Result<HashMap<String, GGBlock>, GraphError>;
+
+    ///Returns every rule `generate_graph` checks that the entries break. Empty when the trace is valid.
+    fn validate(&self, entries: &[VisFlowLogEntry]) -> Vec<GraphViolation>;
+
+    ///Keeps only the first iteration of every loop. The loop's call still counts all of them.
+    fn collapse_loops(&self, graph: &mut HashMap<String, GGBlock>);
 }
 
 pub fn new() -> impl GraphGenerator {
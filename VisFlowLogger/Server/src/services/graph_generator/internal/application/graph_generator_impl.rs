@@ -1,14 +1,17 @@
+use crate::services::graph_generator::api::models::graph_error::GraphError;
+use crate::services::graph_generator::api::models::graph_violation::GraphViolation;
+use crate::services::graph_generator::api::models::vis_flow::{
+    GGBlock, GGBlockDuration, GGBlockFlow, GGBlockFlowType, GGBlockKind,
+};
 use crate::services::graph_generator::api::models::vis_flow_log_entry::{
     VisFlowLogEntry, VisFlowLogEntryLogType,
 };
 use crate::services::graph_generator::api::services::graph_generator::GraphGenerator;
-use crate::services::graph_generator::api::models::vis_flow::{
-    GGBlock, GGBlockFlow, GGBlockFlowType,
-};
+use crate::services::graph_generator::internal::application::{graph_transform, graph_validator};
 
 use async_trait::async_trait;
-use std::collections::HashMap;
-use uuid::Uuid;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 
 pub struct GraphGeneratorImpl;
 
@@ -17,131 +20,451 @@ impl GraphGenerator for GraphGeneratorImpl {
     fn generate_graph(
         &self,
         entries: Vec<VisFlowLogEntry>,
-    ) -> Result<HashMap<String, GGBlock>, String> {
-        // Initial entries validation
-        {
-            if entries.len() < 2 {
-                return Err("GraphGenerator needs at least two entries".to_string());
-            }
-            if entries.first().unwrap().block_name != entries.last().unwrap().block_name {
-                return Err("Starting and Ending block's name are not the same".to_string());
-            }
+    ) -> Result<HashMap<String, GGBlock>, GraphError> {
+        //A valid trace needs no repairs, so both modes build it the same way
+        if let Some(violation) = graph_validator::violations(&entries).into_iter().next() {
+            return Err(violation.error);
         }
-        let mut graph = HashMap::new();
-        let mut current_block_id: Option<String> = None;
-        let mut caller_stack: Vec<String> = Vec::new();
-        //First entry operations
-        {
-            let first_entry = entries.first().unwrap();
-            let block = create_block(&first_entry.block_name, None);
-            current_block_id = Some("START".to_string());
-            graph.insert("START".to_string(), block);
+        build_graph(entries)
+    }
+
+    fn generate_lenient_graph(
+        &self,
+        entries: Vec<VisFlowLogEntry>,
+    ) -> Result<HashMap<String, GGBlock>, GraphError> {
+        build_graph(entries)
+    }
+
+    fn validate(&self, entries: &[VisFlowLogEntry]) -> Vec<GraphViolation> {
+        graph_validator::validate(entries)
+    }
+
+    fn collapse_loops(&self, graph: &mut HashMap<String, GGBlock>) {
+        graph_transform::collapse_loops(graph)
+    }
+}
+
+///Blocks open in one span, the innermost one is `current_block_id`
+#[derive(Default)]
+struct SpanStack {
+    current_block_id: Option<String>,
+    caller_stack: Vec<String>,
+}
+
+/**
+Builds the graph. Every span has its own call stack, so entries of concurrent spans may interleave. <br>
+The first block of a span is added to the flow of the block open in its parent span as an `AsyncCall`.
+Entries without a span all belong to one span, which is the format used before spans. <br>
+Broken traces are repaired, which leaves valid ones untouched:
+an END of a block further up the stack ends the blocks inside it, an END of a block that isn't open is skipped,
+a STORE without a call becomes a standalone `Store` flow and blocks still open at the end are ended.
+Blocks ended that way are marked `aborted`.
+ */
+fn build_graph(entries: Vec<VisFlowLogEntry>) -> Result<HashMap<String, GGBlock>, GraphError> {
+    if entries.is_empty() {
+        return Err(GraphError::NotEnoughEntries(0));
+    }
+    let mut graph = HashMap::new();
+    let ids = EntryIds::new(&entries);
+    let root_span = entries.first().unwrap().span_id.clone();
+    let mut spans: HashMap<Option<String>, SpanStack> = HashMap::new();
+    //Spans in the order they first appear, the starting span first
+    let mut span_order: Vec<Option<String>> = Vec::new();
+    //First entry operations
+    {
+        let first_entry = entries.first().unwrap();
+        let block = create_block(&first_entry.block_name, None, first_entry);
+        graph.insert("START".to_string(), block);
+        spans.insert(
+            root_span.clone(),
+            SpanStack {
+                current_block_id: Some("START".to_string()),
+                caller_stack: Vec::new(),
+            },
+        );
+        span_order.push(root_span.clone());
+    }
+    //Other Entries
+    for (index, entry) in entries.iter().enumerate().skip(1) {
+        let key = ids.key(index, entry);
+        if let Entry::Vacant(span) = spans.entry(entry.span_id.clone()) {
+            span.insert(SpanStack::default());
+            span_order.push(entry.span_id.clone());
         }
-        //Other Entries
-        for entry in entries.iter().skip(1) {
-            let current_block: &mut GGBlock =
-                graph.get_mut(&current_block_id.clone().unwrap()).unwrap();
-            match entry.log_type {
-                //If it's a log, it is to be directly added to the flow of the previous block.
-                VisFlowLogEntryLogType::Log => current_block.flow.push(GGBlockFlow {
-                    flow_id: Uuid::new_v4().to_string(),
-                    flow_type: GGBlockFlowType::Log,
-                    value: entry.log_value.clone(),
-                    flow_pointer_id: None,
-                    //current_block_id is still the same
-                }),
-                //Is a start of a new block. Called by previous block
-                VisFlowLogEntryLogType::Start => {
-                    let block_id = Uuid::new_v4().to_string();
-                    let block = create_block(&entry.block_name, current_block_id.clone());
-                    caller_stack.push(current_block_id.clone().unwrap());
-                    graph.insert(block_id.clone(), block);
-                    current_block_id = Some(block_id); //start of a new block
+        let Some(current_block_id) = spans[&entry.span_id].current_block_id.clone() else {
+            //Entries after the end of the starting block are ignored
+            if entry.span_id == root_span {
+                continue;
+            }
+            if entry.log_type != VisFlowLogEntryLogType::Start {
+                continue;
+            }
+            //Top block of a span. Called by the block open in the parent span
+            let caller_id = spawning_block(&spans, entry, &root_span);
+            let block_id = block_id(&key);
+            graph.get_mut(&caller_id).unwrap().flow.push(GGBlockFlow {
+                flow_id: call_id(&block_id),
+                flow_type: GGBlockFlowType::AsyncCall,
+                value: None,
+                flow_pointer_id: Some(block_id.clone()),
+                sequence: Some(entry.sequence),
+                change: None,
+            });
+            let block = create_block(&entry.block_name, Some(caller_id), entry);
+            graph.insert(block_id.clone(), block);
+            spans.get_mut(&entry.span_id).unwrap().current_block_id = Some(block_id);
+            continue;
+        };
+        let span = spans.get_mut(&entry.span_id).unwrap();
+        let current_block: &mut GGBlock = graph.get_mut(&current_block_id).unwrap();
+        match entry.log_type {
+            //If it's a log, it is to be directly added to the flow of the previous block.
+            VisFlowLogEntryLogType::Log => current_block.flow.push(GGBlockFlow {
+                flow_id: flow_id(&key),
+                flow_type: GGBlockFlowType::Log,
+                value: entry.log_value.clone(),
+                flow_pointer_id: None,
+                sequence: Some(entry.sequence),
+                change: None,
+                //current_block_id is still the same
+            }),
+            //Is a start of a new block. Called by previous block
+            VisFlowLogEntryLogType::Start => {
+                let block_id = block_id(&key);
+                let block = create_block(&entry.block_name, Some(current_block_id.clone()), entry);
+                span.caller_stack.push(current_block_id);
+                graph.insert(block_id.clone(), block);
+                span.current_block_id = Some(block_id); //start of a new block
+            }
+            //End of current block. Added to the caller's flow. A THROW also fails the block.
+            VisFlowLogEntryLogType::End | VisFlowLogEntryLogType::Throw => {
+                //Previous block's name and current entry's name needs to match. Loops end on their own.
+                if current_block.kind == GGBlockKind::Loop || current_block.name != entry.block_name
+                {
+                    let Some(position) = span.caller_stack.iter().rposition(|block_id| {
+                        let block = &graph[block_id];
+                        block.kind == GGBlockKind::Block && block.name == entry.block_name
+                    }) else {
+                        //Nothing open to end
+                        continue;
+                    };
+                    abort_inner_blocks(&mut graph, span, position);
                 }
-                //End of current block. Added to the caller's flow.
-                VisFlowLogEntryLogType::End => {
-                    //Validations
-                    {
-                        //Previous block's name and current entry's name needs to match
-                        if current_block.name != entry.block_name {
-                            return Err(
-                                "Starting and Ending block's name are not the same".to_string()
-                            );
-                        }
+                let block_id = span.current_block_id.take().unwrap();
+                let block = graph.get_mut(&block_id).unwrap();
+                block.ended_at = entry.timestamp;
+                let end = match block.flow.last() {
+                    _ if entry.log_type == VisFlowLogEntryLogType::Throw => {
+                        BlockEnd::Thrown(entry.log_value.clone())
                     }
-                    let caller_id_popped: Option<String> = caller_stack.pop();
-                    //If current block has no caller then it MUST be the starting block
-                    if caller_id_popped.is_none() {
-                        let root_block = graph.get("START").unwrap();
-                        if root_block.name != entry.block_name {
-                            return Err("End of a block but it has no caller. Only starting block can have no caller".to_string());
-                        }
-                        break;
+                    //Ending right after a call that threw passes its error on, anything in between handled it
+                    Some(flow) if flow.flow_type == GGBlockFlowType::CallError => {
+                        BlockEnd::Thrown(flow.value.clone())
                     }
-                    let caller_id: String = caller_id_popped.unwrap();
-                    let caller_block: &mut GGBlock = graph.get_mut(&caller_id).unwrap();
-                    caller_block.flow.push(GGBlockFlow {
-                        flow_id: Uuid::new_v4().to_string(),
-                        flow_type: GGBlockFlowType::Call,
-                        value: None,
-                        //Points to the ID of the called block
-                        flow_pointer_id: Some(current_block_id.unwrap()),
-                    });
-                    //Caller was popped and is now the latest interacted block.
-                    current_block_id = Some(caller_id);
+                    _ => BlockEnd::Returned,
+                };
+                //If current block has no caller in the span, the span has nothing open anymore
+                span.current_block_id =
+                    end_block(&mut graph, &mut span.caller_stack, block_id, end);
+            }
+            //A loop is a block of its own inside the current one, its iterations are flows of it
+            VisFlowLogEntryLogType::LoopStart => {
+                let block_id = block_id(&key);
+                let mut block =
+                    create_block(entry.loop_label(), Some(current_block_id.clone()), entry);
+                block.kind = GGBlockKind::Loop;
+                span.caller_stack.push(current_block_id);
+                graph.insert(block_id.clone(), block);
+                span.current_block_id = Some(block_id);
+            }
+            VisFlowLogEntryLogType::LoopIter => {
+                if current_block.kind != GGBlockKind::Loop {
+                    continue;
                 }
-                //Previous flow was a block call, and it's return value is stored
-                VisFlowLogEntryLogType::Store => {
-                    //Validations
-                    {
-                        //Latest flow must be of type call
-                        if current_block.flow.is_empty()
-                            || current_block.flow.last().unwrap().flow_type != GGBlockFlowType::Call
-                        {
-                            return Err("Store entry type while the previous block flow was either empty or not a block call".to_string());
-                        }
-                    }
-                    let last_flow = current_block.flow.last_mut().unwrap();
-                    last_flow.flow_type = GGBlockFlowType::CallStore;
-                    last_flow.value = Some(entry.log_value.clone().unwrap());
+                current_block.flow.push(GGBlockFlow {
+                    flow_id: flow_id(&key),
+                    flow_type: GGBlockFlowType::LoopIteration,
+                    value: entry.log_value.clone(),
+                    flow_pointer_id: None,
+                    sequence: Some(entry.sequence),
+                    change: None,
+                });
+            }
+            VisFlowLogEntryLogType::LoopEnd => {
+                if current_block.kind != GGBlockKind::Loop {
+                    let Some(position) = span
+                        .caller_stack
+                        .iter()
+                        .rposition(|block_id| graph[block_id].kind == GGBlockKind::Loop)
+                    else {
+                        continue;
+                    };
+                    abort_inner_blocks(&mut graph, span, position);
                 }
-                //External block call or block store call.
-                VisFlowLogEntryLogType::ExternalCall
-                | VisFlowLogEntryLogType::ExternalCallStore => {
-                    let block = GGBlockFlow {
-                        value: Some(entry.log_value.clone().unwrap()),
-                        flow_type: match entry.log_type {
-                            VisFlowLogEntryLogType::ExternalCallStore => {
-                                GGBlockFlowType::ExternalCallStore
-                            }
-                            VisFlowLogEntryLogType::ExternalCall => GGBlockFlowType::ExternalCall,
-                            _ => {
-                                return Err("Expected entry log's type to be ExternalCallStore or ExternalCallStore".to_string());
-                            }
-                        },
-                        flow_id: Uuid::new_v4().to_string(),
+                let block_id = span.current_block_id.take().unwrap();
+                graph.get_mut(&block_id).unwrap().ended_at = entry.timestamp;
+                span.current_block_id = end_block(
+                    &mut graph,
+                    &mut span.caller_stack,
+                    block_id,
+                    BlockEnd::Returned,
+                );
+            }
+            VisFlowLogEntryLogType::Branch => current_block.flow.push(GGBlockFlow {
+                flow_id: flow_id(&key),
+                flow_type: GGBlockFlowType::Branch,
+                value: entry.log_value.clone(),
+                flow_pointer_id: None,
+                sequence: Some(entry.sequence),
+                change: None,
+            }),
+            //The block keeps running, but it failed
+            VisFlowLogEntryLogType::Error => {
+                current_block.failed = true;
+                current_block.flow.push(GGBlockFlow {
+                    flow_id: flow_id(&key),
+                    flow_type: GGBlockFlowType::Error,
+                    value: entry.log_value.clone(),
+                    flow_pointer_id: None,
+                    sequence: Some(entry.sequence),
+                    change: None,
+                });
+            }
+            //Previous flow was a block call, and it's return value is stored
+            VisFlowLogEntryLogType::Store => {
+                //Latest flow must be of type call
+                if current_block.flow.is_empty()
+                    || current_block.flow.last().unwrap().flow_type != GGBlockFlowType::Call
+                {
+                    current_block.flow.push(GGBlockFlow {
+                        flow_id: flow_id(&key),
+                        flow_type: GGBlockFlowType::Store,
+                        value: entry.log_value.clone(),
                         flow_pointer_id: None,
-                    };
-                    current_block.flow.push(block);
+                        sequence: Some(entry.sequence),
+                        change: None,
+                    });
+                    continue;
                 }
+                let last_flow = current_block.flow.last_mut().unwrap();
+                last_flow.flow_type = GGBlockFlowType::CallStore;
+                last_flow.value = entry.log_value.clone();
             }
-        }
-        //Post graph gen validation
-        {
-            if !caller_stack.is_empty() {
-                return Err(
-                    "caller stack is NOT empty after generating graph. Please check your log"
-                        .to_string(),
-                );
+            //External block call or block store call.
+            VisFlowLogEntryLogType::ExternalCall | VisFlowLogEntryLogType::ExternalCallStore => {
+                let block = GGBlockFlow {
+                    value: entry.log_value.clone(),
+                    flow_type: if matches!(
+                        entry.log_type,
+                        VisFlowLogEntryLogType::ExternalCallStore
+                    ) {
+                        GGBlockFlowType::ExternalCallStore
+                    } else {
+                        GGBlockFlowType::ExternalCall
+                    },
+                    flow_id: flow_id(&key),
+                    flow_pointer_id: None,
+                    sequence: Some(entry.sequence),
+                    change: None,
+                };
+                current_block.flow.push(block);
             }
         }
-        Ok(graph)
+    }
+    //Blocks still open, including the starting block, never ended
+    for span_id in &span_order {
+        let span = spans.get_mut(span_id).unwrap();
+        while let Some(block_id) = span.current_block_id.take() {
+            span.current_block_id = end_block(
+                &mut graph,
+                &mut span.caller_stack,
+                block_id,
+                BlockEnd::Aborted,
+            );
+        }
+    }
+    measure_durations(&mut graph);
+    Ok(graph)
+}
+
+///Ends the blocks above `position` in the span's stack, which never ended themselves
+fn abort_inner_blocks(graph: &mut HashMap<String, GGBlock>, span: &mut SpanStack, position: usize) {
+    while span.caller_stack.len() > position {
+        let block_id = span.current_block_id.take().unwrap();
+        span.current_block_id =
+            end_block(graph, &mut span.caller_stack, block_id, BlockEnd::Aborted);
+    }
+}
+
+///Block that a new span was started from: the innermost open block of its parent span,
+///else of the starting span, else the starting block.
+fn spawning_block(
+    spans: &HashMap<Option<String>, SpanStack>,
+    entry: &VisFlowLogEntry,
+    root_span: &Option<String>,
+) -> String {
+    let parent_span = if entry.parent_span_id.is_some() {
+        &entry.parent_span_id
+    } else {
+        root_span
+    };
+    [parent_span, root_span]
+        .into_iter()
+        .find_map(|span_id| spans.get(span_id)?.current_block_id.clone())
+        .unwrap_or_else(|| "START".to_string())
+}
+
+///How a block ended
+enum BlockEnd {
+    Returned,
+    ///THROW, with the error it carried
+    Thrown(Option<String>),
+    ///Never ended, closed by the lenient mode
+    Aborted,
+}
+
+///Ends a block by adding it to its caller's flow. Returns the caller, which is the block open now.
+fn end_block(
+    graph: &mut HashMap<String, GGBlock>,
+    caller_stack: &mut Vec<String>,
+    block_id: String,
+    end: BlockEnd,
+) -> Option<String> {
+    let block = graph.get_mut(&block_id).unwrap();
+    //The call is where the block started
+    let sequence = block.sequence;
+    let (flow_type, value) = match end {
+        BlockEnd::Returned => (GGBlockFlowType::Call, None),
+        BlockEnd::Thrown(error) => {
+            block.failed = true;
+            (GGBlockFlowType::CallError, error)
+        }
+        BlockEnd::Aborted => {
+            block.aborted = true;
+            (GGBlockFlowType::Call, None)
+        }
+    };
+    //A loop returns the number of iterations it ran
+    let (flow_type, value) = if block.kind == GGBlockKind::Loop {
+        let iterations = block
+            .flow
+            .iter()
+            .filter(|flow| flow.flow_type == GGBlockFlowType::LoopIteration)
+            .count();
+        (GGBlockFlowType::Loop, Some(iterations.to_string()))
+    } else {
+        (flow_type, value)
+    };
+    let caller_id = caller_stack.pop()?;
+    let caller_block: &mut GGBlock = graph.get_mut(&caller_id).unwrap();
+    caller_block.flow.push(GGBlockFlow {
+        flow_id: call_id(&block_id),
+        flow_type,
+        value,
+        //Points to the ID of the called block
+        flow_pointer_id: Some(block_id),
+        sequence,
+        change: None,
+    });
+    Some(caller_id)
+}
+
+/**
+IDs are made from the sequence of the entry that added the block or flow, so the same trace always gives the same graph. <br>
+A sequence used by several entries also gets the entry's position. The flow that calls a block is named after the block,
+since a block is called from one place only.
+ */
+struct EntryIds {
+    repeated: HashSet<u32>,
+}
+
+impl EntryIds {
+    fn new(entries: &[VisFlowLogEntry]) -> Self {
+        let mut seen = HashSet::new();
+        let repeated = entries
+            .iter()
+            .filter(|entry| !seen.insert(entry.sequence))
+            .map(|entry| entry.sequence)
+            .collect();
+        EntryIds { repeated }
+    }
+
+    fn key(&self, index: usize, entry: &VisFlowLogEntry) -> String {
+        if self.repeated.contains(&entry.sequence) {
+            format!("{}_{}", entry.sequence, index)
+        } else {
+            entry.sequence.to_string()
+        }
     }
 }
-fn create_block(block_name: &String, caller: Option<String>) -> GGBlock {
+
+fn block_id(key: &str) -> String {
+    format!("b{}", key)
+}
+
+fn flow_id(key: &str) -> String {
+    format!("f{}", key)
+}
+
+fn call_id(block_id: &str) -> String {
+    format!("{}_call", block_id)
+}
+
+///Block started by `entry`, which is a START, a LOOP_START or the first entry of a span
+fn create_block(block_name: &str, caller: Option<String>, entry: &VisFlowLogEntry) -> GGBlock {
     GGBlock {
-        name: block_name.clone(),
+        name: block_name.to_string(),
         flow: vec![],
         caller,
+        aborted: false,
+        failed: false,
+        kind: GGBlockKind::Block,
+        span_id: entry.span_id.clone(),
+        sequence: Some(entry.sequence),
+        started_at: entry.timestamp,
+        ended_at: None,
+        duration: None,
+        runs: None,
+        change: None,
+    }
+}
+
+/**
+Sets the duration of every block whose start and end time are known. <br>
+The exclusive time leaves out the blocks and loops it called and waited for.
+Blocks started in another span ran concurrently, so they are not subtracted.
+ */
+fn measure_durations(graph: &mut HashMap<String, GGBlock>) {
+    let inclusive: HashMap<String, u64> = graph
+        .iter()
+        .filter_map(|(block_id, block)| {
+            let elapsed = block.ended_at? - block.started_at?;
+            //Clocks may step back, which is no time at all
+            let elapsed = elapsed.num_microseconds().unwrap_or(i64::MAX).max(0) as u64;
+            Some((block_id.clone(), elapsed))
+        })
+        .collect();
+    for (block_id, block) in graph.iter_mut() {
+        let Some(&inclusive_us) = inclusive.get(block_id) else {
+            continue;
+        };
+        let called_us: u64 = block
+            .flow
+            .iter()
+            .filter(|flow| {
+                matches!(
+                    flow.flow_type,
+                    GGBlockFlowType::Call
+                        | GGBlockFlowType::CallStore
+                        | GGBlockFlowType::CallError
+                        | GGBlockFlowType::Loop
+                )
+            })
+            .filter_map(|flow| inclusive.get(flow.flow_pointer_id.as_ref()?))
+            .sum();
+        block.duration = Some(GGBlockDuration {
+            inclusive_us,
+            exclusive_us: inclusive_us.saturating_sub(called_us),
+        });
     }
 }
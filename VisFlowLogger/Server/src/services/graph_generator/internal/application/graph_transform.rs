@@ -0,0 +1,53 @@
+use crate::services::graph_generator::api::models::vis_flow::{
+    GGBlock, GGBlockFlowType, GGBlockKind,
+};
+use std::collections::HashMap;
+
+/**
+Keeps only the first iteration of every loop block. <br>
+The flows of later iterations are dropped together with every block they called,
+the call of the loop still carries the full iteration count.
+ */
+pub fn collapse_loops(graph: &mut HashMap<String, GGBlock>) {
+    let loop_ids: Vec<String> = graph
+        .iter()
+        .filter(|(_, block)| block.kind == GGBlockKind::Loop)
+        .map(|(block_id, _)| block_id.clone())
+        .collect();
+    for loop_id in loop_ids {
+        //An inner loop may already be gone with an outer one
+        let Some(block) = graph.get_mut(&loop_id) else {
+            continue;
+        };
+        let second_iteration = block
+            .flow
+            .iter()
+            .enumerate()
+            .filter(|(_, flow)| flow.flow_type == GGBlockFlowType::LoopIteration)
+            .nth(1)
+            .map(|(position, _)| position);
+        let Some(position) = second_iteration else {
+            continue;
+        };
+        let dropped: Vec<String> = block
+            .flow
+            .drain(position..)
+            .filter_map(|flow| flow.flow_pointer_id)
+            .collect();
+        for block_id in dropped {
+            remove_block(graph, &block_id);
+        }
+    }
+}
+
+///Removes a block and every block it called
+fn remove_block(graph: &mut HashMap<String, GGBlock>, block_id: &str) {
+    let Some(block) = graph.remove(block_id) else {
+        return;
+    };
+    for flow in block.flow {
+        if let Some(called) = flow.flow_pointer_id {
+            remove_block(graph, &called);
+        }
+    }
+}
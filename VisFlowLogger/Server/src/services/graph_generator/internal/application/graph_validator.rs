@@ -0,0 +1,240 @@
+use crate::services::graph_generator::api::models::graph_error::GraphError;
+use crate::services::graph_generator::api::models::graph_violation::GraphViolation;
+use crate::services::graph_generator::api::models::vis_flow_log_entry::{
+    VisFlowLogEntry, VisFlowLogEntryLogType,
+};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+///What the latest flow of a block is, as far as the flows after it care
+#[derive(PartialEq)]
+enum Latest {
+    Flow,
+    ///A call that returned, which a STORE may follow
+    Call,
+    ///A call that threw, whose error an END right after it passes on
+    FailedCall,
+}
+
+struct OpenBlock<'a> {
+    name: &'a str,
+    latest: Latest,
+    //A loop inside a block, named by its label
+    is_loop: bool,
+}
+
+impl<'a> OpenBlock<'a> {
+    fn new(name: &'a str, is_loop: bool) -> Self {
+        OpenBlock {
+            name,
+            latest: Latest::Flow,
+            is_loop,
+        }
+    }
+}
+
+///A broken rule, the entry it was detected at and the blocks open at that point
+pub struct Violation {
+    pub error: GraphError,
+    pub at: Option<(usize, u32)>,
+    pub open_blocks: Vec<String>,
+}
+
+impl Violation {
+    fn new(error: GraphError, at: Option<(usize, u32)>, open_blocks: Vec<String>) -> Self {
+        Violation {
+            error,
+            at,
+            open_blocks,
+        }
+    }
+}
+
+fn names(stack: &[OpenBlock]) -> Vec<String> {
+    stack.iter().map(|block| block.name.to_string()).collect()
+}
+
+///Every rule the entries break, as reported by `GraphGenerator::validate`
+pub fn validate(entries: &[VisFlowLogEntry]) -> Vec<GraphViolation> {
+    violations(entries)
+        .into_iter()
+        .map(|violation| GraphViolation::new(violation.error, violation.at, violation.open_blocks))
+        .collect()
+}
+
+/**
+The rules of a valid trace, checked in the order of the entries. Keeps going after a violation. <br>
+`generate_graph` fails with the first violation, so the rules only live here.
+ */
+pub fn violations(entries: &[VisFlowLogEntry]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if entries.len() < 2 {
+        violations.push(Violation::new(
+            GraphError::NotEnoughEntries(entries.len()),
+            None,
+            vec![],
+        ));
+        return violations;
+    }
+    let first = &entries[0];
+    let root_span = first.span_id.as_deref();
+    let (last_index, last) = entries
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(_, entry)| entry.span_id.as_deref() == root_span)
+        .unwrap();
+    if first.block_name != last.block_name {
+        violations.push(Violation::new(
+            GraphError::UnbalancedBlock {
+                expected: first.block_name.clone(),
+                found: last.block_name.clone(),
+            },
+            Some((last_index, last.sequence)),
+            vec![],
+        ));
+    }
+
+    //Open blocks of every span, and the spans in the order they first appear
+    let mut stacks: HashMap<Option<&str>, Vec<OpenBlock>> = HashMap::new();
+    let mut span_order = vec![root_span];
+    stacks.insert(root_span, vec![OpenBlock::new(&first.block_name, false)]);
+    for (index, entry) in entries.iter().enumerate().skip(1) {
+        let span_id = entry.span_id.as_deref();
+        if let Entry::Vacant(stack) = stacks.entry(span_id) {
+            stack.insert(Vec::new());
+            span_order.push(span_id);
+        }
+        let at = Some((index, entry.sequence));
+        if stacks[&span_id].is_empty() {
+            //Entries after the end of the starting block are ignored
+            if span_id == root_span {
+                continue;
+            }
+            if entry.log_type != VisFlowLogEntryLogType::Start {
+                let error = GraphError::EntryOutsideBlock {
+                    block_name: entry.block_name.clone(),
+                };
+                violations.push(Violation::new(error, at, vec![]));
+                continue;
+            }
+            //The block that starts the span gets a new flow, so a STORE can't follow its last call
+            let parent_span = entry.parent_span_id.as_deref().or(root_span);
+            if let Some(caller) = [parent_span, root_span]
+                .into_iter()
+                .find(|span_id| stacks.get(span_id).is_some_and(|stack| !stack.is_empty()))
+            {
+                stacks.get_mut(&caller).unwrap().last_mut().unwrap().latest = Latest::Flow;
+            }
+            stacks
+                .get_mut(&span_id)
+                .unwrap()
+                .push(OpenBlock::new(&entry.block_name, false));
+            continue;
+        }
+        let stack = stacks.get_mut(&span_id).unwrap();
+        let top = stack.last_mut().unwrap();
+        match entry.log_type {
+            VisFlowLogEntryLogType::Log
+            | VisFlowLogEntryLogType::Error
+            | VisFlowLogEntryLogType::Branch
+            | VisFlowLogEntryLogType::ExternalCall
+            | VisFlowLogEntryLogType::ExternalCallStore => top.latest = Latest::Flow,
+            VisFlowLogEntryLogType::Start => {
+                top.latest = Latest::Flow;
+                stack.push(OpenBlock::new(&entry.block_name, false));
+            }
+            VisFlowLogEntryLogType::LoopStart => {
+                top.latest = Latest::Flow;
+                stack.push(OpenBlock::new(entry.loop_label(), true));
+            }
+            VisFlowLogEntryLogType::Store => {
+                if top.latest != Latest::Call {
+                    let error = GraphError::StoreWithoutCall {
+                        block_name: top.name.to_string(),
+                    };
+                    violations.push(Violation::new(error, at, names(stack)));
+                } else {
+                    top.latest = Latest::Flow;
+                }
+            }
+            VisFlowLogEntryLogType::LoopIter => {
+                if top.is_loop {
+                    top.latest = Latest::Flow;
+                } else {
+                    let error = GraphError::NotInLoop {
+                        block_name: entry.block_name.clone(),
+                    };
+                    violations.push(Violation::new(error, at, names(stack)));
+                }
+            }
+            VisFlowLogEntryLogType::LoopEnd => {
+                let closed = if top.is_loop {
+                    Some(stack.len() - 1)
+                } else {
+                    let error = GraphError::NotInLoop {
+                        block_name: entry.block_name.clone(),
+                    };
+                    violations.push(Violation::new(error, at, names(stack)));
+                    stack.iter().rposition(|block| block.is_loop)
+                };
+                if let Some(position) = closed {
+                    stack.truncate(position);
+                    //A loop returns nothing to store
+                    if let Some(caller) = stack.last_mut() {
+                        caller.latest = Latest::Flow;
+                    }
+                }
+            }
+            VisFlowLogEntryLogType::End | VisFlowLogEntryLogType::Throw => {
+                let closed = if !top.is_loop && top.name == entry.block_name {
+                    Some(stack.len() - 1)
+                } else {
+                    let error = if top.is_loop {
+                        GraphError::UnclosedLoop {
+                            label: top.name.to_string(),
+                        }
+                    } else {
+                        GraphError::UnbalancedBlock {
+                            expected: top.name.to_string(),
+                            found: entry.block_name.clone(),
+                        }
+                    };
+                    violations.push(Violation::new(error, at, names(stack)));
+                    //Recover by treating blocks inside the one being ended as never ended
+                    stack
+                        .iter()
+                        .rposition(|block| !block.is_loop && block.name == entry.block_name)
+                };
+                if let Some(position) = closed {
+                    //Blocks inside it end without an error, so only the innermost one can pass one on
+                    let threw = entry.log_type == VisFlowLogEntryLogType::Throw
+                        || (position == stack.len() - 1
+                            && stack[position].latest == Latest::FailedCall);
+                    stack.truncate(position);
+                    //A block that threw returns nothing to store
+                    if let Some(caller) = stack.last_mut() {
+                        caller.latest = if threw {
+                            Latest::FailedCall
+                        } else {
+                            Latest::Call
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    let open_blocks: Vec<String> = span_order
+        .iter()
+        .flat_map(|span_id| names(&stacks[span_id]))
+        .collect();
+    if !open_blocks.is_empty() {
+        violations.push(Violation::new(
+            GraphError::UnclosedBlocks(open_blocks.clone()),
+            None,
+            open_blocks,
+        ));
+    }
+    violations
+}
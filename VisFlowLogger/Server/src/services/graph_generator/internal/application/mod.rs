@@ -1 +1,3 @@
-pub mod graph_generator_impl;
\ No newline at end of file
+pub mod graph_generator_impl;
+pub mod graph_transform;
+pub mod graph_validator;
@@ -1,5 +1,8 @@
-use crate::services::graph_generator::api::models::vis_flow_log_entry::{VisFlowLogEntry, VisFlowLogEntryLogType};
+use crate::services::graph_generator::api::models::vis_flow_log_entry::{
+    VisFlowLogEntry, VisFlowLogEntryLogType,
+};
 use crate::services::graph_generator::api::services::graph_generator::GraphGenerator;
+use crate::services::graph_generator::test_fixture::entry;
 #[test]
 fn graph_generator_test() {
     let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
@@ -9,85 +12,130 @@ fn graph_generator_test() {
             log_type: VisFlowLogEntryLogType::Start,
             log_value: None,
             block_name: "main".parse().unwrap(),
+            sequence: 0,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
         // Main logs adding 2 and 1
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::Log,
             log_value: Some("Adding 2 and 1".to_string()),
             block_name: "main".parse().unwrap(),
+            sequence: 1,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
-
         // Main starts sum for the first time
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::Start,
             log_value: None,
             block_name: "sum".parse().unwrap(),
+            sequence: 2,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
         // Sum logs the calculation of 2 + 1 = 3
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::Log,
             log_value: Some("2 + 1 = 3".to_string()),
             block_name: "sum".parse().unwrap(),
+            sequence: 3,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
-
         // Sum recursively calls sum
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::Start,
             log_value: None,
             block_name: "sum".parse().unwrap(),
+            sequence: 4,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
         // Recursive sum logs the calculation of 1 + 1 = 2
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::Log,
             log_value: Some("1 + 1 = 2".to_string()),
             block_name: "sum".parse().unwrap(),
+            sequence: 5,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
-
         // Recursive sum ends
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::End,
             log_value: None,
             block_name: "sum".parse().unwrap(),
+            sequence: 6,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
-
         // Sum ends after returning from the recursive call
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::End,
             log_value: None,
             block_name: "sum".parse().unwrap(),
+            sequence: 7,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
-
         // Main stores the result of sum
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::Store,
             log_value: Some("sum = 3".to_string()),
             block_name: "main".parse().unwrap(),
+            sequence: 8,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
-
         // Main calls foo
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::Start,
             log_value: None,
             block_name: "foo".parse().unwrap(),
+            sequence: 9,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
         // Foo logs its calculation
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::Log,
             log_value: Some("foo called".to_string()),
             block_name: "foo".parse().unwrap(),
+            sequence: 10,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
         // Foo ends
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::End,
             log_value: None,
             block_name: "foo".parse().unwrap(),
+            sequence: 11,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
-
         // Main ends
         VisFlowLogEntry {
             log_type: VisFlowLogEntryLogType::End,
             log_value: None,
             block_name: "main".parse().unwrap(),
+            sequence: 12,
+            span_id: None,
+            parent_span_id: None,
+            timestamp: None,
         },
     ];
     /*
@@ -165,3 +213,775 @@ fn graph_generator_test() {
         println!("{} : {}", k, v);
     })
 }
+
+#[test]
+fn graph_generator_reports_typed_errors() {
+    use crate::services::graph_generator::api::models::graph_error::GraphError;
+    use VisFlowLogEntryLogType::{End, Start, Store};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+
+    let error = graph_gen
+        .generate_graph(vec![entry(Start, "main")])
+        .unwrap_err();
+    assert_eq!(error, GraphError::NotEnoughEntries(1));
+
+    let error = graph_gen
+        .generate_graph(vec![
+            entry(Start, "main"),
+            entry(Start, "foo"),
+            entry(End, "bar"),
+            entry(End, "main"),
+        ])
+        .unwrap_err();
+    assert_eq!(error.code(), "UnbalancedBlock");
+    assert_eq!(
+        error,
+        GraphError::UnbalancedBlock {
+            expected: "foo".to_string(),
+            found: "bar".to_string()
+        }
+    );
+
+    let error = graph_gen
+        .generate_graph(vec![
+            entry(Start, "main"),
+            entry(Store, "main"),
+            entry(End, "main"),
+        ])
+        .unwrap_err();
+    assert_eq!(error.code(), "StoreWithoutCall");
+
+    let error = graph_gen
+        .generate_graph(vec![
+            entry(Start, "main"),
+            entry(Start, "foo"),
+            entry(Start, "main"),
+        ])
+        .unwrap_err();
+    assert_eq!(
+        error,
+        GraphError::UnclosedBlocks(vec![
+            "main".to_string(),
+            "foo".to_string(),
+            "main".to_string()
+        ])
+    );
+}
+
+//Sequences are spaced out so they can't be confused with indexes
+fn trace(entries: &[(VisFlowLogEntryLogType, &str)]) -> Vec<VisFlowLogEntry> {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, (log_type, block_name))| VisFlowLogEntry {
+            sequence: index as u32 * 10,
+            ..entry(*log_type, block_name)
+        })
+        .collect()
+}
+
+#[test]
+fn validation_reports_every_violation_with_position_and_stack() {
+    use VisFlowLogEntryLogType::{End, Log, Start, Store};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let entries = trace(&[
+        (Start, "main"),
+        (Store, "main"),
+        (Start, "foo"),
+        (Start, "bar"),
+        (Log, "bar"),
+        (End, "foo"),
+        (End, "main"),
+    ]);
+
+    let violations = graph_gen.validate(&entries);
+    let summary: Vec<_> = violations
+        .iter()
+        .map(|v| (v.code, v.index, v.sequence))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("StoreWithoutCall", Some(1), Some(10)),
+            ("UnbalancedBlock", Some(5), Some(50)),
+        ]
+    );
+    let unbalanced = &violations[1];
+    assert_eq!(unbalanced.expected.as_deref(), Some("bar"));
+    assert_eq!(unbalanced.actual.as_deref(), Some("foo"));
+    assert_eq!(unbalanced.open_blocks, vec!["main", "foo", "bar"]);
+
+    let unclosed = graph_gen.validate(&trace(&[(Start, "main"), (Start, "foo"), (Log, "main")]));
+    assert_eq!(unclosed.last().unwrap().code, "UnclosedBlocks");
+    assert_eq!(unclosed.last().unwrap().open_blocks, vec!["main", "foo"]);
+}
+
+#[test]
+fn validation_agrees_with_graph_generation() {
+    use VisFlowLogEntryLogType::{End, ExternalCall, Log, Start, Store};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let traces = vec![
+        vec![(Start, "main"), (End, "main")],
+        vec![
+            (Start, "main"),
+            (Start, "foo"),
+            (End, "foo"),
+            (Store, "main"),
+            (End, "main"),
+        ],
+        vec![
+            (Start, "main"),
+            (ExternalCall, "main"),
+            (Store, "main"),
+            (End, "main"),
+        ],
+        vec![
+            (Start, "main"),
+            (Start, "foo"),
+            (End, "foo"),
+            (Store, "main"),
+            (Store, "main"),
+            (End, "main"),
+        ],
+        vec![(Start, "main"), (Start, "foo"), (End, "main")],
+        vec![(Start, "main"), (Log, "main")],
+        vec![(Start, "main"), (Start, "foo"), (End, "foo")],
+        vec![(Start, "main")],
+    ];
+    for entries in traces {
+        let violations = graph_gen.validate(&trace(&entries));
+        match graph_gen.generate_graph(trace(&entries)) {
+            Ok(_) => assert!(violations.is_empty(), "{:?}", violations),
+            Err(error) => assert_eq!(violations.first().map(|v| v.code), Some(error.code())),
+        }
+    }
+}
+
+///Rule 1 covers the starting block too, which traces without a final END used to get away with
+#[test]
+fn strict_graph_rejects_a_starting_block_that_never_ends() {
+    use crate::services::graph_generator::api::models::graph_error::GraphError;
+    use VisFlowLogEntryLogType::{Log, Start};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let entries = || trace(&[(Start, "main"), (Log, "main")]);
+
+    let error = graph_gen.generate_graph(entries()).unwrap_err();
+    assert_eq!(error, GraphError::UnclosedBlocks(vec!["main".to_string()]));
+    let graph = graph_gen.generate_lenient_graph(entries()).unwrap();
+    assert!(graph["START"].aborted);
+}
+
+#[test]
+fn lenient_graph_repairs_broken_traces() {
+    use crate::services::graph_generator::api::models::vis_flow::{GGBlock, GGBlockFlowType};
+    use std::collections::HashMap;
+    use VisFlowLogEntryLogType::{End, Log, Start, Store};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let block = |graph: &HashMap<String, GGBlock>, name: &str| -> (String, bool) {
+        let (id, block) = graph.iter().find(|(_, b)| b.name == name).unwrap();
+        (id.clone(), block.aborted)
+    };
+
+    //"bar" never ends, the END of "foo" ends it
+    let broken = [
+        (Start, "main"),
+        (Start, "foo"),
+        (Start, "bar"),
+        (Log, "bar"),
+        (End, "foo"),
+        (Store, "main"),
+        (End, "main"),
+    ];
+    assert!(graph_gen.generate_graph(trace(&broken)).is_err());
+    let graph = graph_gen.generate_lenient_graph(trace(&broken)).unwrap();
+    assert_eq!(graph.len(), 3);
+    let (foo_id, foo_aborted) = block(&graph, "foo");
+    let (bar_id, bar_aborted) = block(&graph, "bar");
+    assert!(!foo_aborted && bar_aborted && !graph["START"].aborted);
+    assert_eq!(
+        graph[&foo_id].flow[0].flow_pointer_id.as_ref(),
+        Some(&bar_id)
+    );
+    assert_eq!(graph["START"].flow[0].flow_type, GGBlockFlowType::CallStore);
+
+    //Orphan STORE, an END of a block that isn't open and a trace cut off before any END
+    let graph = graph_gen
+        .generate_lenient_graph(trace(&[
+            (Start, "main"),
+            (Store, "main"),
+            (End, "baz"),
+            (Start, "foo"),
+            (Log, "foo"),
+        ]))
+        .unwrap();
+    let (foo_id, foo_aborted) = block(&graph, "foo");
+    assert!(foo_aborted && graph["START"].aborted);
+    let root_flow: Vec<_> = graph["START"].flow.iter().map(|f| &f.flow_type).collect();
+    assert_eq!(
+        root_flow,
+        vec![&GGBlockFlowType::Store, &GGBlockFlowType::Call]
+    );
+    assert_eq!(
+        graph["START"].flow[1].flow_pointer_id.as_ref(),
+        Some(&foo_id)
+    );
+
+    let graph = graph_gen
+        .generate_lenient_graph(trace(&[(Start, "main")]))
+        .unwrap();
+    assert!(graph["START"].aborted);
+    assert!(graph_gen.generate_lenient_graph(vec![]).is_err());
+}
+
+fn spans(
+    entries: &[(VisFlowLogEntryLogType, &str, Option<&str>, Option<&str>)],
+) -> Vec<VisFlowLogEntry> {
+    entries
+        .iter()
+        .enumerate()
+        .map(
+            |(index, (log_type, block_name, span_id, parent_span_id))| VisFlowLogEntry {
+                sequence: index as u32,
+                span_id: span_id.map(str::to_string),
+                parent_span_id: parent_span_id.map(str::to_string),
+                timestamp: None,
+                ..entry(*log_type, block_name)
+            },
+        )
+        .collect()
+}
+
+#[test]
+fn interleaved_spans_build_one_tree() {
+    use crate::services::graph_generator::api::models::graph_error::GraphError;
+    use crate::services::graph_generator::api::models::vis_flow::GGBlockFlowType;
+    use VisFlowLogEntryLogType::{End, Log, Start, Store};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    //"main" spawns two tasks running "fetch" at the same time, the second one from inside "load"
+    let entries = spans(&[
+        (Start, "main", None, None),
+        (Start, "fetch", Some("a"), None),
+        (Start, "load", None, None),
+        (Start, "fetch", Some("b"), None),
+        (Log, "fetch", Some("a"), None),
+        (Start, "parse", Some("b"), Some("a")),
+        (End, "fetch", Some("a"), None),
+        (End, "parse", Some("b"), None),
+        (Store, "fetch", Some("b"), None),
+        (End, "fetch", Some("b"), None),
+        (End, "load", None, None),
+        (End, "main", None, None),
+    ]);
+    let graph = graph_gen.generate_graph(entries).unwrap();
+    assert_eq!(graph.len(), 5);
+
+    let main = &graph["START"];
+    let main_flow: Vec<_> = main.flow.iter().map(|f| &f.flow_type).collect();
+    assert_eq!(
+        main_flow,
+        vec![&GGBlockFlowType::AsyncCall, &GGBlockFlowType::Call]
+    );
+    let fetch_a = &graph[main.flow[0].flow_pointer_id.as_ref().unwrap()];
+    assert_eq!(
+        (fetch_a.name.as_str(), fetch_a.span_id.as_deref()),
+        ("fetch", Some("a"))
+    );
+    assert_eq!(fetch_a.caller.as_deref(), Some("START"));
+    assert_eq!(
+        fetch_a.flow.len(),
+        1,
+        "the task's log stays in its own block"
+    );
+
+    //Without a parent span, "b" was started from the block open in the first span
+    let load_id = main.flow[1].flow_pointer_id.as_ref().unwrap();
+    let load = &graph[load_id];
+    assert_eq!(load.flow[0].flow_type, GGBlockFlowType::AsyncCall);
+    let fetch_b = &graph[load.flow[0].flow_pointer_id.as_ref().unwrap()];
+    assert_eq!(fetch_b.caller.as_ref(), Some(load_id));
+    assert_eq!(fetch_b.flow[0].flow_type, GGBlockFlowType::CallStore);
+    let parse = &graph[fetch_b.flow[0].flow_pointer_id.as_ref().unwrap()];
+    assert_eq!(parse.span_id.as_deref(), Some("b"));
+
+    let error = graph_gen
+        .generate_graph(spans(&[
+            (Start, "main", None, None),
+            (Log, "fetch", Some("a"), None),
+            (End, "main", None, None),
+        ]))
+        .unwrap_err();
+    assert_eq!(
+        error,
+        GraphError::EntryOutsideBlock {
+            block_name: "fetch".to_string()
+        }
+    );
+
+    let error = graph_gen
+        .generate_graph(spans(&[
+            (Start, "main", None, None),
+            (Start, "fetch", Some("a"), None),
+            (End, "main", None, None),
+        ]))
+        .unwrap_err();
+    assert_eq!(error, GraphError::UnclosedBlocks(vec!["fetch".to_string()]));
+    let graph = graph_gen
+        .generate_lenient_graph(spans(&[
+            (Start, "main", None, None),
+            (Start, "fetch", Some("a"), None),
+            (End, "main", None, None),
+        ]))
+        .unwrap();
+    assert!(graph
+        .values()
+        .any(|block| block.name == "fetch" && block.aborted));
+}
+
+#[test]
+fn span_validation_agrees_with_graph_generation() {
+    use VisFlowLogEntryLogType::{End, Log, Start, Store};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let traces = vec![
+        vec![
+            (Start, "main", None, None),
+            (Start, "a", Some("t"), None),
+            (End, "main", None, None),
+            (End, "a", Some("t"), None),
+        ],
+        vec![
+            (Start, "main", None, None),
+            (Log, "a", Some("t"), None),
+            (End, "main", None, None),
+        ],
+        vec![
+            (Start, "main", None, None),
+            (Start, "a", Some("t"), None),
+            (End, "main", None, None),
+        ],
+        vec![
+            (Start, "main", None, None),
+            (Start, "foo", None, None),
+            (End, "foo", None, None),
+            (Start, "a", Some("t"), None),
+            (Store, "main", None, None),
+            (End, "a", Some("t"), None),
+            (End, "main", None, None),
+        ],
+        vec![
+            (Start, "main", None, None),
+            (Start, "a", Some("t"), None),
+            (End, "b", Some("t"), None),
+            (End, "a", Some("t"), None),
+            (End, "main", None, None),
+        ],
+    ];
+    for entries in traces {
+        let violations = graph_gen.validate(&spans(&entries));
+        match graph_gen.generate_graph(spans(&entries)) {
+            Ok(_) => assert!(violations.is_empty(), "{:?}", violations),
+            Err(error) => assert_eq!(violations.first().map(|v| v.code), Some(error.code())),
+        }
+    }
+}
+
+#[test]
+fn thrown_errors_fail_the_block_and_reach_the_caller() {
+    use crate::services::graph_generator::api::models::vis_flow::GGBlockFlowType;
+    use VisFlowLogEntryLogType::{End, Error, Start, Store, Throw};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let mut entries = trace(&[
+        (Start, "main"),
+        (Start, "load"),
+        (Throw, "load"),
+        (Start, "fallback"),
+        (Error, "fallback"),
+        (End, "fallback"),
+        (Store, "main"),
+        (End, "main"),
+    ]);
+    entries[2].log_value = Some("file not found".to_string());
+    let graph = graph_gen.generate_graph(entries).unwrap();
+
+    let main = &graph["START"];
+    assert!(!main.failed, "the caller handled the error");
+    let thrown = &main.flow[0];
+    assert_eq!(thrown.flow_type, GGBlockFlowType::CallError);
+    assert_eq!(thrown.value.as_deref(), Some("file not found"));
+    assert!(graph[thrown.flow_pointer_id.as_ref().unwrap()].failed);
+    assert_eq!(main.flow[1].flow_type, GGBlockFlowType::CallStore);
+    let fallback = &graph[main.flow[1].flow_pointer_id.as_ref().unwrap()];
+    assert!(fallback.failed);
+    assert_eq!(fallback.flow[0].flow_type, GGBlockFlowType::Error);
+
+    //Nothing is returned from a block that threw
+    let error = graph_gen
+        .generate_graph(trace(&[
+            (Start, "main"),
+            (Start, "load"),
+            (Throw, "load"),
+            (Store, "main"),
+            (End, "main"),
+        ]))
+        .unwrap_err();
+    assert_eq!(error.code(), "StoreWithoutCall");
+
+    //An error thrown out of the starting block ends the trace
+    let graph = graph_gen
+        .generate_graph(trace(&[(Start, "main"), (Throw, "main")]))
+        .unwrap();
+    assert!(graph["START"].failed);
+}
+
+#[test]
+fn thrown_errors_pass_through_callers_until_one_handles_them() {
+    use crate::services::graph_generator::api::models::vis_flow::GGBlockFlowType;
+    use VisFlowLogEntryLogType::{End, Log, Start, Throw};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let mut entries = trace(&[
+        (Start, "main"),
+        (Start, "load"),
+        (Start, "read"),
+        (Throw, "read"),
+        (End, "load"),
+        (Log, "main"),
+        (End, "main"),
+    ]);
+    entries[3].log_value = Some("file not found".to_string());
+    let graph = graph_gen.generate_graph(entries).unwrap();
+
+    let main = &graph["START"];
+    assert!(
+        !main.failed,
+        "main logged something after the call, so it handled the error"
+    );
+    let load_call = &main.flow[0];
+    assert_eq!(load_call.flow_type, GGBlockFlowType::CallError);
+    assert_eq!(load_call.value.as_deref(), Some("file not found"));
+    let load = &graph[load_call.flow_pointer_id.as_ref().unwrap()];
+    assert!(load.failed, "load ended right after read threw");
+    assert_eq!(load.flow[0].flow_type, GGBlockFlowType::CallError);
+    assert!(graph[load.flow[0].flow_pointer_id.as_ref().unwrap()].failed);
+}
+
+#[test]
+fn thrown_error_validation_agrees_with_graph_generation() {
+    use VisFlowLogEntryLogType::{End, Error, Log, Start, Store, Throw};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let traces = vec![
+        vec![(Start, "main"), (Throw, "main")],
+        vec![
+            (Start, "main"),
+            (Start, "foo"),
+            (Throw, "foo"),
+            (Store, "main"),
+            (End, "main"),
+        ],
+        vec![
+            (Start, "main"),
+            (Start, "foo"),
+            (Throw, "bar"),
+            (End, "main"),
+        ],
+        vec![(Start, "main"), (Error, "main"), (End, "main")],
+        vec![(Start, "main"), (Start, "foo"), (Throw, "foo")],
+        vec![
+            (Start, "main"),
+            (Start, "foo"),
+            (Start, "bar"),
+            (Throw, "bar"),
+            (End, "foo"),
+            (Store, "main"),
+            (End, "main"),
+        ],
+        vec![
+            (Start, "main"),
+            (Start, "foo"),
+            (Start, "bar"),
+            (Throw, "bar"),
+            (Log, "foo"),
+            (End, "foo"),
+            (Store, "main"),
+            (End, "main"),
+        ],
+    ];
+    for entries in traces {
+        let violations = graph_gen.validate(&trace(&entries));
+        match graph_gen.generate_graph(trace(&entries)) {
+            Ok(_) => assert!(violations.is_empty(), "{:?}", violations),
+            Err(error) => assert_eq!(violations.first().map(|v| v.code), Some(error.code())),
+        }
+    }
+}
+
+#[test]
+fn loops_become_blocks_called_with_their_iteration_count() {
+    use crate::services::graph_generator::api::models::graph_error::GraphError;
+    use crate::services::graph_generator::api::models::vis_flow::{GGBlockFlowType, GGBlockKind};
+    use VisFlowLogEntryLogType::{Branch, End, LoopEnd, LoopIter, LoopStart, Start, Store};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let mut entries = trace(&[
+        (Start, "main"),
+        (Branch, "main"),
+        (LoopStart, "main"),
+        (LoopIter, "main"),
+        (Start, "fetch"),
+        (End, "fetch"),
+        (Store, "main"),
+        (LoopIter, "main"),
+        (Start, "fetch"),
+        (End, "fetch"),
+        (LoopEnd, "main"),
+        (End, "main"),
+    ]);
+    entries[1].log_value = Some("cache miss".to_string());
+    entries[2].log_value = Some("pages".to_string());
+    let graph = graph_gen.generate_graph(entries).unwrap();
+
+    let main = &graph["START"];
+    assert_eq!(main.flow[0].flow_type, GGBlockFlowType::Branch);
+    assert_eq!(main.flow[0].value.as_deref(), Some("cache miss"));
+    let called_loop = &main.flow[1];
+    assert_eq!(called_loop.flow_type, GGBlockFlowType::Loop);
+    assert_eq!(called_loop.value.as_deref(), Some("2"));
+    let pages = &graph[called_loop.flow_pointer_id.as_ref().unwrap()];
+    assert_eq!(
+        (pages.name.as_str(), pages.kind),
+        ("pages", GGBlockKind::Loop)
+    );
+    let flow_types: Vec<&GGBlockFlowType> = pages.flow.iter().map(|f| &f.flow_type).collect();
+    assert_eq!(
+        flow_types,
+        vec![
+            &GGBlockFlowType::LoopIteration,
+            &GGBlockFlowType::CallStore,
+            &GGBlockFlowType::LoopIteration,
+            &GGBlockFlowType::Call,
+        ]
+    );
+    assert_eq!(graph.len(), 4);
+
+    let error = graph_gen
+        .generate_graph(trace(&[(Start, "main"), (LoopIter, "main"), (End, "main")]))
+        .unwrap_err();
+    assert_eq!(
+        error,
+        GraphError::NotInLoop {
+            block_name: "main".to_string()
+        }
+    );
+    let error = graph_gen
+        .generate_graph(trace(&[
+            (Start, "main"),
+            (LoopStart, "main"),
+            (End, "main"),
+        ]))
+        .unwrap_err();
+    assert_eq!(
+        error,
+        GraphError::UnclosedLoop {
+            label: "loop".to_string()
+        }
+    );
+}
+
+#[test]
+fn lenient_graph_closes_loops_left_running() {
+    use VisFlowLogEntryLogType::{End, LoopEnd, LoopIter, LoopStart, Start};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let graph = graph_gen
+        .generate_lenient_graph(trace(&[
+            (Start, "main"),
+            (LoopStart, "main"),
+            (LoopIter, "main"),
+            (Start, "fetch"),
+            (End, "main"),
+            (LoopEnd, "main"),
+        ]))
+        .unwrap();
+    let aborted: Vec<&str> = graph
+        .values()
+        .filter(|b| b.aborted)
+        .map(|b| b.name.as_str())
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    assert_eq!(aborted, vec!["fetch", "loop"]);
+}
+
+#[test]
+fn loop_validation_agrees_with_graph_generation() {
+    use VisFlowLogEntryLogType::{Branch, End, LoopEnd, LoopIter, LoopStart, Start, Store};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let traces = vec![
+        vec![
+            (Start, "main"),
+            (LoopStart, "main"),
+            (LoopIter, "main"),
+            (LoopEnd, "main"),
+            (End, "main"),
+        ],
+        vec![
+            (Start, "main"),
+            (LoopStart, "main"),
+            (LoopEnd, "main"),
+            (Store, "main"),
+            (End, "main"),
+        ],
+        vec![
+            (Start, "main"),
+            (Branch, "main"),
+            (LoopIter, "main"),
+            (End, "main"),
+        ],
+        vec![(Start, "main"), (LoopEnd, "main"), (End, "main")],
+        vec![(Start, "main"), (LoopStart, "main"), (End, "main")],
+        vec![
+            (Start, "main"),
+            (LoopStart, "main"),
+            (Start, "foo"),
+            (LoopEnd, "main"),
+            (End, "main"),
+        ],
+        vec![(Start, "main"), (LoopStart, "main"), (LoopIter, "main")],
+    ];
+    for entries in traces {
+        let violations = graph_gen.validate(&trace(&entries));
+        match graph_gen.generate_graph(trace(&entries)) {
+            Ok(_) => assert!(violations.is_empty(), "{:?}", violations),
+            Err(error) => assert_eq!(violations.first().map(|v| v.code), Some(error.code())),
+        }
+    }
+}
+
+#[test]
+fn collapsed_loops_keep_the_first_iteration() {
+    use crate::services::graph_generator::api::models::vis_flow::GGBlockFlowType;
+    use VisFlowLogEntryLogType::{End, LoopEnd, LoopIter, LoopStart, Start};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let mut graph = graph_gen
+        .generate_graph(trace(&[
+            (Start, "main"),
+            (LoopStart, "main"),
+            (LoopIter, "main"),
+            (Start, "fetch"),
+            (End, "fetch"),
+            (LoopIter, "main"),
+            (Start, "fetch"),
+            (Start, "parse"),
+            (End, "parse"),
+            (End, "fetch"),
+            (LoopIter, "main"),
+            (LoopEnd, "main"),
+            (End, "main"),
+        ]))
+        .unwrap();
+    assert_eq!(graph.len(), 5);
+    graph_gen.collapse_loops(&mut graph);
+
+    let names: std::collections::BTreeSet<&str> = graph.values().map(|b| b.name.as_str()).collect();
+    assert_eq!(graph.len(), 3);
+    assert_eq!(names, ["fetch", "loop", "main"].into_iter().collect());
+    let called_loop = &graph["START"].flow[0];
+    assert_eq!(
+        called_loop.value.as_deref(),
+        Some("3"),
+        "the count covers every iteration"
+    );
+    let flow_types: Vec<&GGBlockFlowType> = graph[called_loop.flow_pointer_id.as_ref().unwrap()]
+        .flow
+        .iter()
+        .map(|f| &f.flow_type)
+        .collect();
+    assert_eq!(
+        flow_types,
+        vec![&GGBlockFlowType::LoopIteration, &GGBlockFlowType::Call]
+    );
+}
+
+#[test]
+fn blocks_with_times_get_inclusive_and_exclusive_durations() {
+    use crate::services::graph_generator::api::models::vis_flow::GGBlockDuration;
+    use VisFlowLogEntryLogType::{End, LoopEnd, LoopIter, LoopStart, Start};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let at_ms = [0, 10, 15, 40, 50, 55, 70, 100];
+    let mut entries = trace(&[
+        (Start, "main"),
+        (Start, "load"),
+        (End, "load"),
+        (LoopStart, "main"),
+        (LoopIter, "main"),
+        (Start, "fetch"),
+        (LoopEnd, "main"),
+        (End, "main"),
+    ]);
+    for (entry, ms) in entries.iter_mut().zip(at_ms) {
+        entry.timestamp = chrono::DateTime::from_timestamp_millis(1_700_000_000_000 + ms);
+    }
+    //"fetch" ends without a time, so it has no duration
+    entries.insert(
+        6,
+        VisFlowLogEntry {
+            timestamp: None,
+            ..entry(End, "fetch")
+        },
+    );
+    let graph = graph_gen.generate_graph(entries).unwrap();
+
+    let duration = |name: &str| graph.values().find(|b| b.name == name).unwrap().duration;
+    let ms = |inclusive: u64, exclusive: u64| {
+        Some(GGBlockDuration {
+            inclusive_us: inclusive * 1000,
+            exclusive_us: exclusive * 1000,
+        })
+    };
+    assert_eq!(duration("main"), ms(100, 65));
+    assert_eq!(duration("load"), ms(5, 5));
+    assert_eq!(
+        duration("loop"),
+        ms(30, 30),
+        "fetch has no end time to subtract"
+    );
+    assert_eq!(duration("fetch"), None);
+    assert!(graph["START"].started_at.is_some());
+}
+
+#[test]
+fn ids_come_from_entry_sequences() {
+    use VisFlowLogEntryLogType::{End, Log, Start};
+    let graph_gen = crate::services::graph_generator::api::services::graph_generator::new();
+    let entries = || {
+        trace(&[
+            (Start, "main"),
+            (Start, "load"),
+            (Log, "load"),
+            (End, "load"),
+            (End, "main"),
+        ])
+    };
+    let graph = graph_gen.generate_graph(entries()).unwrap();
+    let mut keys: Vec<&String> = graph.keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["START", "b10"]);
+    assert_eq!(graph["b10"].flow[0].flow_id, "f20");
+    assert_eq!(graph["START"].flow[0].flow_id, "b10_call");
+    assert_eq!(
+        graph["START"].flow[0].flow_pointer_id.as_deref(),
+        Some("b10")
+    );
+    assert_eq!(graph, graph_gen.generate_graph(entries()).unwrap());
+
+    //Repeated sequences are told apart by position
+    let graph = graph_gen
+        .generate_graph(vec![
+            entry(Start, "main"),
+            entry(Start, "a"),
+            entry(End, "a"),
+            entry(Start, "b"),
+            entry(End, "b"),
+            entry(End, "main"),
+        ])
+        .unwrap();
+    let mut keys: Vec<&String> = graph.keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["START", "b0_1", "b0_3"]);
+}
@@ -3,6 +3,8 @@ use std::sync::Arc;
 
 pub mod api;
 mod internal;
+#[cfg(test)]
+pub(crate) mod test_fixture;
 
 #[derive(Clone)]
 pub struct AvailableServices {
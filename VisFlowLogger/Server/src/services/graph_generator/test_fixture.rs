@@ -0,0 +1,50 @@
+use crate::services::graph_generator::api::models::vis_flow::GGBlock;
+use crate::services::graph_generator::api::models::vis_flow_log_entry::{
+    VisFlowLogEntry, VisFlowLogEntryLogType,
+};
+use crate::services::graph_generator::api::services::graph_generator::GraphGenerator;
+use std::collections::HashMap;
+
+///Entry with an empty value, no span and no time
+pub(crate) fn entry(log_type: VisFlowLogEntryLogType, block_name: &str) -> VisFlowLogEntry {
+    VisFlowLogEntry {
+        log_type,
+        log_value: Some(String::new()),
+        block_name: block_name.to_string(),
+        sequence: 0,
+        span_id: None,
+        parent_span_id: None,
+        timestamp: None,
+    }
+}
+
+///Entries of a trace as `(log_type, block_name, log_value)`, numbered by their position
+pub(crate) fn entries(
+    entries: &[(VisFlowLogEntryLogType, &str, Option<&str>)],
+) -> Vec<VisFlowLogEntry> {
+    entries
+        .iter()
+        .enumerate()
+        .map(
+            |(index, (log_type, block_name, log_value))| VisFlowLogEntry {
+                log_value: log_value.map(str::to_string),
+                sequence: index as u32,
+                ..entry(*log_type, block_name)
+            },
+        )
+        .collect()
+}
+
+///Strict graph of the entries, which must describe a valid flow
+pub(crate) fn graph_of(entries: Vec<VisFlowLogEntry>) -> HashMap<String, GGBlock> {
+    crate::services::graph_generator::api::services::graph_generator::new()
+        .generate_graph(entries)
+        .unwrap()
+}
+
+///Strict graph of a trace given as in `entries`
+pub(crate) fn graph(
+    trace: &[(VisFlowLogEntryLogType, &str, Option<&str>)],
+) -> HashMap<String, GGBlock> {
+    graph_of(entries(trace))
+}
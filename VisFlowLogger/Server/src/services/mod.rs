@@ -1,3 +1,5 @@
 pub mod persistence;
 pub mod graph_generator;
 pub mod diagram_generator;
+pub mod graph_aggregator;
+pub mod graph_differ;
\ No newline at end of file
@@ -0,0 +1,21 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    /// None while the migration is pending.
+    pub applied_at: Option<NaiveDateTime>,
+}
+
+impl Display for MigrationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self.applied_at {
+            Some(applied_at) => format!("applied {}", applied_at.format("%Y-%m-%d %H:%M:%S")),
+            None => "pending".to_string(),
+        };
+        write!(f, "{:>4}  {:<40} {}", self.version, self.name, state)
+    }
+}
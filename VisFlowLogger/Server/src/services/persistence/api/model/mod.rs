@@ -1,2 +1,5 @@
+pub mod migration_model;
+pub mod persistence_error;
+pub mod vis_flow_ingest_model;
 pub mod vis_flow_log_model;
 pub mod vis_flow_op_model;
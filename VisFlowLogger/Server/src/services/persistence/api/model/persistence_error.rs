@@ -0,0 +1,80 @@
+use chrono::NaiveDateTime;
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum PersistenceError {
+    /// The database can't be reached, or no connection became free in time
+    DbUnavailable(String),
+    /// There are no logs for the operation ID
+    OperationNotFound(String),
+    /// No operation has the name, or none was created between `from` and `to`
+    NoOperationsNamed {
+        name: String,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    },
+    MigrationFailed {
+        version: i64,
+        name: String,
+        cause: String,
+    },
+    /// Any other database error
+    QueryFailed(String),
+}
+
+impl PersistenceError {
+    /// Machine-readable name of the error, stable across releases
+    pub fn code(&self) -> &'static str {
+        match self {
+            PersistenceError::DbUnavailable(_) => "DbUnavailable",
+            PersistenceError::OperationNotFound(_) => "OperationNotFound",
+            PersistenceError::NoOperationsNamed { .. } => "NoOperationsNamed",
+            PersistenceError::MigrationFailed { .. } => "MigrationFailed",
+            PersistenceError::QueryFailed(_) => "QueryFailed",
+        }
+    }
+
+    /// `context` says what was attempted, e.g. "Error fetching logs"
+    pub fn from_sqlx(context: &str, error: sqlx::Error) -> Self {
+        let message = format!("{}: {}", context, error);
+        match error {
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => PersistenceError::DbUnavailable(message),
+            _ => PersistenceError::QueryFailed(message),
+        }
+    }
+}
+
+impl Display for PersistenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PersistenceError::DbUnavailable(message) => {
+                write!(f, "Database unavailable. {}", message)
+            }
+            PersistenceError::OperationNotFound(operation_id) => {
+                write!(f, "No logs found for operation {}", operation_id)
+            }
+            PersistenceError::NoOperationsNamed { name, from, to } => {
+                write!(f, "No operations named {}", name)?;
+                if let Some(from) = from {
+                    write!(f, " created from {}", from)?;
+                }
+                if let Some(to) = to {
+                    write!(f, " created before {}", to)?;
+                }
+                Ok(())
+            }
+            PersistenceError::MigrationFailed {
+                version,
+                name,
+                cause,
+            } => write!(f, "Migration {} ({}) failed: {}", version, name, cause),
+            PersistenceError::QueryFailed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for PersistenceError {}
@@ -0,0 +1,28 @@
+use serde::Serialize;
+use std::fmt::Display;
+
+/// A log whose `(operation_id, sequence)` is already taken by a log with different content
+#[derive(Serialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct LogConflict {
+    pub operation_id: String,
+    pub sequence: u32,
+}
+
+impl Display for LogConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Log {} of operation {} was already saved with different content",
+            self.sequence, self.operation_id
+        )
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum IngestOutcome {
+    /// Everything was committed. `duplicates` were identical to logs that already existed.
+    Saved { inserted: usize, duplicates: usize },
+    /// Nothing was committed
+    Conflict { conflicts: Vec<LogConflict> },
+}
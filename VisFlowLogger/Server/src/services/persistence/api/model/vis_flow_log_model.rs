@@ -1,5 +1,7 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgRow;
+use sqlx::sqlite::SqliteRow;
 use sqlx::Row;
 use std::fmt::Display;
 #[derive(Serialize, Deserialize)]
@@ -9,8 +11,17 @@ pub struct VisFlowLogEntry {
     pub log_type: String,
     pub log_value: Option<String>,
     pub sequence: u32,
+    /// Task or thread the log was written from. Logs without it belong to the operation's first span.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    /// Span that started `span_id`. Missing means the operation's first span.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_span_id: Option<String>,
+    /// Time the client wrote the log. Used to measure how long blocks ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
 }
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct VisFlowLogEntity {
     pub id: String,
     pub operation_id: String,
@@ -18,6 +29,18 @@ pub struct VisFlowLogEntity {
     pub log_type: String,
     pub log_value: Option<String>,
     pub sequence: u32,
+    /// Task or thread the log was written from. Logs without it belong to the operation's first span.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    /// Span that started `span_id`. Missing means the operation's first span.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_span_id: Option<String>,
+    /// Time the client wrote the log. Used to measure how long blocks ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Time the server saved the log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<DateTime<Utc>>,
 }
 
 impl VisFlowLogEntity {
@@ -28,9 +51,33 @@ impl VisFlowLogEntity {
             block_name: row.get("block_name"),
             log_type: row.get("log_type"),
             log_value: Some(row.get("log_value")),
-            sequence: row.get::<i32, _>("sequence") as u32,
+            sequence: row.get::<i64, _>("sequence") as u32,
+            span_id: row.get("span_id"),
+            parent_span_id: row.get("parent_span_id"),
+            timestamp: utc(row.get("logged_at")),
+            received_at: utc(row.get("received_at")),
         }
     }
+
+    pub fn from_sqlite_row(row: &SqliteRow) -> VisFlowLogEntity {
+        VisFlowLogEntity {
+            id: row.get("id"),
+            operation_id: row.get("operation_id"),
+            block_name: row.get("block_name"),
+            log_type: row.get("log_type"),
+            log_value: row.get("log_value"),
+            sequence: row.get::<i64, _>("sequence") as u32,
+            span_id: row.get("span_id"),
+            parent_span_id: row.get("parent_span_id"),
+            timestamp: utc(row.get("logged_at")),
+            received_at: utc(row.get("received_at")),
+        }
+    }
+}
+
+///The time columns hold UTC without an offset
+fn utc(time: Option<NaiveDateTime>) -> Option<DateTime<Utc>> {
+    time.map(|time| time.and_utc())
 }
 
 impl Display for VisFlowLogEntity {
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::Row;
 use std::fmt::Display;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct VisFlowOperationEntity {
     pub operation_id: String,
     pub operation_name: String,
@@ -20,6 +20,15 @@ impl VisFlowOperationEntity {
             updated_at: row.get("updated"),
         }
     }
+
+    pub(crate) fn from_sqlite_row(row: &sqlx::sqlite::SqliteRow) -> Self {
+        VisFlowOperationEntity {
+            operation_id: row.get("id"),
+            operation_name: row.get("name"),
+            created_at: row.get("created"),
+            updated_at: row.get("updated"),
+        }
+    }
 }
 
 impl Display for VisFlowOperationEntity {
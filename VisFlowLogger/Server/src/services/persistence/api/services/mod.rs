@@ -1,2 +1,4 @@
+pub mod schema_migration;
+pub mod vis_flow_ingest;
 pub mod vis_flow_log;
 pub mod vis_flow_op;
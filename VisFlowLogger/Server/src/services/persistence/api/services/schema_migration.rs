@@ -0,0 +1,12 @@
+use crate::services::persistence::api::model::migration_model::MigrationStatus;
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait SchemaMigration: Send + Sync {
+    ///Every migration shipped with the server and whether it was applied, ordered by version
+    async fn status(&self) -> Result<Vec<MigrationStatus>, PersistenceError>;
+    ///Applies pending migrations in version order and returns them.
+    /// With `dry_run` nothing is executed and the pending migrations are returned as is.
+    async fn up(&self, dry_run: bool) -> Result<Vec<MigrationStatus>, PersistenceError>;
+}
@@ -0,0 +1,16 @@
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::model::vis_flow_ingest_model::IngestOutcome;
+use crate::services::persistence::api::model::vis_flow_log_model::VisFlowLogEntry;
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait VisFlowIngest: Send + Sync {
+    ///Upserts the operations (ID, Name) and saves the logs in one transaction.
+    /// A log is identified by its `(operation_id, sequence)`, so resending a log is a no-op,
+    /// while a different log under a taken key is a conflict and nothing is saved.
+    async fn ingest(
+        &self,
+        operations: &[(String, String)],
+        logs: &[&VisFlowLogEntry],
+    ) -> Result<IngestOutcome, PersistenceError>;
+}
@@ -1,14 +1,21 @@
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
 use crate::services::persistence::api::model::vis_flow_op_model::VisFlowOperationEntity;
-use crate::services::persistence::internal::application::vis_flow_op_impl::VisLogOpImpl;
 use async_trait::async_trait;
+use chrono::NaiveDateTime;
 
 #[async_trait]
 pub trait VisFlowOp: Send + Sync {
     ///If operation doesn't exist creates a new one. If exists, updates the updated timestamp
-    /// ID, Name
-    async fn upsert(&self, operation_id: Vec<(String, String)>) -> bool;
-    async fn get_operations(&self) -> Result<Vec<VisFlowOperationEntity>, String>;
-}
-pub async fn new() -> impl VisFlowOp {
-    VisLogOpImpl::new().await
+    /// ID, Name. Upserting nothing is a no-op
+    async fn upsert(&self, operation_id: Vec<(String, String)>) -> Result<(), PersistenceError>;
+    async fn get_operations(&self) -> Result<Vec<VisFlowOperationEntity>, PersistenceError>;
+    ///Operations with the name created at or after `from` and before `to`, newest first.
+    /// At most `limit` of them
+    async fn get_operations_named(
+        &self,
+        operation_name: &str,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: usize,
+    ) -> Result<Vec<VisFlowOperationEntity>, PersistenceError>;
 }
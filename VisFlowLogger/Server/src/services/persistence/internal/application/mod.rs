@@ -1,2 +1,12 @@
+pub mod schema_migration_impl;
+pub mod schema_migration_memory_impl;
+pub mod schema_migration_sqlite_impl;
+pub mod vis_flow_ingest_impl;
+pub mod vis_flow_ingest_memory_impl;
+pub mod vis_flow_ingest_sqlite_impl;
 pub mod vis_flow_log_impl;
+pub mod vis_flow_log_memory_impl;
+pub mod vis_flow_log_sqlite_impl;
 pub mod vis_flow_op_impl;
+pub mod vis_flow_op_memory_impl;
+pub mod vis_flow_op_sqlite_impl;
@@ -0,0 +1,111 @@
+use crate::services::persistence::api::model::migration_model::MigrationStatus;
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::services::schema_migration::SchemaMigration;
+use crate::services::persistence::internal::common::migration::{
+    pending, statuses, Migration, HISTORY_TABLE, POSTGRES_MIGRATIONS,
+};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Pool, Postgres, Row};
+use std::collections::HashMap;
+
+/// Serializes migrations between server instances starting at the same time.
+const MIGRATION_LOCK_ID: i64 = 0x5646_4c4d;
+
+pub struct SchemaMigrationImpl {
+    db: Pool<Postgres>,
+}
+
+impl SchemaMigrationImpl {
+    pub fn new(db: Pool<Postgres>) -> Self {
+        SchemaMigrationImpl { db }
+    }
+
+    /// Runs one migration and records it. Returns None when another instance applied it first.
+    async fn apply(&self, migration: &Migration) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(MIGRATION_LOCK_ID)
+            .execute(&mut *tx)
+            .await?;
+        // Another instance may have applied it while we waited for the lock
+        let already_applied = sqlx::query("SELECT 1 FROM schema_migrations WHERE version = $1")
+            .bind(migration.version)
+            .fetch_optional(&mut *tx)
+            .await?
+            .is_some();
+        if already_applied {
+            return Ok(None);
+        }
+        // Without bind arguments this uses the simple protocol, so a file may hold several statements
+        (&mut *tx).execute(migration.sql).await?;
+        let applied_at = chrono::Utc::now().naive_utc();
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES ($1, $2, $3)",
+        )
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(applied_at)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(applied_at))
+    }
+
+    async fn applied(&self) -> Result<HashMap<i64, NaiveDateTime>, sqlx::Error> {
+        sqlx::query(HISTORY_TABLE).execute(&self.db).await?;
+        let rows = sqlx::query("SELECT version, applied_at FROM schema_migrations")
+            .fetch_all(&self.db)
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("version"), row.get("applied_at")))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl SchemaMigration for SchemaMigrationImpl {
+    async fn status(&self) -> Result<Vec<MigrationStatus>, PersistenceError> {
+        let applied = self
+            .applied()
+            .await
+            .map_err(|e| PersistenceError::from_sqlx("Error reading migration history", e))?;
+        Ok(statuses(POSTGRES_MIGRATIONS, &applied))
+    }
+
+    async fn up(&self, dry_run: bool) -> Result<Vec<MigrationStatus>, PersistenceError> {
+        let applied = self
+            .applied()
+            .await
+            .map_err(|e| PersistenceError::from_sqlx("Error reading migration history", e))?;
+        let mut ran = Vec::new();
+        for migration in pending(POSTGRES_MIGRATIONS, &applied) {
+            if dry_run {
+                ran.push(MigrationStatus {
+                    version: migration.version,
+                    name: migration.name.to_string(),
+                    applied_at: None,
+                });
+                continue;
+            }
+            let result = self.apply(migration).await;
+            match result {
+                Ok(Some(applied_at)) => ran.push(MigrationStatus {
+                    version: migration.version,
+                    name: migration.name.to_string(),
+                    applied_at: Some(applied_at),
+                }),
+                Ok(None) => {}
+                Err(e) => {
+                    return Err(PersistenceError::MigrationFailed {
+                        version: migration.version,
+                        name: migration.name.to_string(),
+                        cause: e.to_string(),
+                    })
+                }
+            }
+        }
+        Ok(ran)
+    }
+}
@@ -0,0 +1,18 @@
+use crate::services::persistence::api::model::migration_model::MigrationStatus;
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::services::schema_migration::SchemaMigration;
+use async_trait::async_trait;
+
+/// The memory backend has no schema, so there is never anything to migrate.
+pub struct SchemaMigrationMemoryImpl;
+
+#[async_trait]
+impl SchemaMigration for SchemaMigrationMemoryImpl {
+    async fn status(&self) -> Result<Vec<MigrationStatus>, PersistenceError> {
+        Ok(vec![])
+    }
+
+    async fn up(&self, _dry_run: bool) -> Result<Vec<MigrationStatus>, PersistenceError> {
+        Ok(vec![])
+    }
+}
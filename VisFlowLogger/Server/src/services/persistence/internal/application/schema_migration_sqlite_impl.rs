@@ -0,0 +1,105 @@
+use crate::services::persistence::api::model::migration_model::MigrationStatus;
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::services::schema_migration::SchemaMigration;
+use crate::services::persistence::internal::common::migration::{
+    pending, statuses, Migration, HISTORY_TABLE, SQLITE_MIGRATIONS,
+};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, Executor, Pool, Row, Sqlite, SqliteConnection};
+use std::collections::HashMap;
+
+pub struct SchemaMigrationSqliteImpl {
+    db: Pool<Sqlite>,
+}
+
+impl SchemaMigrationSqliteImpl {
+    pub fn new(db: Pool<Sqlite>) -> Self {
+        SchemaMigrationSqliteImpl { db }
+    }
+
+    /// Migrations run on a single connection. A pooled connection that read the schema
+    /// earlier can keep an old WAL snapshot and miss tables or indexes created on another one.
+    async fn acquire(&self) -> Result<PoolConnection<Sqlite>, PersistenceError> {
+        self.db
+            .acquire()
+            .await
+            .map_err(|e| PersistenceError::from_sqlx("Error acquiring a connection", e))
+    }
+
+    /// SQLite takes a write lock for the whole transaction, so no extra locking is needed
+    async fn apply(
+        conn: &mut SqliteConnection,
+        migration: &Migration,
+        applied_at: NaiveDateTime,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = conn.begin().await?;
+        // Without bind arguments this uses the simple protocol, so a file may hold several statements
+        (&mut *tx).execute(migration.sql).await?;
+        sqlx::query("INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(applied_at)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    async fn applied(
+        conn: &mut SqliteConnection,
+    ) -> Result<HashMap<i64, NaiveDateTime>, sqlx::Error> {
+        sqlx::query(HISTORY_TABLE).execute(&mut *conn).await?;
+        let rows = sqlx::query("SELECT version, applied_at FROM schema_migrations")
+            .fetch_all(&mut *conn)
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("version"), row.get("applied_at")))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl SchemaMigration for SchemaMigrationSqliteImpl {
+    async fn status(&self) -> Result<Vec<MigrationStatus>, PersistenceError> {
+        let mut conn = self.acquire().await?;
+        let applied = Self::applied(&mut conn)
+            .await
+            .map_err(|e| PersistenceError::from_sqlx("Error reading migration history", e))?;
+        Ok(statuses(SQLITE_MIGRATIONS, &applied))
+    }
+
+    async fn up(&self, dry_run: bool) -> Result<Vec<MigrationStatus>, PersistenceError> {
+        let mut conn = self.acquire().await?;
+        let applied = Self::applied(&mut conn)
+            .await
+            .map_err(|e| PersistenceError::from_sqlx("Error reading migration history", e))?;
+        let mut ran = Vec::new();
+        for migration in pending(SQLITE_MIGRATIONS, &applied) {
+            if dry_run {
+                ran.push(MigrationStatus {
+                    version: migration.version,
+                    name: migration.name.to_string(),
+                    applied_at: None,
+                });
+                continue;
+            }
+            let applied_at = chrono::Utc::now().naive_utc();
+            let result = Self::apply(&mut conn, migration, applied_at).await;
+            if let Err(e) = result {
+                return Err(PersistenceError::MigrationFailed {
+                    version: migration.version,
+                    name: migration.name.to_string(),
+                    cause: e.to_string(),
+                });
+            }
+            ran.push(MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                applied_at: Some(applied_at),
+            });
+        }
+        Ok(ran)
+    }
+}
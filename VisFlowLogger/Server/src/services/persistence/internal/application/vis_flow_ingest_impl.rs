@@ -0,0 +1,142 @@
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::model::vis_flow_ingest_model::IngestOutcome;
+use crate::services::persistence::api::model::vis_flow_log_model::{
+    VisFlowLogEntity, VisFlowLogEntry,
+};
+use crate::services::persistence::api::services::vis_flow_ingest::VisFlowIngest;
+use crate::services::persistence::internal::common::ingest::{
+    dedupe, dedupe_operations, stored_conflicts,
+};
+use async_trait::async_trait;
+use sqlx::{Pool, Postgres, Row};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Postgres allows at most 65535 bind parameters per statement, 10 are used per log and 2 per operation.
+const ROWS_PER_INSERT: usize = 6_500;
+
+pub struct VisFlowIngestImpl {
+    db: Pool<Postgres>,
+}
+
+impl VisFlowIngestImpl {
+    pub fn new(db: Pool<Postgres>) -> Self {
+        VisFlowIngestImpl { db }
+    }
+
+    async fn ingest_in_transaction(
+        &self,
+        operations: &[(String, String)],
+        logs: &[&VisFlowLogEntry],
+    ) -> Result<IngestOutcome, sqlx::Error> {
+        let deduped = dedupe(logs);
+        if !deduped.conflicts.is_empty() {
+            return Ok(IngestOutcome::Conflict {
+                conflicts: deduped.conflicts,
+            });
+        }
+
+        let mut tx = self.db.begin().await?;
+        for chunk in dedupe_operations(operations).chunks(ROWS_PER_INSERT) {
+            let placeholders: Vec<String> = (0..chunk.len())
+                .map(|idx| format!("(${}, ${}, NOW(), NOW())", idx * 2 + 1, idx * 2 + 2))
+                .collect();
+            let query = format!(
+                "INSERT INTO operations (id, name, created, updated) VALUES {}
+                ON CONFLICT (id) DO UPDATE SET updated = NOW()",
+                placeholders.join(", ")
+            );
+            let mut query = sqlx::query(&query);
+            for (id, name) in chunk {
+                query = query.bind(id).bind(name);
+            }
+            query.execute(&mut *tx).await?;
+        }
+
+        let now = chrono::Utc::now().naive_utc();
+        let mut inserted: HashSet<(String, i64)> = HashSet::new();
+        for chunk in deduped.unique.chunks(ROWS_PER_INSERT) {
+            let placeholders: Vec<String> = (0..chunk.len())
+                .map(|idx| {
+                    let offset = idx * 10;
+                    let params: Vec<String> = (1..=10)
+                        .map(|param| format!("${}", offset + param))
+                        .collect();
+                    format!("({})", params.join(","))
+                })
+                .collect();
+            let query = format!(
+                "INSERT INTO logs (id, operation_id, block_name, log_type, log_value, sequence, span_id, parent_span_id, logged_at, received_at) VALUES {}
+                ON CONFLICT (operation_id, sequence) DO NOTHING
+                RETURNING operation_id, sequence",
+                placeholders.join(",")
+            );
+            let mut query = sqlx::query(&query);
+            for log in chunk {
+                query = query
+                    .bind(Uuid::new_v4().to_string())
+                    .bind(&log.operation_id)
+                    .bind(&log.block_name)
+                    .bind(&log.log_type)
+                    .bind(log.log_value.as_deref().unwrap_or_default())
+                    .bind(i64::from(log.sequence))
+                    .bind(&log.span_id)
+                    .bind(&log.parent_span_id)
+                    .bind(log.timestamp.map(|time| time.naive_utc()))
+                    .bind(now);
+            }
+            for row in query.fetch_all(&mut *tx).await? {
+                inserted.insert((row.get("operation_id"), row.get("sequence")));
+            }
+        }
+
+        let skipped: Vec<&VisFlowLogEntry> = deduped
+            .unique
+            .iter()
+            .filter(|log| !inserted.contains(&(log.operation_id.clone(), i64::from(log.sequence))))
+            .copied()
+            .collect();
+        if !skipped.is_empty() {
+            let operation_ids: Vec<&str> = skipped
+                .iter()
+                .map(|log| log.operation_id.as_str())
+                .collect();
+            let sequences: Vec<i64> = skipped.iter().map(|log| i64::from(log.sequence)).collect();
+            let stored: Vec<VisFlowLogEntity> = sqlx::query(
+                "SELECT * FROM logs WHERE (operation_id, sequence) IN
+                (SELECT * FROM UNNEST($1::text[], $2::bigint[]))",
+            )
+            .bind(operation_ids)
+            .bind(sequences)
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .map(VisFlowLogEntity::from_row)
+            .collect();
+            let conflicts = stored_conflicts(&stored, &skipped);
+            if !conflicts.is_empty() {
+                tx.rollback().await?;
+                return Ok(IngestOutcome::Conflict { conflicts });
+            }
+        }
+
+        tx.commit().await?;
+        Ok(IngestOutcome::Saved {
+            inserted: inserted.len(),
+            duplicates: deduped.duplicates + skipped.len(),
+        })
+    }
+}
+
+#[async_trait]
+impl VisFlowIngest for VisFlowIngestImpl {
+    async fn ingest(
+        &self,
+        operations: &[(String, String)],
+        logs: &[&VisFlowLogEntry],
+    ) -> Result<IngestOutcome, PersistenceError> {
+        self.ingest_in_transaction(operations, logs)
+            .await
+            .map_err(|e| PersistenceError::from_sqlx("Error ingesting logs", e))
+    }
+}
@@ -0,0 +1,74 @@
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::model::vis_flow_ingest_model::IngestOutcome;
+use crate::services::persistence::api::model::vis_flow_log_model::{
+    VisFlowLogEntity, VisFlowLogEntry,
+};
+use crate::services::persistence::api::services::vis_flow_ingest::VisFlowIngest;
+use crate::services::persistence::api::services::vis_flow_op::VisFlowOp;
+use crate::services::persistence::internal::application::vis_flow_log_memory_impl::VisFlowLogMemoryImpl;
+use crate::services::persistence::internal::application::vis_flow_op_memory_impl::VisFlowOpMemoryImpl;
+use crate::services::persistence::internal::common::ingest::{dedupe, stored_conflicts};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Writes to the same stores as the memory log and operation services.
+pub struct VisFlowIngestMemoryImpl {
+    logs: Arc<VisFlowLogMemoryImpl>,
+    operations: Arc<VisFlowOpMemoryImpl>,
+}
+
+impl VisFlowIngestMemoryImpl {
+    pub fn new(logs: Arc<VisFlowLogMemoryImpl>, operations: Arc<VisFlowOpMemoryImpl>) -> Self {
+        VisFlowIngestMemoryImpl { logs, operations }
+    }
+}
+
+#[async_trait]
+impl VisFlowIngest for VisFlowIngestMemoryImpl {
+    async fn ingest(
+        &self,
+        operations: &[(String, String)],
+        logs: &[&VisFlowLogEntry],
+    ) -> Result<IngestOutcome, PersistenceError> {
+        let deduped = dedupe(logs);
+        if !deduped.conflicts.is_empty() {
+            return Ok(IngestOutcome::Conflict {
+                conflicts: deduped.conflicts,
+            });
+        }
+
+        let (inserted, skipped) = {
+            // Checking and inserting under one lock keeps concurrent requests from interleaving
+            let mut stored = self.logs.logs.write().unwrap();
+            let (skipped, new): (Vec<&VisFlowLogEntry>, Vec<&VisFlowLogEntry>) =
+                deduped.unique.iter().partition(|log| {
+                    stored
+                        .get(&log.operation_id)
+                        .is_some_and(|logs| logs.contains_key(&log.sequence))
+                });
+            let existing: Vec<VisFlowLogEntity> = skipped
+                .iter()
+                .map(|log| stored[&log.operation_id][&log.sequence].clone())
+                .collect();
+            let conflicts = stored_conflicts(&existing, &skipped);
+            if !conflicts.is_empty() {
+                return Ok(IngestOutcome::Conflict { conflicts });
+            }
+            let now = chrono::Utc::now();
+            for log in &new {
+                stored
+                    .entry(log.operation_id.clone())
+                    .or_default()
+                    .insert(log.sequence, VisFlowLogMemoryImpl::to_entity(log, now));
+            }
+            (new.len(), skipped.len())
+        };
+
+        // Cannot fail, so the request is still applied entirely
+        self.operations.upsert(operations.to_vec()).await?;
+        Ok(IngestOutcome::Saved {
+            inserted,
+            duplicates: deduped.duplicates + skipped,
+        })
+    }
+}
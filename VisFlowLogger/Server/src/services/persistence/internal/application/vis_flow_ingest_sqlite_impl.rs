@@ -0,0 +1,128 @@
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::model::vis_flow_ingest_model::IngestOutcome;
+use crate::services::persistence::api::model::vis_flow_log_model::{
+    VisFlowLogEntity, VisFlowLogEntry,
+};
+use crate::services::persistence::api::services::vis_flow_ingest::VisFlowIngest;
+use crate::services::persistence::internal::common::ingest::{
+    dedupe, dedupe_operations, stored_conflicts,
+};
+use async_trait::async_trait;
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// SQLite limits the number of bind parameters per statement, so rows are written and read in chunks.
+const ROWS_PER_STATEMENT: usize = 1000;
+
+pub struct VisFlowIngestSqliteImpl {
+    db: Pool<Sqlite>,
+}
+
+impl VisFlowIngestSqliteImpl {
+    pub fn new(db: Pool<Sqlite>) -> Self {
+        VisFlowIngestSqliteImpl { db }
+    }
+
+    async fn ingest_in_transaction(
+        &self,
+        operations: &[(String, String)],
+        logs: &[&VisFlowLogEntry],
+    ) -> Result<IngestOutcome, sqlx::Error> {
+        let deduped = dedupe(logs);
+        if !deduped.conflicts.is_empty() {
+            return Ok(IngestOutcome::Conflict {
+                conflicts: deduped.conflicts,
+            });
+        }
+
+        let mut tx = self.db.begin().await?;
+        let now = chrono::Utc::now().naive_utc();
+        for chunk in dedupe_operations(operations).chunks(ROWS_PER_STATEMENT) {
+            let query = format!(
+                "INSERT INTO operations (id, name, created, updated) VALUES {}
+                ON CONFLICT (id) DO UPDATE SET updated = excluded.updated",
+                vec!["(?, ?, ?, ?)"; chunk.len()].join(", ")
+            );
+            let mut query = sqlx::query(&query);
+            for (id, name) in chunk {
+                query = query.bind(id).bind(name).bind(now).bind(now);
+            }
+            query.execute(&mut *tx).await?;
+        }
+
+        let mut inserted: HashSet<(String, u32)> = HashSet::new();
+        for chunk in deduped.unique.chunks(ROWS_PER_STATEMENT) {
+            let query = format!(
+                "INSERT INTO logs (id, operation_id, block_name, log_type, log_value, sequence, span_id, parent_span_id, logged_at, received_at) VALUES {}
+                ON CONFLICT (operation_id, sequence) DO NOTHING
+                RETURNING operation_id, sequence",
+                vec!["(?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"; chunk.len()].join(",")
+            );
+            let mut query = sqlx::query(&query);
+            for log in chunk {
+                query = query
+                    .bind(Uuid::new_v4().to_string())
+                    .bind(&log.operation_id)
+                    .bind(&log.block_name)
+                    .bind(&log.log_type)
+                    .bind(log.log_value.as_deref().unwrap_or_default())
+                    .bind(log.sequence)
+                    .bind(&log.span_id)
+                    .bind(&log.parent_span_id)
+                    .bind(log.timestamp.map(|time| time.naive_utc()))
+                    .bind(now);
+            }
+            for row in query.fetch_all(&mut *tx).await? {
+                inserted.insert((
+                    row.get("operation_id"),
+                    row.get::<i64, _>("sequence") as u32,
+                ));
+            }
+        }
+
+        let skipped: Vec<&VisFlowLogEntry> = deduped
+            .unique
+            .iter()
+            .filter(|log| !inserted.contains(&(log.operation_id.clone(), log.sequence)))
+            .copied()
+            .collect();
+        let mut stored: Vec<VisFlowLogEntity> = Vec::new();
+        for chunk in skipped.chunks(ROWS_PER_STATEMENT) {
+            let query = format!(
+                "SELECT * FROM logs WHERE (operation_id, sequence) IN (VALUES {})",
+                vec!["(?, ?)"; chunk.len()].join(",")
+            );
+            let mut query = sqlx::query(&query);
+            for log in chunk {
+                query = query.bind(&log.operation_id).bind(log.sequence);
+            }
+            let rows = query.fetch_all(&mut *tx).await?;
+            stored.extend(rows.iter().map(VisFlowLogEntity::from_sqlite_row));
+        }
+        let conflicts = stored_conflicts(&stored, &skipped);
+        if !conflicts.is_empty() {
+            tx.rollback().await?;
+            return Ok(IngestOutcome::Conflict { conflicts });
+        }
+
+        tx.commit().await?;
+        Ok(IngestOutcome::Saved {
+            inserted: inserted.len(),
+            duplicates: deduped.duplicates + skipped.len(),
+        })
+    }
+}
+
+#[async_trait]
+impl VisFlowIngest for VisFlowIngestSqliteImpl {
+    async fn ingest(
+        &self,
+        operations: &[(String, String)],
+        logs: &[&VisFlowLogEntry],
+    ) -> Result<IngestOutcome, PersistenceError> {
+        self.ingest_in_transaction(operations, logs)
+            .await
+            .map_err(|e| PersistenceError::from_sqlx("Error ingesting logs", e))
+    }
+}
@@ -0,0 +1,50 @@
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::model::vis_flow_log_model::{
+    VisFlowLogEntity, VisFlowLogEntry,
+};
+use crate::services::persistence::api::services::vis_flow_log::VisFlowLog;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use uuid::Uuid;
+
+/// Keeps logs in process memory, grouped by operation ID and keyed by sequence.
+#[derive(Default)]
+pub struct VisFlowLogMemoryImpl {
+    pub(super) logs: RwLock<HashMap<String, BTreeMap<u32, VisFlowLogEntity>>>,
+}
+
+impl VisFlowLogMemoryImpl {
+    pub(super) fn to_entity(log: &VisFlowLogEntry, received_at: DateTime<Utc>) -> VisFlowLogEntity {
+        VisFlowLogEntity {
+            id: Uuid::new_v4().to_string(),
+            operation_id: log.operation_id.clone(),
+            block_name: log.block_name.clone(),
+            log_type: log.log_type.clone(),
+            // Same as the SQL backends, which store a missing value as an empty string
+            log_value: Some(log.log_value.clone().unwrap_or_default()),
+            sequence: log.sequence,
+            span_id: log.span_id.clone(),
+            parent_span_id: log.parent_span_id.clone(),
+            timestamp: log.timestamp,
+            received_at: Some(received_at),
+        }
+    }
+}
+
+#[async_trait]
+impl VisFlowLog for VisFlowLogMemoryImpl {
+    async fn get_logs_by_operation_id(
+        &self,
+        operation_id: String,
+    ) -> Result<Vec<VisFlowLogEntity>, PersistenceError> {
+        Ok(self
+            .logs
+            .read()
+            .unwrap()
+            .get(&operation_id)
+            .map(|logs| logs.values().cloned().collect())
+            .unwrap_or_default())
+    }
+}
@@ -0,0 +1,32 @@
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::model::vis_flow_log_model::VisFlowLogEntity;
+use crate::services::persistence::api::services::vis_flow_log::VisFlowLog;
+use async_trait::async_trait;
+use sqlx::{Pool, Sqlite};
+
+pub struct VisFlowLogSqliteImpl {
+    db: Pool<Sqlite>,
+}
+
+impl VisFlowLogSqliteImpl {
+    pub fn new(db: Pool<Sqlite>) -> Self {
+        VisFlowLogSqliteImpl { db }
+    }
+}
+
+#[async_trait]
+impl VisFlowLog for VisFlowLogSqliteImpl {
+    async fn get_logs_by_operation_id(
+        &self,
+        operation_id: String,
+    ) -> Result<Vec<VisFlowLogEntity>, PersistenceError> {
+        match sqlx::query("SELECT * FROM logs WHERE operation_id = ? ORDER BY sequence")
+            .bind(operation_id)
+            .fetch_all(&self.db)
+            .await
+        {
+            Ok(rows) => Ok(rows.iter().map(VisFlowLogEntity::from_sqlite_row).collect()),
+            Err(err) => Err(PersistenceError::from_sqlx("Error fetching logs", err)),
+        }
+    }
+}
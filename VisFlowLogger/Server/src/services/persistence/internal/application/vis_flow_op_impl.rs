@@ -1,31 +1,30 @@
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
 use crate::services::persistence::api::model::vis_flow_op_model::VisFlowOperationEntity;
 use crate::services::persistence::api::services::vis_flow_op::VisFlowOp;
-use crate::services::persistence::internal::common::db::init_database;
 use async_trait::async_trait;
+use chrono::NaiveDateTime;
 use sqlx::{Pool, Postgres};
 
 pub struct VisLogOpImpl {
     db: Pool<Postgres>,
 }
 impl VisLogOpImpl {
-    pub async fn new() -> Self {
-        VisLogOpImpl {
-            db: init_database().await.unwrap(),
-        }
+    pub fn new(db: Pool<Postgres>) -> Self {
+        VisLogOpImpl { db }
     }
 }
 #[async_trait]
 impl VisFlowOp for VisLogOpImpl {
-    async fn upsert(&self, operation_ids: Vec<(String, String)>) -> bool {
+    async fn upsert(&self, operation_ids: Vec<(String, String)>) -> Result<(), PersistenceError> {
         if operation_ids.is_empty() {
-            return false; // No operations to insert or update
+            return Ok(()); // No operations to insert or update
         }
 
         // Dynamically construct the VALUES part of the query
         let mut count = 0;
         let placeholders: Vec<String> = operation_ids
             .iter()
-            .map(|operation| {
+            .map(|_operation| {
                 let string = format!("(${}, ${}, NOW(), NOW())", count + 1, count + 2);
                 count += 2;
                 string
@@ -53,25 +52,51 @@ impl VisFlowOp for VisLogOpImpl {
         let result = query_builder.execute(&self.db).await;
 
         match result {
-            Ok(result) => result.rows_affected() > 0,
-            Err(e) => {
-                eprintln!("Error in batch upsert: {}", e); // Log the error
-                false
-            }
+            Ok(_) => Ok(()),
+            Err(e) => Err(PersistenceError::from_sqlx("Error in batch upsert", e)),
         }
     }
 
-    async fn get_operations(&self) -> Result<Vec<VisFlowOperationEntity>, String> {
+    async fn get_operations(&self) -> Result<Vec<VisFlowOperationEntity>, PersistenceError> {
         let query = "SELECT * FROM operations ORDER BY updated";
         match sqlx::query(query).fetch_all(&self.db).await {
             Ok(rows) => {
-                let operations = rows
-                    .iter()
-                    .map(|row| VisFlowOperationEntity::from_row(row))
-                    .collect();
+                let operations = rows.iter().map(VisFlowOperationEntity::from_row).collect();
                 Ok(operations)
             }
-            Err(err) => Err(format!("Error fetching operations: {}", err)),
+            Err(err) => Err(PersistenceError::from_sqlx(
+                "Error fetching operations",
+                err,
+            )),
+        }
+    }
+
+    async fn get_operations_named(
+        &self,
+        operation_name: &str,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: usize,
+    ) -> Result<Vec<VisFlowOperationEntity>, PersistenceError> {
+        let query = "SELECT * FROM operations
+            WHERE name = $1
+            AND ($2::timestamp IS NULL OR created >= $2)
+            AND ($3::timestamp IS NULL OR created < $3)
+            ORDER BY created DESC
+            LIMIT $4";
+        match sqlx::query(query)
+            .bind(operation_name)
+            .bind(from)
+            .bind(to)
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .fetch_all(&self.db)
+            .await
+        {
+            Ok(rows) => Ok(rows.iter().map(VisFlowOperationEntity::from_row).collect()),
+            Err(err) => Err(PersistenceError::from_sqlx(
+                "Error fetching operations",
+                err,
+            )),
         }
     }
 }
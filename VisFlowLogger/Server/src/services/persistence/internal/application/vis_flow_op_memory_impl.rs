@@ -0,0 +1,65 @@
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::model::vis_flow_op_model::VisFlowOperationEntity;
+use crate::services::persistence::api::services::vis_flow_op::VisFlowOp;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Keeps operations in process memory, keyed by operation ID.
+#[derive(Default)]
+pub struct VisFlowOpMemoryImpl {
+    operations: RwLock<HashMap<String, VisFlowOperationEntity>>,
+}
+
+#[async_trait]
+impl VisFlowOp for VisFlowOpMemoryImpl {
+    async fn upsert(&self, operation_ids: Vec<(String, String)>) -> Result<(), PersistenceError> {
+        if operation_ids.is_empty() {
+            return Ok(()); // No operations to insert or update
+        }
+        let now = chrono::Utc::now().naive_utc();
+        let mut operations = self.operations.write().unwrap();
+        for (id, name) in operation_ids {
+            operations
+                .entry(id.clone())
+                .and_modify(|operation| operation.updated_at = now)
+                .or_insert(VisFlowOperationEntity {
+                    operation_id: id,
+                    operation_name: name,
+                    created_at: now,
+                    updated_at: now,
+                });
+        }
+        Ok(())
+    }
+
+    async fn get_operations(&self) -> Result<Vec<VisFlowOperationEntity>, PersistenceError> {
+        let mut operations: Vec<VisFlowOperationEntity> =
+            self.operations.read().unwrap().values().cloned().collect();
+        operations.sort_by_key(|operation| operation.updated_at);
+        Ok(operations)
+    }
+
+    async fn get_operations_named(
+        &self,
+        operation_name: &str,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: usize,
+    ) -> Result<Vec<VisFlowOperationEntity>, PersistenceError> {
+        let mut operations: Vec<VisFlowOperationEntity> = self
+            .operations
+            .read()
+            .unwrap()
+            .values()
+            .filter(|operation| operation.operation_name == operation_name)
+            .filter(|operation| from.is_none_or(|from| operation.created_at >= from))
+            .filter(|operation| to.is_none_or(|to| operation.created_at < to))
+            .cloned()
+            .collect();
+        operations.sort_by_key(|operation| std::cmp::Reverse(operation.created_at));
+        operations.truncate(limit);
+        Ok(operations)
+    }
+}
@@ -0,0 +1,90 @@
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::model::vis_flow_op_model::VisFlowOperationEntity;
+use crate::services::persistence::api::services::vis_flow_op::VisFlowOp;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::{Pool, Sqlite};
+
+pub struct VisFlowOpSqliteImpl {
+    db: Pool<Sqlite>,
+}
+
+impl VisFlowOpSqliteImpl {
+    pub fn new(db: Pool<Sqlite>) -> Self {
+        VisFlowOpSqliteImpl { db }
+    }
+}
+
+#[async_trait]
+impl VisFlowOp for VisFlowOpSqliteImpl {
+    async fn upsert(&self, operation_ids: Vec<(String, String)>) -> Result<(), PersistenceError> {
+        if operation_ids.is_empty() {
+            return Ok(()); // No operations to insert or update
+        }
+
+        let placeholders = vec!["(?, ?, ?, ?)"; operation_ids.len()].join(", ");
+        let query = format!(
+            "INSERT INTO operations (id, name, created, updated) VALUES {}
+            ON CONFLICT (id) DO UPDATE SET updated = excluded.updated",
+            placeholders
+        );
+        let now = chrono::Utc::now().naive_utc();
+        let mut query_builder = sqlx::query(&query);
+        for (id, name) in operation_ids {
+            query_builder = query_builder.bind(id).bind(name).bind(now).bind(now);
+        }
+
+        match query_builder.execute(&self.db).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(PersistenceError::from_sqlx("Error in batch upsert", e)),
+        }
+    }
+
+    async fn get_operations(&self) -> Result<Vec<VisFlowOperationEntity>, PersistenceError> {
+        match sqlx::query("SELECT * FROM operations ORDER BY updated")
+            .fetch_all(&self.db)
+            .await
+        {
+            Ok(rows) => Ok(rows
+                .iter()
+                .map(VisFlowOperationEntity::from_sqlite_row)
+                .collect()),
+            Err(err) => Err(PersistenceError::from_sqlx(
+                "Error fetching operations",
+                err,
+            )),
+        }
+    }
+
+    async fn get_operations_named(
+        &self,
+        operation_name: &str,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: usize,
+    ) -> Result<Vec<VisFlowOperationEntity>, PersistenceError> {
+        let query = "SELECT * FROM operations
+            WHERE name = ?1
+            AND (?2 IS NULL OR created >= ?2)
+            AND (?3 IS NULL OR created < ?3)
+            ORDER BY created DESC
+            LIMIT ?4";
+        match sqlx::query(query)
+            .bind(operation_name)
+            .bind(from)
+            .bind(to)
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .fetch_all(&self.db)
+            .await
+        {
+            Ok(rows) => Ok(rows
+                .iter()
+                .map(VisFlowOperationEntity::from_sqlite_row)
+                .collect()),
+            Err(err) => Err(PersistenceError::from_sqlx(
+                "Error fetching operations",
+                err,
+            )),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::services::persistence::api::model::vis_flow_ingest_model::LogConflict;
+use crate::services::persistence::api::model::vis_flow_log_model::{
+    VisFlowLogEntity, VisFlowLogEntry,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Logs of one request with repeated `(operation_id, sequence)` keys folded together
+pub struct DedupedLogs<'a> {
+    pub unique: Vec<&'a VisFlowLogEntry>,
+    pub duplicates: usize,
+    pub conflicts: Vec<LogConflict>,
+}
+
+/// Keeps the first log per key. A repeated key with different content is a conflict.
+pub fn dedupe<'a>(logs: &[&'a VisFlowLogEntry]) -> DedupedLogs<'a> {
+    let mut first_by_key: HashMap<(&str, u32), &VisFlowLogEntry> = HashMap::new();
+    let mut deduped = DedupedLogs {
+        unique: Vec::with_capacity(logs.len()),
+        duplicates: 0,
+        conflicts: Vec::new(),
+    };
+    for log in logs {
+        match first_by_key.get(&(log.operation_id.as_str(), log.sequence)) {
+            None => {
+                first_by_key.insert((&log.operation_id, log.sequence), log);
+                deduped.unique.push(log);
+            }
+            Some(first) if same_content(first, log) => deduped.duplicates += 1,
+            Some(_) => {
+                let conflict = conflict(log);
+                if !deduped.conflicts.contains(&conflict) {
+                    deduped.conflicts.push(conflict);
+                }
+            }
+        }
+    }
+    deduped
+}
+
+/// The stored columns besides the key and the times. A missing value is stored as an empty string.
+/// Times are left out since the databases round them, so a retry would look like a conflict.
+fn content<'a>(
+    block_name: &'a str,
+    log_type: &'a str,
+    log_value: &'a Option<String>,
+    span_id: &'a Option<String>,
+    parent_span_id: &'a Option<String>,
+) -> (&'a str, &'a str, &'a str, Option<&'a str>, Option<&'a str>) {
+    (
+        block_name,
+        log_type,
+        log_value.as_deref().unwrap_or_default(),
+        span_id.as_deref(),
+        parent_span_id.as_deref(),
+    )
+}
+
+fn entry_content(log: &VisFlowLogEntry) -> (&str, &str, &str, Option<&str>, Option<&str>) {
+    content(
+        &log.block_name,
+        &log.log_type,
+        &log.log_value,
+        &log.span_id,
+        &log.parent_span_id,
+    )
+}
+
+fn same_content(a: &VisFlowLogEntry, b: &VisFlowLogEntry) -> bool {
+    entry_content(a) == entry_content(b)
+}
+
+/// Logs that were not inserted because their key exists, but whose stored content differs
+pub fn stored_conflicts(
+    stored: &[VisFlowLogEntity],
+    skipped: &[&VisFlowLogEntry],
+) -> Vec<LogConflict> {
+    let stored: HashMap<(&str, u32), &VisFlowLogEntity> = stored
+        .iter()
+        .map(|log| ((log.operation_id.as_str(), log.sequence), log))
+        .collect();
+    skipped
+        .iter()
+        .filter(|log| {
+            stored
+                .get(&(log.operation_id.as_str(), log.sequence))
+                .is_none_or(|existing| {
+                    content(
+                        &existing.block_name,
+                        &existing.log_type,
+                        &existing.log_value,
+                        &existing.span_id,
+                        &existing.parent_span_id,
+                    ) != entry_content(log)
+                })
+        })
+        .map(|log| conflict(log))
+        .collect()
+}
+
+pub fn conflict(log: &VisFlowLogEntry) -> LogConflict {
+    LogConflict {
+        operation_id: log.operation_id.clone(),
+        sequence: log.sequence,
+    }
+}
+
+/// Keeps the first name per operation ID, since one upsert statement may not touch a row twice
+pub fn dedupe_operations(operations: &[(String, String)]) -> Vec<&(String, String)> {
+    let mut seen = HashSet::new();
+    operations
+        .iter()
+        .filter(|(id, _)| seen.insert(id.as_str()))
+        .collect()
+}
@@ -0,0 +1,122 @@
+use crate::services::persistence::api::model::migration_model::MigrationStatus;
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+
+/// A schema change shipped with the binary. Versions only ever grow and a shipped migration
+/// must never be edited, since databases that already applied it won't run it again.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Records which migrations were applied. Created before anything else runs.
+pub const HISTORY_TABLE: &str = "CREATE TABLE IF NOT EXISTS schema_migrations (
+    version BIGINT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    applied_at TIMESTAMP NOT NULL
+)";
+
+pub const POSTGRES_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "tables",
+        sql: include_str!("../../../../../migrations/postgres/1_tables.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "widen_log_value",
+        sql: include_str!("../../../../../migrations/postgres/2_widen_log_value.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "logs_operation_sequence_index",
+        sql: include_str!("../../../../../migrations/postgres/3_logs_operation_sequence_index.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "logs_operation_sequence_unique",
+        sql: include_str!(
+            "../../../../../migrations/postgres/4_logs_operation_sequence_unique.sql"
+        ),
+    },
+    Migration {
+        version: 5,
+        name: "logs_span",
+        sql: include_str!("../../../../../migrations/postgres/5_logs_span.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "logs_time",
+        sql: include_str!("../../../../../migrations/postgres/6_logs_time.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "logs_sequence_bigint",
+        sql: include_str!("../../../../../migrations/postgres/7_logs_sequence_bigint.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "operations_name_created_index",
+        sql: include_str!("../../../../../migrations/postgres/8_operations_name_created_index.sql"),
+    },
+];
+
+/// SQLite has no length limit on TEXT and its INTEGER holds 64 bits, so it has no equivalent of versions 2 and 7.
+pub const SQLITE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "tables",
+        sql: include_str!("../../../../../migrations/sqlite/1_tables.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "logs_operation_sequence_index",
+        sql: include_str!("../../../../../migrations/sqlite/3_logs_operation_sequence_index.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "logs_operation_sequence_unique",
+        sql: include_str!("../../../../../migrations/sqlite/4_logs_operation_sequence_unique.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "logs_span",
+        sql: include_str!("../../../../../migrations/sqlite/5_logs_span.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "logs_time",
+        sql: include_str!("../../../../../migrations/sqlite/6_logs_time.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "operations_name_created_index",
+        sql: include_str!("../../../../../migrations/sqlite/8_operations_name_created_index.sql"),
+    },
+];
+
+/// Pairs every known migration with the time it was applied, if it was.
+pub fn statuses(
+    migrations: &[Migration],
+    applied: &HashMap<i64, NaiveDateTime>,
+) -> Vec<MigrationStatus> {
+    migrations
+        .iter()
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            name: migration.name.to_string(),
+            applied_at: applied.get(&migration.version).copied(),
+        })
+        .collect()
+}
+
+pub fn pending<'a>(
+    migrations: &'a [Migration],
+    applied: &HashMap<i64, NaiveDateTime>,
+) -> Vec<&'a Migration> {
+    migrations
+        .iter()
+        .filter(|migration| !applied.contains_key(&migration.version))
+        .collect()
+}
@@ -1,2 +1,4 @@
 pub mod application;
 pub mod common;
+#[cfg(test)]
+mod unit_test;
@@ -0,0 +1,416 @@
+use crate::config::settings::{DatabaseBackend, DatabaseSettings};
+use crate::services::persistence::api::model::vis_flow_ingest_model::{IngestOutcome, LogConflict};
+use crate::services::persistence::api::model::vis_flow_log_model::VisFlowLogEntry;
+use crate::services::persistence::AvailableServices;
+use uuid::Uuid;
+
+/*
+Conformance suite every persistence backend has to pass.
+Postgres is ignored by default. Run it with `cargo test -- --ignored` and VFL_TEST_POSTGRES_URL pointing at a database. Migrations are applied first.
+ */
+
+async fn services(settings: DatabaseSettings) -> AvailableServices {
+    let services = crate::services::persistence::new(&settings).await.unwrap();
+    services.schema_migration.up(false).await.unwrap();
+    services
+}
+
+fn settings(backend: DatabaseBackend, url: &str) -> DatabaseSettings {
+    DatabaseSettings {
+        backend,
+        url: url.to_string(),
+        ..DatabaseSettings::default()
+    }
+}
+
+fn entry(
+    operation_id: &str,
+    log_type: &str,
+    value: Option<&str>,
+    sequence: u32,
+) -> VisFlowLogEntry {
+    VisFlowLogEntry {
+        operation_id: operation_id.to_string(),
+        block_name: "main".to_string(),
+        log_type: log_type.to_string(),
+        log_value: value.map(str::to_string),
+        sequence,
+        span_id: None,
+        parent_span_id: None,
+        timestamp: None,
+    }
+}
+
+fn saved(inserted: usize, duplicates: usize) -> IngestOutcome {
+    IngestOutcome::Saved {
+        inserted,
+        duplicates,
+    }
+}
+
+async fn conformance(services: &AvailableServices) {
+    let (log, op, ingest) = (
+        &services.vis_flow_log,
+        &services.vis_flow_op,
+        &services.vis_flow_ingest,
+    );
+    // IDs are unique per run so a shared database can be reused
+    let first = Uuid::new_v4().to_string();
+    let second = Uuid::new_v4().to_string();
+    // Whole microseconds, which is what the SQL backends keep
+    let logged_at = chrono::DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+
+    assert_eq!(
+        ingest.ingest(&[], &[]).await.unwrap(),
+        saved(0, 0),
+        "saving nothing succeeds"
+    );
+    let entries = [
+        entry(&first, "END", None, 2),
+        entry(&second, "START", None, 0),
+        entry(&first, "START", None, 0),
+        VisFlowLogEntry {
+            span_id: Some("task".to_string()),
+            parent_span_id: Some("root".to_string()),
+            timestamp: Some(logged_at),
+            ..entry(&first, "LOG", Some("hello"), 1)
+        },
+    ];
+    let entries: Vec<&VisFlowLogEntry> = entries.iter().collect();
+    assert_eq!(ingest.ingest(&[], &entries).await.unwrap(), saved(4, 0));
+
+    let logs = log.get_logs_by_operation_id(first.clone()).await.unwrap();
+    assert_eq!(
+        logs.iter().map(|l| l.sequence).collect::<Vec<_>>(),
+        vec![0, 1, 2],
+        "logs are ordered by sequence and filtered by operation"
+    );
+    assert!(logs.iter().all(|l| l.operation_id == first));
+    assert_eq!(logs[1].log_type, "LOG");
+    assert_eq!(logs[1].log_value.as_deref(), Some("hello"));
+    assert_eq!(logs[1].span_id.as_deref(), Some("task"));
+    assert_eq!(logs[1].parent_span_id.as_deref(), Some("root"));
+    assert_eq!(
+        logs[0].span_id, None,
+        "logs without a span read back without one"
+    );
+    assert_eq!(logs[1].timestamp, Some(logged_at));
+    assert_eq!(logs[0].timestamp, None);
+    assert!(
+        logs.iter().all(|l| l.received_at.is_some()),
+        "the time logs were received is recorded"
+    );
+    assert_eq!(
+        logs[0].log_value.as_deref(),
+        Some(""),
+        "missing value reads back empty"
+    );
+    assert_ne!(logs[0].id, logs[1].id);
+    assert!(log
+        .get_logs_by_operation_id(Uuid::new_v4().to_string())
+        .await
+        .unwrap()
+        .is_empty());
+
+    op.upsert(vec![])
+        .await
+        .expect("upserting nothing is a no-op");
+    op.upsert(vec![
+        (first.clone(), "first".to_string()),
+        (second.clone(), "second".to_string()),
+    ])
+    .await
+    .unwrap();
+    let created = op
+        .get_operations()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|o| o.operation_id == first)
+        .unwrap();
+    assert_eq!(created.operation_name, "first");
+
+    op.upsert(vec![(first.clone(), "renamed".to_string())])
+        .await
+        .unwrap();
+    let operations = op.get_operations().await.unwrap();
+    let updated = operations.iter().find(|o| o.operation_id == first).unwrap();
+    assert_eq!(
+        updated.operation_name, "first",
+        "upsert keeps the original name"
+    );
+    assert_eq!(updated.created_at, created.created_at);
+    assert!(updated.updated_at >= created.updated_at);
+    assert_eq!(
+        operations
+            .iter()
+            .filter(|o| o.operation_id == first)
+            .count(),
+        1
+    );
+    assert!(
+        operations
+            .windows(2)
+            .all(|pair| pair[0].updated_at <= pair[1].updated_at),
+        "operations are ordered by updated"
+    );
+
+    named_conformance(services).await;
+    ingest_conformance(services).await;
+}
+
+async fn named_conformance(services: &AvailableServices) {
+    let op = &services.vis_flow_op;
+    let name = Uuid::new_v4().to_string();
+    let ids: Vec<String> = (0..3).map(|_| Uuid::new_v4().to_string()).collect();
+    for id in &ids {
+        op.upsert(vec![(id.clone(), name.clone())]).await.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    op.upsert(vec![(Uuid::new_v4().to_string(), "other".to_string())])
+        .await
+        .unwrap();
+
+    let named = op
+        .get_operations_named(&name, None, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        named.iter().map(|o| &o.operation_id).collect::<Vec<_>>(),
+        ids.iter().rev().collect::<Vec<_>>(),
+        "only operations with the name, newest first"
+    );
+    let newest = op.get_operations_named(&name, None, None, 2).await.unwrap();
+    assert_eq!(
+        newest.iter().map(|o| &o.operation_id).collect::<Vec<_>>(),
+        vec![&ids[2], &ids[1]]
+    );
+    let window = op
+        .get_operations_named(
+            &name,
+            Some(named[1].created_at),
+            Some(named[0].created_at),
+            10,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        window.iter().map(|o| &o.operation_id).collect::<Vec<_>>(),
+        vec![&ids[1]],
+        "from is inclusive, to is exclusive"
+    );
+    assert!(op
+        .get_operations_named(&Uuid::new_v4().to_string(), None, None, 10)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+async fn ingest_conformance(services: &AvailableServices) {
+    let (log, op, ingest) = (
+        &services.vis_flow_log,
+        &services.vis_flow_op,
+        &services.vis_flow_ingest,
+    );
+    let id = Uuid::new_v4().to_string();
+    let operations = [(id.clone(), "ingested".to_string())];
+    let entries = [
+        entry(&id, "START", None, 0),
+        entry(&id, "LOG", Some("a"), 1),
+        entry(&id, "LOG", Some("a"), 1),
+    ];
+    let entries: Vec<&VisFlowLogEntry> = entries.iter().collect();
+    assert_eq!(
+        ingest.ingest(&operations, &entries).await.unwrap(),
+        saved(2, 1),
+        "a log repeated within a request is saved once"
+    );
+    assert!(op
+        .get_operations()
+        .await
+        .unwrap()
+        .iter()
+        .any(|o| o.operation_id == id && o.operation_name == "ingested"));
+
+    assert_eq!(
+        ingest.ingest(&operations, &entries).await.unwrap(),
+        saved(0, 3),
+        "a retried request saves nothing new"
+    );
+    assert_eq!(
+        log.get_logs_by_operation_id(id.clone())
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let other = Uuid::new_v4().to_string();
+    let conflicting = [
+        entry(&id, "END", None, 2),
+        entry(&id, "LOG", Some("b"), 1),
+        entry(&other, "START", None, 0),
+    ];
+    let conflicting: Vec<&VisFlowLogEntry> = conflicting.iter().collect();
+    assert_eq!(
+        ingest
+            .ingest(&[(other.clone(), "other".to_string())], &conflicting)
+            .await
+            .unwrap(),
+        IngestOutcome::Conflict {
+            conflicts: vec![LogConflict {
+                operation_id: id.clone(),
+                sequence: 1
+            }]
+        }
+    );
+    assert_eq!(
+        log.get_logs_by_operation_id(id.clone())
+            .await
+            .unwrap()
+            .len(),
+        2,
+        "a conflict saves nothing"
+    );
+    assert!(log
+        .get_logs_by_operation_id(other.clone())
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(
+        !op.get_operations()
+            .await
+            .unwrap()
+            .iter()
+            .any(|o| o.operation_id == other),
+        "a conflict rolls back the operations too"
+    );
+
+    let reused = [entry(&id, "LOG", Some("a"), 5), entry(&id, "LOG", None, 5)];
+    let reused: Vec<&VisFlowLogEntry> = reused.iter().collect();
+    assert!(matches!(
+        ingest.ingest(&[], &reused).await.unwrap(),
+        IngestOutcome::Conflict { .. }
+    ));
+
+    let high = [
+        entry(&id, "LOG", Some("high"), u32::MAX),
+        entry(&id, "LOG", Some("higher"), i32::MAX as u32 + 1),
+    ];
+    let high: Vec<&VisFlowLogEntry> = high.iter().collect();
+    assert_eq!(
+        ingest.ingest(&[], &high).await.unwrap(),
+        saved(2, 0),
+        "sequences above i32::MAX are kept apart"
+    );
+    assert_eq!(ingest.ingest(&[], &high).await.unwrap(), saved(0, 2));
+    let sequences: Vec<u32> = log
+        .get_logs_by_operation_id(id.clone())
+        .await
+        .unwrap()
+        .iter()
+        .map(|l| l.sequence)
+        .collect();
+    assert_eq!(sequences, vec![0, 1, i32::MAX as u32 + 1, u32::MAX]);
+}
+
+#[tokio::test]
+async fn memory_backend_conformance() {
+    conformance(&services(settings(DatabaseBackend::Memory, "")).await).await;
+}
+
+#[tokio::test]
+async fn sqlite_backend_conformance() {
+    conformance(&services(settings(DatabaseBackend::Sqlite, "sqlite::memory:")).await).await;
+}
+
+#[tokio::test]
+async fn sqlite_file_backend_conformance() {
+    let path = std::env::temp_dir().join(format!("vis_flow_logger_{}.db", Uuid::new_v4()));
+    let url = format!("sqlite://{}?mode=rwc", path.display());
+    conformance(&services(settings(DatabaseBackend::Sqlite, &url)).await).await;
+    let _ = std::fs::remove_file(path);
+}
+
+/// Every pooled connection has to see the unique index, not only the one that ran the migrations
+#[tokio::test]
+async fn sqlite_pooled_connections_see_migrated_schema() {
+    let path = std::env::temp_dir().join(format!("vis_flow_logger_{}.db", Uuid::new_v4()));
+    let url = format!("sqlite://{}?mode=rwc", path.display());
+    let services = crate::services::persistence::new(&DatabaseSettings {
+        min_connections: 4,
+        max_connections: 4,
+        ..settings(DatabaseBackend::Sqlite, &url)
+    })
+    .await
+    .unwrap();
+    // Reading the history first touches the schema before it is migrated
+    services.schema_migration.status().await.unwrap();
+    services.schema_migration.up(false).await.unwrap();
+    let id = Uuid::new_v4().to_string();
+    let log = entry(&id, "START", None, 0);
+    let logs = [&log];
+    let outcomes =
+        futures_util::future::join_all((0..8).map(|_| services.vis_flow_ingest.ingest(&[], &logs)))
+            .await;
+    let inserted: usize = outcomes
+        .into_iter()
+        .map(|outcome| match outcome.unwrap() {
+            IngestOutcome::Saved { inserted, .. } => inserted,
+            conflict => panic!("a repeated log is a duplicate, not {:?}", conflict),
+        })
+        .sum();
+    assert_eq!(inserted, 1);
+    assert_eq!(
+        services
+            .vis_flow_log
+            .get_logs_by_operation_id(id)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+#[ignore = "needs a Postgres database at VFL_TEST_POSTGRES_URL"]
+async fn postgres_backend_conformance() {
+    let url = std::env::var("VFL_TEST_POSTGRES_URL")
+        .expect("VFL_TEST_POSTGRES_URL must point at a Postgres database");
+    conformance(&services(settings(DatabaseBackend::Postgres, &url)).await).await;
+}
+
+#[tokio::test]
+async fn migrations_are_applied_once() {
+    let services =
+        crate::services::persistence::new(&settings(DatabaseBackend::Sqlite, "sqlite::memory:"))
+            .await
+            .unwrap();
+    let migration = services.schema_migration;
+    let pending = migration.up(true).await.unwrap();
+    assert!(!pending.is_empty());
+    assert!(pending.iter().all(|m| m.applied_at.is_none()));
+    assert!(
+        migration
+            .status()
+            .await
+            .unwrap()
+            .iter()
+            .all(|m| m.applied_at.is_none()),
+        "dry run applies nothing"
+    );
+
+    let applied = migration.up(false).await.unwrap();
+    assert_eq!(
+        applied.iter().map(|m| m.version).collect::<Vec<_>>(),
+        pending.iter().map(|m| m.version).collect::<Vec<_>>()
+    );
+    assert!(migration
+        .status()
+        .await
+        .unwrap()
+        .iter()
+        .all(|m| m.applied_at.is_some()));
+    assert!(migration.up(false).await.unwrap().is_empty());
+}
@@ -1,5 +1,22 @@
+use crate::config::settings::{DatabaseBackend, DatabaseSettings};
+use crate::services::persistence::api::model::persistence_error::PersistenceError;
+use crate::services::persistence::api::services::schema_migration::SchemaMigration;
+use crate::services::persistence::api::services::vis_flow_ingest::VisFlowIngest;
 use crate::services::persistence::api::services::vis_flow_log::VisFlowLog;
 use crate::services::persistence::api::services::vis_flow_op::VisFlowOp;
+use crate::services::persistence::internal::application::schema_migration_impl::SchemaMigrationImpl;
+use crate::services::persistence::internal::application::schema_migration_memory_impl::SchemaMigrationMemoryImpl;
+use crate::services::persistence::internal::application::schema_migration_sqlite_impl::SchemaMigrationSqliteImpl;
+use crate::services::persistence::internal::application::vis_flow_ingest_impl::VisFlowIngestImpl;
+use crate::services::persistence::internal::application::vis_flow_ingest_memory_impl::VisFlowIngestMemoryImpl;
+use crate::services::persistence::internal::application::vis_flow_ingest_sqlite_impl::VisFlowIngestSqliteImpl;
+use crate::services::persistence::internal::application::vis_flow_log_impl::VisFlowLogImpl;
+use crate::services::persistence::internal::application::vis_flow_log_memory_impl::VisFlowLogMemoryImpl;
+use crate::services::persistence::internal::application::vis_flow_log_sqlite_impl::VisFlowLogSqliteImpl;
+use crate::services::persistence::internal::application::vis_flow_op_impl::VisLogOpImpl;
+use crate::services::persistence::internal::application::vis_flow_op_memory_impl::VisFlowOpMemoryImpl;
+use crate::services::persistence::internal::application::vis_flow_op_sqlite_impl::VisFlowOpSqliteImpl;
+use crate::services::persistence::internal::common::db::{init_database, init_sqlite_database};
 use std::sync::Arc;
 
 pub mod api;
@@ -9,6 +26,50 @@ pub struct AvailableServices {
     //Arc = multi owner just like ReferenceCount but thread safe
     pub vis_flow_log: Arc<dyn VisFlowLog>,
     pub vis_flow_op: Arc<dyn VisFlowOp>,
+    ///Saves operations and logs of one request atomically
+    pub vis_flow_ingest: Arc<dyn VisFlowIngest>,
+    pub schema_migration: Arc<dyn SchemaMigration>,
+}
+
+/// Connects to the configured `database.backend`. All services share one connection pool.
+pub async fn new(settings: &DatabaseSettings) -> Result<AvailableServices, PersistenceError> {
+    Ok(match settings.backend {
+        DatabaseBackend::Postgres => {
+            let db = init_database(settings)
+                .await
+                .map_err(|e| PersistenceError::from_sqlx("Failed to connect to database", e))?;
+            AvailableServices {
+                vis_flow_log: Arc::new(VisFlowLogImpl::new(db.clone())),
+                vis_flow_op: Arc::new(VisLogOpImpl::new(db.clone())),
+                vis_flow_ingest: Arc::new(VisFlowIngestImpl::new(db.clone())),
+                schema_migration: Arc::new(SchemaMigrationImpl::new(db)),
+            }
+        }
+        DatabaseBackend::Sqlite => {
+            let db = init_sqlite_database(settings)
+                .await
+                .map_err(|e| PersistenceError::from_sqlx("Failed to open SQLite database", e))?;
+            AvailableServices {
+                vis_flow_log: Arc::new(VisFlowLogSqliteImpl::new(db.clone())),
+                vis_flow_op: Arc::new(VisFlowOpSqliteImpl::new(db.clone())),
+                vis_flow_ingest: Arc::new(VisFlowIngestSqliteImpl::new(db.clone())),
+                schema_migration: Arc::new(SchemaMigrationSqliteImpl::new(db)),
+            }
+        }
+        DatabaseBackend::Memory => {
+            let logs = Arc::new(VisFlowLogMemoryImpl::default());
+            let operations = Arc::new(VisFlowOpMemoryImpl::default());
+            AvailableServices {
+                vis_flow_ingest: Arc::new(VisFlowIngestMemoryImpl::new(
+                    logs.clone(),
+                    operations.clone(),
+                )),
+                vis_flow_log: logs,
+                vis_flow_op: operations,
+                schema_migration: Arc::new(SchemaMigrationMemoryImpl),
+            }
+        }
+    })
 }
 /*
 Why we need Arc:
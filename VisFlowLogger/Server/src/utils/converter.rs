@@ -1,5 +1,9 @@
-use crate::services::diagram_generator::api::models::block::{DGBlock, DGBlockFlow, DGBlockFlowType};
-use crate::services::graph_generator::api::models::vis_flow::{GGBlock, GGBlockFlow, GGBlockFlowType};
+use crate::services::diagram_generator::api::models::block::{
+    DGBlock, DGBlockChange, DGBlockDuration, DGBlockFlow, DGBlockFlowType, DGBlockKind,
+};
+use crate::services::graph_generator::api::models::vis_flow::{
+    GGBlock, GGBlockChange, GGBlockDuration, GGBlockFlow, GGBlockFlowType, GGBlockKind,
+};
 
 // Convert DGBlockFlowType to GGBlockFlowType
 impl From<DGBlockFlowType> for GGBlockFlowType {
@@ -10,6 +14,34 @@ impl From<DGBlockFlowType> for GGBlockFlowType {
             DGBlockFlowType::Log => GGBlockFlowType::Log,
             DGBlockFlowType::ExternalCall => GGBlockFlowType::ExternalCall,
             DGBlockFlowType::ExternalCallStore => GGBlockFlowType::ExternalCallStore,
+            DGBlockFlowType::Store => GGBlockFlowType::Store,
+            DGBlockFlowType::AsyncCall => GGBlockFlowType::AsyncCall,
+            DGBlockFlowType::Error => GGBlockFlowType::Error,
+            DGBlockFlowType::CallError => GGBlockFlowType::CallError,
+            DGBlockFlowType::Branch => GGBlockFlowType::Branch,
+            DGBlockFlowType::Loop => GGBlockFlowType::Loop,
+            DGBlockFlowType::LoopIteration => GGBlockFlowType::LoopIteration,
+        }
+    }
+}
+
+// Convert DGBlockKind to GGBlockKind
+impl From<DGBlockKind> for GGBlockKind {
+    fn from(kind: DGBlockKind) -> Self {
+        match kind {
+            DGBlockKind::Block => GGBlockKind::Block,
+            DGBlockKind::Loop => GGBlockKind::Loop,
+        }
+    }
+}
+
+// Convert DGBlockChange to GGBlockChange
+impl From<DGBlockChange> for GGBlockChange {
+    fn from(change: DGBlockChange) -> Self {
+        match change {
+            DGBlockChange::Added => GGBlockChange::Added,
+            DGBlockChange::Removed => GGBlockChange::Removed,
+            DGBlockChange::Changed => GGBlockChange::Changed,
         }
     }
 }
@@ -22,6 +54,17 @@ impl From<DGBlockFlow> for GGBlockFlow {
             flow_id: flow.flow_id,
             flow_type: flow.flow_type.into(),
             value: flow.value,
+            sequence: flow.sequence,
+            change: flow.change.map(|c| c.into()),
+        }
+    }
+}
+
+impl From<DGBlockDuration> for GGBlockDuration {
+    fn from(duration: DGBlockDuration) -> Self {
+        GGBlockDuration {
+            inclusive_us: duration.inclusive_us,
+            exclusive_us: duration.exclusive_us,
         }
     }
 }
@@ -33,6 +76,17 @@ impl From<DGBlock> for GGBlock {
             caller: block.caller,
             name: block.name,
             flow: block.flow.into_iter().map(|f| f.into()).collect(),
+            aborted: block.aborted,
+            failed: block.failed,
+            kind: block.kind.into(),
+            span_id: block.span_id,
+            //Diagrams don't keep the times, only what was measured from them. Their calls keep the sequence.
+            sequence: None,
+            runs: block.runs,
+            change: block.change.map(|c| c.into()),
+            started_at: None,
+            ended_at: None,
+            duration: block.duration.map(|d| d.into()),
         }
     }
 }
@@ -46,6 +100,34 @@ impl From<GGBlockFlowType> for DGBlockFlowType {
             GGBlockFlowType::Log => DGBlockFlowType::Log,
             GGBlockFlowType::ExternalCall => DGBlockFlowType::ExternalCall,
             GGBlockFlowType::ExternalCallStore => DGBlockFlowType::ExternalCallStore,
+            GGBlockFlowType::Store => DGBlockFlowType::Store,
+            GGBlockFlowType::AsyncCall => DGBlockFlowType::AsyncCall,
+            GGBlockFlowType::Error => DGBlockFlowType::Error,
+            GGBlockFlowType::CallError => DGBlockFlowType::CallError,
+            GGBlockFlowType::Branch => DGBlockFlowType::Branch,
+            GGBlockFlowType::Loop => DGBlockFlowType::Loop,
+            GGBlockFlowType::LoopIteration => DGBlockFlowType::LoopIteration,
+        }
+    }
+}
+
+// Convert GGBlockKind to DGBlockKind
+impl From<GGBlockKind> for DGBlockKind {
+    fn from(kind: GGBlockKind) -> Self {
+        match kind {
+            GGBlockKind::Block => DGBlockKind::Block,
+            GGBlockKind::Loop => DGBlockKind::Loop,
+        }
+    }
+}
+
+// Convert GGBlockChange to DGBlockChange
+impl From<GGBlockChange> for DGBlockChange {
+    fn from(change: GGBlockChange) -> Self {
+        match change {
+            GGBlockChange::Added => DGBlockChange::Added,
+            GGBlockChange::Removed => DGBlockChange::Removed,
+            GGBlockChange::Changed => DGBlockChange::Changed,
         }
     }
 }
@@ -58,6 +140,17 @@ impl From<GGBlockFlow> for DGBlockFlow {
             flow_id: flow.flow_id,
             flow_type: flow.flow_type.into(),
             value: flow.value,
+            sequence: flow.sequence,
+            change: flow.change.map(|c| c.into()),
+        }
+    }
+}
+
+impl From<GGBlockDuration> for DGBlockDuration {
+    fn from(duration: GGBlockDuration) -> Self {
+        DGBlockDuration {
+            inclusive_us: duration.inclusive_us,
+            exclusive_us: duration.exclusive_us,
         }
     }
 }
@@ -69,6 +162,14 @@ impl From<GGBlock> for DGBlock {
             caller: block.caller,
             name: block.name,
             flow: block.flow.into_iter().map(|f| f.into()).collect(),
+            aborted: block.aborted,
+            failed: block.failed,
+            kind: block.kind.into(),
+            span_id: block.span_id,
+            duration: block.duration.map(|d| d.into()),
+            runs: block.runs,
+            hidden: None,
+            change: block.change.map(|c| c.into()),
         }
     }
 }